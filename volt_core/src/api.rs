@@ -26,6 +26,11 @@ struct ApiRequest {
     // DEX
     pub side: Option<String>,
     pub price: Option<u64>,
+    pub quote: Option<String>, // Quote asset of the pair (token is the base, defaults to VLT)
+    pub limit: Option<usize>, // Max records returned (trades, candles)
+    pub interval: Option<String>, // Candle timeframe: 1m, 5m, 15m, 1h, 4h, 1d
+    pub start_time: Option<u64>, // Unix Seconds (inclusive)
+    pub end_time: Option<u64>,
     // New params for explorer
     start_index: Option<usize>,
     end_index: Option<usize>,
@@ -181,7 +186,7 @@ fn handle_request(
 
     match req.command.as_str() {
         // --- SENSITIVE COMMANDS (Protected) ---
-        "get_address" | "get_mnemonic" | "generate_mnemonic" | "import_mnemonic" | "send_transaction" | "import_wallet" | "encrypt_wallet" | "unlock_wallet" | "lock_wallet" | "stake" | "unstake" | "place_order" | "cancel_order" => {
+        "get_address" | "get_mnemonic" | "generate_mnemonic" | "import_mnemonic" | "send_transaction" | "import_wallet" | "encrypt_wallet" | "unlock_wallet" | "lock_wallet" | "stake" | "unstake" | "delegate" | "undelegate" | "set_commission" | "propose" | "vote" | "report_equivocation" | "mint_asset" | "renounce_mint" | "token_control" | "approve" | "transfer_from" | "list_pair" | "place_order" | "cancel_order" | "get_my_fills" | "swap_route" => {
             // 1. Check IP (Localhost is always trusted)
             let is_local = peer_addr.ip().is_loopback(); 
             
@@ -298,7 +303,98 @@ fn handle_request(
                     "difficulty": chain.difficulty,
                     "last_hash": last_hash,
                     "pending_count": chain.pending_transactions.len(),
-                    "peers": node.peers.lock().unwrap().len()
+                    "peers": node.peers.lock().unwrap().len(),
+                    "last_checkpoint": chain.params.last_checkpoint().map(|(h, _)| h),
+                    "assume_valid": chain.params.assume_valid,
+                    "finalized_height": chain.state.finalized.as_ref().map(|(h, _)| h)
+                }))
+            }
+        },
+        "get_finality" => {
+            let chain = blockchain.lock().unwrap();
+            let checkpoints: Vec<serde_json::Value> = chain.state.finality_checkpoints.values().map(|c| {
+                let (attested, total) = chain.state.attested_stake(c);
+                serde_json::json!({
+                    "height": c.height,
+                    "hash": c.hash,
+                    "attesters": c.attesters.len(),
+                    "attested_stake": attested,
+                    "total_stake": total
+                })
+            }).collect();
+
+            ApiResponse {
+                status: "success".to_string(),
+                message: "Finality retrieved".to_string(),
+                data: Some(serde_json::json!({
+                    "finality_interval": crate::chain::FINALITY_INTERVAL,
+                    "finalized_height": chain.state.finalized.as_ref().map(|(h, _)| h),
+                    "finalized_hash": chain.state.finalized.as_ref().map(|(_, hash)| hash),
+                    "open_checkpoints": checkpoints
+                }))
+            }
+        },
+        "get_token_supply" => {
+            // Optional token (defaults to all issued tokens)
+            let chain = blockchain.lock().unwrap();
+            let mut tokens: Vec<&String> = match &req.token {
+                Some(token) => chain.state.token_supply.keys().filter(|t| *t == token).collect(),
+                None => chain.state.token_supply.keys().filter(|t| *t != "VLT").collect(),
+            };
+            tokens.sort();
+
+            let supplies: Vec<serde_json::Value> = tokens.into_iter().map(|token| {
+                let supply = &chain.state.token_supply[token];
+                serde_json::json!({
+                    "token": token,
+                    "issuer": chain.state.tokens.get(token),
+                    "metadata": chain.state.token_meta.get(token),
+                    "compliance": chain.state.token_compliance.get(token),
+                    "issued": supply.issued,
+                    "burned": supply.burned,
+                    "circulating": supply.circulating(),
+                    "holders": chain.state.holder_count(token)
+                })
+            }).collect();
+
+            if req.token.is_some() && supplies.is_empty() {
+                return ApiResponse { status: "error".to_string(), message: "Token not found".to_string(), data: None };
+            }
+            ApiResponse {
+                status: "success".to_string(),
+                message: "Token supply retrieved".to_string(),
+                data: Some(serde_json::json!({ "tokens": supplies }))
+            }
+        },
+        "get_supply" => {
+            // Optional height (defaults to the tip)
+            let chain = blockchain.lock().unwrap();
+            let height = req.height.map(|h| h as u64).unwrap_or(u64::MAX);
+            ApiResponse {
+                status: "success".to_string(),
+                message: "Supply retrieved".to_string(),
+                data: Some(serde_json::json!(chain.supply_at(height)))
+            }
+        },
+        "audit_supply" => {
+            let chain = blockchain.lock().unwrap();
+            let audit = chain.audit_supply();
+            ApiResponse {
+                status: if audit.ok { "success".to_string() } else { "error".to_string() },
+                message: if audit.ok { "Supply consistent".to_string() } else { format!("Supply mismatch: {}", audit.difference) },
+                data: Some(serde_json::json!(audit))
+            }
+        },
+        "get_deployments" => {
+            let chain = blockchain.lock().unwrap();
+            let height = chain.chain.len() as u64;
+            ApiResponse {
+                status: "success".to_string(),
+                message: "Deployments retrieved".to_string(),
+                data: Some(serde_json::json!({
+                    "height": height,
+                    "next_block_version": chain.compute_block_version(height),
+                    "deployments": chain.get_deployments_info()
                 }))
             }
         },
@@ -340,6 +436,7 @@ fn handle_request(
                  let simplified: Vec<serde_json::Value> = blocks.iter().map(|b| {
                      serde_json::json!({
                          "index": b.index,
+                         "version": b.version,
                          "timestamp": b.timestamp,
                          "transactions": b.transactions.len(),
                          "hash": b.hash,
@@ -365,6 +462,8 @@ fn handle_request(
                  let chain = blockchain.lock().unwrap();
                  let bal = chain.state.get_balance(&addr, "VLT");
                  let staked = *chain.state.stakes.get(&addr).unwrap_or(&0);
+                 let unbonding = chain.state.get_unbonding_total(&addr);
+                 let delegated: u64 = chain.state.delegations.values().filter_map(|d| d.get(&addr)).sum();
                  let nonce = *chain.state.nonces.get(&addr).unwrap_or(&0);
                  
                  ApiResponse {
@@ -373,6 +472,8 @@ fn handle_request(
                      data: Some(serde_json::json!({ 
                          "balance": bal, 
                          "staked": staked,
+                         "unbonding": unbonding,
+                         "delegated": delegated,
                          "nonce": nonce 
                      }))
                  }
@@ -380,6 +481,33 @@ fn handle_request(
                  ApiResponse { status: "error".to_string(), message: "Missing Address".to_string(), data: None }
              }
         },
+        "get_unbonding" => {
+             if let Some(addr) = req.address {
+                 let chain = blockchain.lock().unwrap();
+                 let height = chain.chain.len() as u64 - 1;
+                 let entries: Vec<serde_json::Value> = chain.state.unbonding.get(&addr)
+                     .map(|queue| queue.iter().map(|u| serde_json::json!({
+                         "amount": u.amount,
+                         "release_height": u.release_height,
+                         "recipient": if u.recipient.is_empty() { &addr } else { &u.recipient },
+                         "blocks_remaining": u.release_height.saturating_sub(height)
+                     })).collect())
+                     .unwrap_or_default();
+
+                 ApiResponse {
+                     status: "success".to_string(),
+                     message: "Unbonding retrieved".to_string(),
+                     data: Some(serde_json::json!({
+                         "height": height,
+                         "unbonding_period": crate::chain::UNBONDING_PERIOD,
+                         "total": chain.state.get_unbonding_total(&addr),
+                         "entries": entries
+                     }))
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing Address".to_string(), data: None }
+             }
+        },
         "get_assets" => {
             if let Some(addr) = req.address {
                 let chain = blockchain.lock().unwrap();
@@ -396,7 +524,8 @@ fn handle_request(
                         if tx.receiver == addr || tx.sender == addr {
                             let entry = assets.entry(tx.token.clone()).or_insert(0);
                             if tx.receiver == addr { *entry += tx.amount as i64; }
-                            if tx.sender == addr && tx.tx_type != crate::transaction::TxType::IssueToken { 
+                            // Issue/Mint credit the receiver without debiting the issuer
+                            if tx.sender == addr && tx.tx_type != crate::transaction::TxType::IssueToken && tx.tx_type != crate::transaction::TxType::Mint { 
                                 *entry -= tx.amount as i64; 
                             }
                         }
@@ -409,10 +538,15 @@ fn handle_request(
                     .map(|(k, v)| (k, v as u64))
                     .collect();
 
+                // Token Metadata for display (name, decimals, max supply, logo URI)
+                let metadata: std::collections::HashMap<&String, &crate::chain::TokenMetadata> = filtered_assets.keys()
+                    .filter_map(|t| chain.state.token_meta.get(t).map(|m| (t, m)))
+                    .collect();
+
                 ApiResponse {
                     status: "success".to_string(),
                     message: "Assets retrieved".to_string(),
-                    data: Some(serde_json::json!({ "assets": filtered_assets }))
+                    data: Some(serde_json::json!({ "assets": filtered_assets, "metadata": metadata }))
                 }
             } else {
                  ApiResponse { status: "error".to_string(), message: "Missing address".to_string(), data: None }
@@ -540,9 +674,15 @@ fn handle_request(
                     supply,
                     next_nonce
                 );
+                // Optional Metadata: { name, decimals, max_supply, uri, mintable, freezable, pausable, allowlistable, clawbackable }
+                if let Some(meta) = req.data {
+                    tx.data = meta.to_string();
+                }
                 
                 tx.sign(&wallet.private_key);
-                chain.pending_transactions.push(tx);
+                if !chain.create_transaction(tx) {
+                    return ApiResponse { status: "error".to_string(), message: "Asset Issue Failed (Check Symbol / Metadata)".to_string(), data: None };
+                }
                 chain.save(); 
                 
                  ApiResponse {
@@ -554,6 +694,137 @@ fn handle_request(
                  ApiResponse { status: "error".to_string(), message: "Missing token/supply".to_string(), data: None }
             }
         },
+        "mint_asset" | "renounce_mint" => {
+            // mint_asset: token, amount, to (defaults to self). renounce_mint: token
+            let locked = *is_locked.lock().unwrap();
+             if locked {
+                 return ApiResponse { status: "error".to_string(), message: "WALLET LOCKED".to_string(), data: None };
+             }
+
+            if let Some(token_name) = req.token {
+                let wallet = wallet.lock().unwrap();
+                let mut chain = blockchain.lock().unwrap();
+
+                let sender = wallet.get_address();
+                let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+                let next_nonce = current_nonce + 1;
+
+                let mut tx = if req.command == "mint_asset" {
+                    let Some(amount) = req.amount else {
+                        return ApiResponse { status: "error".to_string(), message: "Missing amount".to_string(), data: None };
+                    };
+                    let receiver = req.to.unwrap_or(sender.clone());
+                    Transaction::new_mint(sender, token_name, receiver, amount, next_nonce)
+                } else {
+                    Transaction::new_renounce_mint(sender, token_name, next_nonce)
+                };
+
+                tx.sign(&wallet.private_key);
+                if chain.create_transaction(tx) {
+                    chain.save();
+                    ApiResponse { status: "success".to_string(), message: "Token Transaction Sent".to_string(), data: None }
+                } else {
+                    ApiResponse { status: "error".to_string(), message: "Rejected (Not issuer / Mint renounced / Max supply)".to_string(), data: None }
+                }
+            } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing token".to_string(), data: None }
+            }
+        },
+        "approve" | "transfer_from" => {
+            // approve: to (spender), token, amount (0 revokes), height (optional expiry height)
+            // transfer_from: address (owner), to (receiver, defaults to self), token, amount
+            let locked = *is_locked.lock().unwrap();
+             if locked {
+                 return ApiResponse { status: "error".to_string(), message: "WALLET LOCKED".to_string(), data: None };
+             }
+
+            let (Some(token_name), Some(amount)) = (req.token, req.amount) else {
+                return ApiResponse { status: "error".to_string(), message: "Missing token or amount".to_string(), data: None };
+            };
+            let wallet = wallet.lock().unwrap();
+            let mut chain = blockchain.lock().unwrap();
+
+            let sender = wallet.get_address();
+            let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+            let next_nonce = current_nonce + 1;
+
+            let mut tx = if req.command == "approve" {
+                let Some(spender) = req.to else {
+                    return ApiResponse { status: "error".to_string(), message: "Missing spender (to)".to_string(), data: None };
+                };
+                let expiry_height = req.height.filter(|h| *h > 0).map(|h| h as u64);
+                Transaction::new_approve(sender, spender, token_name, amount, expiry_height, next_nonce)
+            } else {
+                let Some(owner) = req.address else {
+                    return ApiResponse { status: "error".to_string(), message: "Missing owner (address)".to_string(), data: None };
+                };
+                let receiver = req.to.unwrap_or(sender.clone());
+                Transaction::new_transfer_from(sender, owner, receiver, token_name, amount, next_nonce)
+            };
+
+            tx.sign(&wallet.private_key);
+            let tx_hash = tx.calculate_hash();
+            if chain.create_transaction(tx) {
+                chain.save();
+                ApiResponse { status: "success".to_string(), message: "Allowance Transaction Sent".to_string(), data: Some(serde_json::json!({ "tx_hash": tx_hash })) }
+            } else {
+                ApiResponse { status: "error".to_string(), message: "Rejected (Invalid spender / Allowance exceeded or expired / Insufficient owner balance)".to_string(), data: None }
+            }
+        },
+        "get_allowances" => {
+            // Optional address (matches owner or spender) and token. Expired allowances are reported as inactive.
+            let chain = blockchain.lock().unwrap();
+            let height = chain.chain.len() as u64;
+            let mut allowances: Vec<serde_json::Value> = chain.state.allowances.iter()
+                .filter(|((owner, spender, token), _)| {
+                    req.address.as_ref().is_none_or(|a| a == owner || a == spender)
+                        && req.token.as_ref().is_none_or(|t| t == token)
+                })
+                .map(|((owner, spender, token), allowance)| serde_json::json!({
+                    "owner": owner,
+                    "spender": spender,
+                    "token": token,
+                    "amount": allowance.amount,
+                    "expiry_height": allowance.expiry_height,
+                    "active": allowance.is_active(height)
+                }))
+                .collect();
+            allowances.sort_by_key(|a| (a["owner"].to_string(), a["spender"].to_string(), a["token"].to_string()));
+
+            ApiResponse {
+                status: "success".to_string(),
+                message: "Allowances retrieved".to_string(),
+                data: Some(serde_json::json!({ "height": height, "allowances": allowances }))
+            }
+        },
+        "token_control" => {
+            // Params: token, data ({ "action": "Freeze" | "Unfreeze" | "Pause" | "Unpause" | "SetAllowlistMode" | "Allow" | "Disallow" | "Clawback" | "Renounce", ... })
+            // Renounce drops one control: { "action": "Renounce", "control": "Freeze" | "Pause" | "Allowlist" | "Clawback" }
+            let locked = *is_locked.lock().unwrap();
+             if locked {
+                 return ApiResponse { status: "error".to_string(), message: "WALLET LOCKED".to_string(), data: None };
+             }
+
+            if let (Some(token_name), Some(action)) = (req.token, req.data) {
+                let wallet = wallet.lock().unwrap();
+                let mut chain = blockchain.lock().unwrap();
+
+                let sender = wallet.get_address();
+                let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+                let next_nonce = current_nonce + 1;
+
+                let mut tx = Transaction::new_token_control(sender, token_name, action.to_string(), next_nonce);
+                tx.sign(&wallet.private_key);
+                if chain.create_transaction(tx) {
+                    chain.save();
+                    ApiResponse { status: "success".to_string(), message: "Token Control Transaction Sent".to_string(), data: None }
+                } else {
+                    ApiResponse { status: "error".to_string(), message: "Rejected (Not issuer / No controls / Invalid action)".to_string(), data: None }
+                }
+            } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing token or action".to_string(), data: None }
+            }
+        },
         "burn_asset" => {
             println!("[API] Received burn_asset request.");
             let locked = *is_locked.lock().unwrap();
@@ -668,7 +939,7 @@ fn handle_request(
                  
                  if chain.create_transaction(tx) {
                      chain.save();
-                     ApiResponse { status: "success".to_string(), message: "Unstake Transaction Sent".to_string(), data: None }
+                     ApiResponse { status: "success".to_string(), message: format!("Unstake Transaction Sent (Spendable after {} blocks)", crate::chain::UNBONDING_PERIOD), data: None }
                  } else {
                      ApiResponse { status: "error".to_string(), message: "Unstake Failed (Check Stake)".to_string(), data: None }
                  }
@@ -676,8 +947,281 @@ fn handle_request(
                  ApiResponse { status: "error".to_string(), message: "Missing amount".to_string(), data: None }
              }
         },
+        "delegate" | "undelegate" => {
+             // Params: to (validator), amount
+             if let (Some(validator), Some(amt)) = (req.to, req.amount) {
+                 if amt == 0 {
+                     return ApiResponse { status: "error".to_string(), message: "Amount must be positive".to_string(), data: None };
+                 }
+                 let is_delegate = req.command == "delegate";
+                 println!("[API] {} {} VLT -> {}", if is_delegate { "Delegating" } else { "Undelegating" }, amt, validator);
+                 let wallet = wallet.lock().unwrap();
+                 let mut chain = blockchain.lock().unwrap();
+
+                 let sender = wallet.get_address();
+                 let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+                 let next_nonce = current_nonce + 1;
+
+                 let mut tx = if is_delegate {
+                     Transaction::new_delegate(sender, validator, amt, next_nonce)
+                 } else {
+                     Transaction::new_undelegate(sender, validator, amt, next_nonce)
+                 };
+                 tx.sign(&wallet.private_key);
+
+                 if chain.create_transaction(tx) {
+                     chain.save();
+                     ApiResponse { status: "success".to_string(), message: "Delegation Transaction Sent".to_string(), data: None }
+                 } else {
+                     ApiResponse { status: "error".to_string(), message: "Delegation Failed (Check Balance / Delegation)".to_string(), data: None }
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing validator (to) or amount".to_string(), data: None }
+             }
+        },
+        "set_commission" => {
+             // Params: amount (Basis Points, 100 = 1%)
+             if let Some(bps) = req.amount {
+                 let wallet = wallet.lock().unwrap();
+                 let mut chain = blockchain.lock().unwrap();
+
+                 let sender = wallet.get_address();
+                 let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+                 let next_nonce = current_nonce + 1;
+
+                 let mut tx = Transaction::new_set_commission(sender, bps, next_nonce);
+                 tx.sign(&wallet.private_key);
+
+                 if chain.create_transaction(tx) {
+                     chain.save();
+                     ApiResponse { status: "success".to_string(), message: "Commission Transaction Sent".to_string(), data: None }
+                 } else {
+                     ApiResponse { status: "error".to_string(), message: format!("Invalid Commission (Max {} bps)", crate::chain::MAX_COMMISSION_BPS), data: None }
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing amount".to_string(), data: None }
+             }
+        },
+        "get_validators" => {
+             let chain = blockchain.lock().unwrap();
+             let mut addresses: Vec<&String> = chain.state.stakes.keys().chain(chain.state.delegations.keys()).collect();
+             addresses.sort();
+             addresses.dedup();
+
+             let mut validators: Vec<serde_json::Value> = addresses.into_iter().map(|addr| serde_json::json!({
+                 "address": addr,
+                 "own_stake": *chain.state.stakes.get(addr).unwrap_or(&0),
+                 "delegated": chain.state.get_delegated_total(addr),
+                 "total_stake": chain.state.get_validator_stake(addr),
+                 "commission_bps": *chain.state.commissions.get(addr).unwrap_or(&0),
+                 "delegators": chain.state.delegations.get(addr).map(|d| d.len()).unwrap_or(0)
+             })).collect();
+             validators.sort_by_key(|v| std::cmp::Reverse(v["total_stake"].as_u64().unwrap_or(0)));
+
+             ApiResponse {
+                 status: "success".to_string(),
+                 message: "Validators retrieved".to_string(),
+                 data: Some(serde_json::json!({ "validators": validators }))
+             }
+        },
+        "get_delegations" => {
+             // Delegations made by `address`
+             if let Some(addr) = req.address {
+                 let chain = blockchain.lock().unwrap();
+                 let delegations: Vec<serde_json::Value> = chain.state.delegations.iter()
+                     .filter_map(|(validator, delegators)| delegators.get(&addr).map(|amount| serde_json::json!({
+                         "validator": validator,
+                         "amount": amount,
+                         "commission_bps": *chain.state.commissions.get(validator).unwrap_or(&0)
+                     })))
+                     .collect();
+
+                 ApiResponse {
+                     status: "success".to_string(),
+                     message: "Delegations retrieved".to_string(),
+                     data: Some(serde_json::json!({ "delegations": delegations }))
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing Address".to_string(), data: None }
+             }
+        },
+        "propose" => {
+             // Params: data (ProposalContent { title, description, kind: { type: "Text" } | { type: "ParamChange", param, value } | { type: "FeeRecipient", address } })
+             if let Some(content) = req.data {
+                 let wallet = wallet.lock().unwrap();
+                 let mut chain = blockchain.lock().unwrap();
+
+                 let sender = wallet.get_address();
+                 let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+                 let next_nonce = current_nonce + 1;
+
+                 let mut tx = Transaction::new_proposal(sender, content.to_string(), next_nonce);
+                 tx.sign(&wallet.private_key);
+
+                 if chain.create_transaction(tx) {
+                     chain.save();
+                     ApiResponse { status: "success".to_string(), message: "Proposal Transaction Sent".to_string(), data: None }
+                 } else {
+                     ApiResponse { status: "error".to_string(), message: "Proposal Failed (Check Content / Stake)".to_string(), data: None }
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing proposal data".to_string(), data: None }
+             }
+        },
+        "vote" => {
+             // Params: amount (Proposal ID), side (YES / NO / ABSTAIN)
+             if let (Some(proposal_id), Some(side)) = (req.amount, req.side) {
+                 let option = match side.to_uppercase().as_str() {
+                     "YES" => crate::governance::VoteOption::Yes,
+                     "NO" => crate::governance::VoteOption::No,
+                     "ABSTAIN" => crate::governance::VoteOption::Abstain,
+                     _ => return ApiResponse { status: "error".to_string(), message: "Invalid vote (YES / NO / ABSTAIN)".to_string(), data: None },
+                 };
+                 let wallet = wallet.lock().unwrap();
+                 let mut chain = blockchain.lock().unwrap();
+
+                 let sender = wallet.get_address();
+                 let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+                 let next_nonce = current_nonce + 1;
+
+                 let mut tx = Transaction::new_vote(sender, proposal_id, option.receiver(), next_nonce);
+                 tx.sign(&wallet.private_key);
+
+                 if chain.create_transaction(tx) {
+                     chain.save();
+                     ApiResponse { status: "success".to_string(), message: "Vote Transaction Sent".to_string(), data: None }
+                 } else {
+                     ApiResponse { status: "error".to_string(), message: "Vote Failed (Check Proposal / Stake)".to_string(), data: None }
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing proposal id (amount) or side".to_string(), data: None }
+             }
+        },
+        "get_proposals" | "get_proposal" => {
+             // get_proposal: amount = Proposal ID (includes individual votes)
+             let chain = blockchain.lock().unwrap();
+             let with_votes = req.command == "get_proposal";
+             let proposals: Vec<serde_json::Value> = chain.state.proposals.values().rev()
+                 .filter(|p| !with_votes || Some(p.id) == req.amount)
+                 .map(|p| {
+                     // Closed proposals report the final tally, open ones a live tally
+                     let tally = p.tally.clone().unwrap_or_else(|| chain.state.tally_proposal(p));
+                     let mut entry = serde_json::json!({
+                         "id": p.id,
+                         "proposer": p.proposer,
+                         "title": p.title,
+                         "description": p.description,
+                         "kind": p.kind,
+                         "status": p.status,
+                         "submit_height": p.submit_height,
+                         "end_height": p.end_height,
+                         "activation_height": p.activation_height,
+                         "vote_count": p.votes.len(),
+                         "tally": tally,
+                         "quorum_reached": tally.quorum_reached(),
+                         "passing": tally.passed()
+                     });
+                     if with_votes {
+                         entry["votes"] = serde_json::json!(p.votes);
+                         entry["weights"] = serde_json::json!(p.weights);
+                     }
+                     entry
+                 })
+                 .collect();
+
+             if with_votes && proposals.is_empty() {
+                 return ApiResponse { status: "error".to_string(), message: "Proposal not found".to_string(), data: None };
+             }
+             ApiResponse {
+                 status: "success".to_string(),
+                 message: "Proposals retrieved".to_string(),
+                 data: Some(serde_json::json!({ "proposals": proposals }))
+             }
+        },
+        "get_gov_params" => {
+             let chain = blockchain.lock().unwrap();
+             ApiResponse {
+                 status: "success".to_string(),
+                 message: "Governance parameters retrieved".to_string(),
+                 data: Some(serde_json::json!({
+                     "params": chain.state.gov_params,
+                     "voting_period": crate::governance::VOTING_PERIOD,
+                     "activation_delay": crate::governance::ACTIVATION_DELAY,
+                     "quorum_percent": crate::governance::QUORUM_PERCENT,
+                     "pass_threshold_percent": crate::governance::PASS_THRESHOLD_PERCENT,
+                     "min_proposer_stake": crate::governance::MIN_PROPOSER_STAKE
+                 }))
+             }
+        },
+        "report_equivocation" => {
+             // Params: address (offender), data (EquivocationEvidence { header_a, header_b })
+             // or hash (our block) + data (the conflicting header seen for the same parent)
+             if let (Some(offender), Some(mut evidence)) = (req.address, req.data) {
+                 let wallet = wallet.lock().unwrap();
+                 let mut chain = blockchain.lock().unwrap();
+
+                 if let Some(hash) = req.hash {
+                     match chain.chain.iter().find(|b| b.hash == hash) {
+                         Some(block) => evidence = serde_json::json!({ "header_a": block.header(), "header_b": evidence }),
+                         None => return ApiResponse { status: "error".to_string(), message: "Block not found".to_string(), data: None },
+                     }
+                 }
+
+                 let sender = wallet.get_address();
+                 let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
+                 let next_nonce = current_nonce + 1;
+
+                 let mut tx = Transaction::new_slash(sender, offender, evidence.to_string(), next_nonce);
+                 tx.sign(&wallet.private_key);
+
+                 if chain.create_transaction(tx) {
+                     chain.save();
+                     ApiResponse { status: "success".to_string(), message: "Slashing Transaction Sent".to_string(), data: None }
+                 } else {
+                     ApiResponse { status: "error".to_string(), message: "Slashing Failed (Check Evidence)".to_string(), data: None }
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing address or evidence".to_string(), data: None }
+             }
+        },
+        "list_pair" => {
+             // Params: token (base), quote, data ({ tick_size, lot_size })
+             if let (Some(base), Some(quote)) = (req.token, req.quote) {
+                 let sizes = req.data.unwrap_or_default();
+                 let mut pair = crate::dex::TradingPair::new(&base, &quote);
+                 if let Some(tick) = sizes.get("tick_size").and_then(|v| v.as_u64()) { pair.tick_size = tick; }
+                 if let Some(lot) = sizes.get("lot_size").and_then(|v| v.as_u64()) { pair.lot_size = lot; }
+
+                 let wallet = wallet.lock().unwrap();
+                 let mut chain = blockchain.lock().unwrap();
+                 let sender = wallet.get_address();
+                 let next_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0) + 1;
+
+                 let mut tx = Transaction::new_list_pair(sender, &pair, next_nonce);
+                 tx.sign(&wallet.private_key);
+                 if chain.create_transaction(tx) {
+                     chain.save();
+                     ApiResponse { status: "success".to_string(), message: format!("Pair {} Listing Sent", pair.id()), data: None }
+                 } else {
+                     ApiResponse { status: "error".to_string(), message: "Rejected (Unknown asset / Already listed / Invalid sizes)".to_string(), data: None }
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing token (base) or quote".to_string(), data: None }
+             }
+        },
+        "get_pairs" => {
+            let chain = blockchain.lock().unwrap();
+            let pairs: Vec<&crate::dex::TradingPair> = chain.state.pairs.values().collect();
+            ApiResponse {
+                status: "success".to_string(),
+                message: "Pairs retrieved".to_string(),
+                data: Some(serde_json::json!({ "pairs": pairs, "default_quote": crate::dex::DEFAULT_QUOTE }))
+            }
+        },
         "place_order" => {
-             // Params: token, side (BUY/SELL), price, amount
+             // Params: token (base), quote (optional, defaults to VLT), side (BUY/SELL), price, amount
+             // data (optional): { order_type: Limit|Market|ImmediateOrCancel|FillOrKill|PostOnly, max_slippage_bps, expiry_height, expiry_time }
+             // Market orders use price as the reference price (signed in data)
              if let (Some(token), Some(side), Some(price), Some(amount)) = 
                 (req.token, req.side, req.price, req.amount) {
                  
@@ -688,14 +1232,28 @@ fn handle_request(
                  let current_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0);
                  let next_nonce = current_nonce + 1;
                  
-                 let mut tx = Transaction::new_order(sender, token, &side, amount, price, next_nonce);
+                 let mut params = match req.data {
+                     Some(options) => match serde_json::from_value::<crate::dex::OrderParams>(options) {
+                         Ok(p) => p,
+                         Err(_) => return ApiResponse { status: "error".to_string(), message: "Invalid order options".to_string(), data: None },
+                     },
+                     None => crate::dex::OrderParams::default(),
+                 };
+                 if let Some(quote) = req.quote {
+                     params.quote = quote;
+                 }
+                 if params.order_type == crate::dex::OrderType::Market && params.reference_price.is_none() {
+                     params.reference_price = Some(price);
+                 }
+                 let mut tx = Transaction::new_pair_order(sender, token, &params, &side, amount, price, next_nonce);
                  tx.sign(&wallet.private_key);
+                 let order_id = hex::encode(tx.get_hash()); // Order ID = PlaceOrder tx hash
                  
                  if chain.create_transaction(tx) {
                      chain.save();
-                     ApiResponse { status: "success".to_string(), message: "Order Placed".to_string(), data: None }
+                     ApiResponse { status: "success".to_string(), message: "Order Placed".to_string(), data: Some(serde_json::json!({ "order_id": order_id })) }
                  } else {
-                     ApiResponse { status: "error".to_string(), message: "Place Order Failed (Funds?)".to_string(), data: None }
+                     ApiResponse { status: "error".to_string(), message: "Place Order Failed (Funds / Unknown pair / Tick or lot size / Expired / Post-only cross / FOK unfillable)".to_string(), data: None }
                  }
              } else {
                  ApiResponse { status: "error".to_string(), message: "Missing DEX params".to_string(), data: None }
@@ -713,18 +1271,24 @@ fn handle_request(
                  let mut tx = Transaction::new_cancel(sender, id, next_nonce);
                  tx.sign(&wallet.private_key);
                  
-                 // Check if order exists before sending tx? No, chain validation handles it.
-                 chain.pending_transactions.push(tx); // bypass strict create_transaction for now or use it if updated
-                 chain.save();
-                 
-                 ApiResponse { status: "success".to_string(), message: "Cancel Request Sent".to_string(), data: None }
+                 if chain.create_transaction(tx) {
+                     chain.save();
+                     ApiResponse { status: "success".to_string(), message: "Cancel Request Sent".to_string(), data: None }
+                 } else {
+                     ApiResponse { status: "error".to_string(), message: "Cancel Failed (Unknown order / Not yours)".to_string(), data: None }
+                 }
              } else {
                   ApiResponse { status: "error".to_string(), message: "Missing Order ID".to_string(), data: None }
              }
         },
         "get_orders" => {
+            // Optional token (base) and quote: orders of one pair (quote defaults to VLT when token is given)
             let chain = blockchain.lock().unwrap();
-            let orders: Vec<crate::chain::Order> = chain.state.orders.values().cloned().collect();
+            let pair = req.token.as_ref().map(|base| crate::dex::pair_id(base, req.quote.as_deref().unwrap_or(crate::dex::DEFAULT_QUOTE)));
+            let orders: Vec<crate::chain::Order> = chain.state.orders.values()
+                .filter(|o| pair.as_ref().is_none_or(|p| &o.pair == p))
+                .cloned()
+                .collect();
             ApiResponse {
                 status: "success".to_string(),
                 message: "Orderbook retrieved".to_string(),
@@ -918,6 +1482,77 @@ fn handle_request(
                  ApiResponse { status: "error".to_string(), message: "Missing address".to_string(), data: None }
              }
         },
+        "quote_swap" => {
+            // Params: token (input), quote (output), amount, data (optional { exact_output: true }).
+            // Read-only best route across all pools; in exact-output mode amount is the output
+            // and the quote's amount_in is the input needed for it.
+            if let (Some(token_in), Some(token_out), Some(amount)) = (req.token, req.quote, req.amount) {
+                let exact_output = req.data.as_ref().and_then(|d| d.get("exact_output")).and_then(|v| v.as_bool()).unwrap_or(false);
+                let chain = blockchain.lock().unwrap();
+                match chain.state.best_route(&token_in, &token_out, amount, exact_output) {
+                    Some(quote) => ApiResponse {
+                        status: "success".to_string(),
+                        message: "Route found".to_string(),
+                        data: Some(serde_json::json!(quote))
+                    },
+                    None => ApiResponse { status: "error".to_string(), message: format!("No route from {} to {}", token_in, token_out), data: None },
+                }
+            } else {
+                ApiResponse { status: "error".to_string(), message: "Missing token, quote or amount".to_string(), data: None }
+            }
+        },
+        "swap_route" => {
+            // Params: token (input), quote (output), amount, price (minimum output / maximum input),
+            // data (optional { path, exact_output, deadline_height, deadline_time }, path defaults to the best route)
+            let locked = *is_locked.lock().unwrap();
+            if locked {
+                return ApiResponse { status: "error".to_string(), message: "WALLET LOCKED".to_string(), data: None };
+            }
+            if let (Some(token_in), Some(token_out), Some(amount), Some(limit)) = (req.token, req.quote, req.amount, req.price) {
+                let wallet = wallet.lock().unwrap();
+                let mut chain = blockchain.lock().unwrap();
+
+                let mut params = match req.data {
+                    Some(d) => match serde_json::from_value::<crate::dex::SwapParams>(d) {
+                        Ok(p) => p,
+                        Err(_) => return ApiResponse { status: "error".to_string(), message: "Malformed Swap Parameters".to_string(), data: None },
+                    },
+                    None => crate::dex::SwapParams::default(),
+                };
+                let quote = if params.path.is_empty() {
+                    chain.state.best_route(&token_in, &token_out, amount, params.exact_output)
+                } else {
+                    let simulated = if params.exact_output {
+                        chain.state.simulate_path_exact_out(&params.path, amount)
+                    } else {
+                        chain.state.simulate_path(&params.path, amount).map(|hops| (amount, hops))
+                    };
+                    simulated.ok().map(|(input, hops)| chain.state.quote_route(&params.path, input, &hops))
+                };
+                let quote = match quote {
+                    Some(q) => q,
+                    None => return ApiResponse { status: "error".to_string(), message: format!("No route from {} to {}", token_in, token_out), data: None },
+                };
+                params.path = quote.path.clone();
+                let path = params.path.clone();
+                if path.last() != Some(&token_out) {
+                    return ApiResponse { status: "error".to_string(), message: "Path does not end with the output token".to_string(), data: None };
+                }
+
+                let sender = wallet.get_address();
+                let next_nonce = *chain.state.nonces.get(&sender).unwrap_or(&0) + 1;
+                let mut tx = Transaction::new_route_swap(sender, &params, amount, limit, next_nonce);
+                tx.sign(&wallet.private_key);
+                if chain.create_transaction(tx) {
+                    chain.save();
+                    ApiResponse { status: "success".to_string(), message: "Swap Sent".to_string(), data: Some(serde_json::json!({ "path": path, "quote": quote })) }
+                } else {
+                    ApiResponse { status: "error".to_string(), message: "Swap Rejected (Funds / Slippage / Path)".to_string(), data: None }
+                }
+            } else {
+                ApiResponse { status: "error".to_string(), message: "Missing token, quote, amount or price (min output / max input)".to_string(), data: None }
+            }
+        },
         "get_pools" => {
             let chain = blockchain.lock().unwrap();
             let pools: Vec<crate::chain::Pool> = chain.state.pools.values().cloned().collect();
@@ -927,14 +1562,124 @@ fn handle_request(
                 data: Some(serde_json::json!({ "pools": pools })) 
             }
         },
+        "get_twap" => {
+            // Params: token (pool ID "A/B"), start_time, end_time (default: last block time).
+            // price_a = token_b per token_a * 10^8, price_b = token_a per token_b * 10^8
+            if let (Some(pool_id), Some(start)) = (req.token, req.start_time) {
+                let chain = blockchain.lock().unwrap();
+                let end = req.end_time.unwrap_or(chain.state.time);
+                match chain.state.twap(&pool_id, start, end) {
+                    Ok(twap) => ApiResponse {
+                        status: "success".to_string(),
+                        message: "TWAP computed".to_string(),
+                        data: Some(serde_json::json!(twap))
+                    },
+                    Err(e) => ApiResponse { status: "error".to_string(), message: e, data: None },
+                }
+            } else {
+                ApiResponse { status: "error".to_string(), message: "Missing token (pool) or start_time".to_string(), data: None }
+            }
+        },
+        "get_orderbook" => {
+             // Params: token (base), quote (optional, defaults to VLT), limit (depth per side, default 20)
+             if let Some(base) = req.token {
+                 let chain = blockchain.lock().unwrap();
+                 let pair = crate::dex::pair_id(&base, req.quote.as_deref().unwrap_or(crate::dex::DEFAULT_QUOTE));
+                 let depth = req.limit.unwrap_or(20).clamp(1, 500);
+                 ApiResponse {
+                     status: "success".to_string(),
+                     message: "Orderbook retrieved".to_string(),
+                     data: Some(serde_json::json!(chain.state.order_book(&pair, depth)))
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing Pair".to_string(), data: None }
+             }
+        },
+        "get_ticker" => {
+             // Params: token (base), quote (optional, defaults to VLT). 24h window ends now.
+             if let Some(base) = req.token {
+                 let chain = blockchain.lock().unwrap();
+                 let pair = crate::dex::pair_id(&base, req.quote.as_deref().unwrap_or(crate::dex::DEFAULT_QUOTE));
+                 let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+                 let (last_price, trades) = match chain.db.as_ref() {
+                     Some(db) => (
+                         db.get_trades_by_pair(&pair, 1).first().map(|t| t.price),
+                         db.get_trades_by_pair_since(&pair, now.saturating_sub(86_400)),
+                     ),
+                     None => (None, Vec::new()),
+                 };
+                 let book = chain.state.order_book(&pair, 1);
+                 let mut ticker = crate::dex::Ticker::from_trades(&pair, last_price, &trades);
+                 ticker.best_bid = book.best_bid;
+                 ticker.best_ask = book.best_ask;
+                 ApiResponse {
+                     status: "success".to_string(),
+                     message: "Ticker retrieved".to_string(),
+                     data: Some(serde_json::json!(ticker))
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing Pair".to_string(), data: None }
+             }
+        },
+        "get_trades" => {
+             // Params: token (base), quote (optional, defaults to VLT), limit (optional). Newest first.
+             if let Some(base) = req.token {
+                 let chain = blockchain.lock().unwrap();
+                 let pair = crate::dex::pair_id(&base, req.quote.as_deref().unwrap_or(crate::dex::DEFAULT_QUOTE));
+                 let limit = req.limit.unwrap_or(100).min(1_000);
+                 let trades = chain.db.as_ref().map(|db| db.get_trades_by_pair(&pair, limit)).unwrap_or_default();
+                 ApiResponse {
+                     status: "success".to_string(),
+                     message: "Trades retrieved".to_string(),
+                     data: Some(serde_json::json!({ "pair": pair, "trades": trades }))
+                 }
+             } else {
+                 ApiResponse { status: "error".to_string(), message: "Missing Pair".to_string(), data: None }
+             }
+        },
+        "get_my_fills" => {
+             // Fills of the wallet address as maker or taker. Optional token (base) + quote filter, limit. Newest first.
+             let address = wallet.lock().unwrap().get_address();
+             let chain = blockchain.lock().unwrap();
+             let pair = req.token.as_ref().map(|base| crate::dex::pair_id(base, req.quote.as_deref().unwrap_or(crate::dex::DEFAULT_QUOTE)));
+             let limit = req.limit.unwrap_or(100).min(1_000);
+             let fills: Vec<serde_json::Value> = chain.db.as_ref()
+                 .map(|db| db.get_trades_by_address(&address, usize::MAX))
+                 .unwrap_or_default()
+                 .into_iter()
+                 .filter(|t| pair.as_ref().is_none_or(|p| &t.pair == p))
+                 .take(limit)
+                 .map(|t| {
+                     let role = if t.taker == address { "taker" } else { "maker" };
+                     let mut fill = serde_json::json!(t);
+                     fill["role"] = serde_json::json!(role);
+                     fill
+                 })
+                 .collect();
+             ApiResponse {
+                 status: "success".to_string(),
+                 message: "Fills retrieved".to_string(),
+                 data: Some(serde_json::json!({ "address": address, "fills": fills }))
+             }
+        },
         "get_candles" => {
-             if let Some(pair) = req.token {
+             // Params: token (base), quote (optional, defaults to VLT), interval (default 1m), start_time, end_time, limit
+             if let Some(base) = req.token {
+                 let interval = req.interval.unwrap_or("1m".to_string());
+                 if crate::dex::candle_interval(&interval).is_none() {
+                     let labels: Vec<&str> = crate::dex::CANDLE_INTERVALS.iter().map(|(l, _)| *l).collect();
+                     return ApiResponse { status: "error".to_string(), message: format!("Invalid interval (use {})", labels.join(", ")), data: None };
+                 }
                  let chain = blockchain.lock().unwrap();
-                 let candles = chain.state.candles.get(&pair).cloned().unwrap_or(Vec::new());
+                 let pair = crate::dex::pair_id(&base, req.quote.as_deref().unwrap_or(crate::dex::DEFAULT_QUOTE));
+                 let start = req.start_time.unwrap_or(0);
+                 let end = req.end_time.unwrap_or(u64::MAX);
+                 let limit = req.limit.unwrap_or(500).min(1_500);
+                 let candles = chain.db.as_ref().map(|db| db.get_candles(&pair, &interval, start, end, limit)).unwrap_or_default();
                  ApiResponse {
                      status: "success".to_string(),
                      message: "Candles retrieved".to_string(),
-                     data: Some(serde_json::json!({ "candles": candles }))
+                     data: Some(serde_json::json!({ "pair": pair, "interval": interval, "candles": candles }))
                  }
              } else {
                  ApiResponse { status: "error".to_string(), message: "Missing Pair".to_string(), data: None }
@@ -2,9 +2,15 @@ use serde::{Serialize, Deserialize};
 use sha2::{Sha256, Digest};
 use chrono::Utc;
 use crate::transaction::Transaction;
+use k256::ecdsa::{SigningKey, VerifyingKey, Signature, signature::Signer};
+use k256::ecdsa::signature::Verifier;
+
+fn default_version() -> u32 { 1 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Block {
+    #[serde(default = "default_version")]
+    pub version: u32, // Header Version (BIP9 Version Bits)
     pub index: u64,
     pub timestamp: u64, // Changed to u64 (Unix Seconds) for Bitcoin compatibility standards
     pub proof_of_work: u32, // Nonce (4 bytes)
@@ -14,6 +20,76 @@ pub struct Block {
     pub difficulty: u32, // Bits (4 bytes)
     pub merkle_root: String,
     pub validator_stake: u64, // Hybrid Consensus: Staked amount Claim
+    #[serde(default)]
+    pub validator_signature: String, // Staking key (Coinbase receiver) signature over Hash + Stake Claim
+}
+
+/// Header fields needed to re-derive a block hash and check its validator signature
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub index: u64,
+    pub timestamp: u64,
+    pub proof_of_work: u32,
+    pub previous_hash: String,
+    pub merkle_root: String,
+    pub difficulty: u32,
+    pub hash: String,
+    pub validator_stake: u64,
+    pub validator_signature: String,
+}
+
+impl BlockHeader {
+    // Body-less block (hashing and validator signature only depend on the header)
+    fn to_block(&self) -> Block {
+        Block {
+            version: self.version,
+            index: self.index,
+            timestamp: self.timestamp,
+            proof_of_work: self.proof_of_work,
+            previous_hash: self.previous_hash.clone(),
+            hash: self.hash.clone(),
+            transactions: Vec::new(),
+            difficulty: self.difficulty,
+            merkle_root: self.merkle_root.clone(),
+            validator_stake: self.validator_stake,
+            validator_signature: self.validator_signature.clone(),
+        }
+    }
+}
+
+/// Slashing Evidence: one staker signed stake claims for two different blocks on the same parent
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EquivocationEvidence {
+    pub header_a: BlockHeader,
+    pub header_b: BlockHeader,
+}
+
+impl EquivocationEvidence {
+    // Height both headers claim. An offender is punished once per height, however many headers it signed there.
+    pub fn height(&self) -> u64 {
+        self.header_a.index
+    }
+
+    pub fn verify(&self, offender: &str) -> Result<(), String> {
+        if self.header_a.hash == self.header_b.hash {
+            return Err("Headers are identical".to_string());
+        }
+        if self.header_a.previous_hash != self.header_b.previous_hash || self.header_a.index != self.header_b.index {
+            return Err("Headers are not at the same height (different parents)".to_string());
+        }
+        for header in [&self.header_a, &self.header_b] {
+            if header.validator_stake == 0 {
+                return Err(format!("Header {} claims no stake", header.hash));
+            }
+            let block = header.to_block();
+            if block.calculate_hash() != header.hash {
+                return Err(format!("Header {} hash mismatch", header.hash));
+            }
+            block.verify_validator_signature_by(offender)?;
+        }
+        Ok(())
+    }
 }
 
 impl Block {
@@ -24,6 +100,7 @@ impl Block {
         let merkle_root = Block::calculate_merkle_root(&transactions);
 
         let mut block = Block {
+            version: default_version(),
             index,
             timestamp,
             proof_of_work,
@@ -33,6 +110,7 @@ impl Block {
             difficulty: difficulty as u32,
             merkle_root,
             validator_stake,
+            validator_signature: String::new(),
         };
         block.hash = block.calculate_hash();
         block
@@ -65,14 +143,21 @@ impl Block {
         hex::encode(&hashes[0])
     }
 
+    pub fn serialized_size(&self) -> usize {
+        serde_json::to_vec(self).map(|b| b.len()).unwrap_or(usize::MAX)
+    }
+
+    pub fn sig_op_count(&self) -> u64 {
+        self.transactions.iter().map(|tx| tx.sig_op_count()).sum()
+    }
+
     pub fn calculate_hash(&self) -> String {
         // Bitcoin Header Format (80 bytes)
         // Version (4) + PrevBlock (32) + MerkleRoot (32) + Timestamp (4) + Bits (4) + Nonce (4)
         
-        let version: u32 = 1;
         let mut bytes = Vec::new();
         
-        bytes.extend(&version.to_le_bytes()); // 4
+        bytes.extend(&self.version.to_le_bytes()); // 4
         
         // PrevHash (32 bytes) - handle genesis "0"
         let prev_hash_bytes = if self.previous_hash == "0" {
@@ -121,6 +206,49 @@ impl Block {
         hex::encode(res2)
     }
 
+    // Message signed by the validator: binds the stake claim to this exact header
+    fn validator_message(&self) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.hash.as_bytes());
+        hasher.update(self.validator_stake.to_le_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    // Call after PoW is found (the final hash is part of the signed message)
+    pub fn sign_validator(&mut self, key: &SigningKey) {
+        let signature: Signature = key.sign(&self.validator_message());
+        self.validator_signature = hex::encode(signature.to_der());
+    }
+
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            version: self.version,
+            index: self.index,
+            timestamp: self.timestamp,
+            proof_of_work: self.proof_of_work,
+            previous_hash: self.previous_hash.clone(),
+            merkle_root: self.merkle_root.clone(),
+            difficulty: self.difficulty,
+            hash: self.hash.clone(),
+            validator_stake: self.validator_stake,
+            validator_signature: self.validator_signature.clone(),
+        }
+    }
+
+    // The stake claim must be signed by the Coinbase receiver (the staker claiming the bonus)
+    pub fn verify_validator_signature(&self) -> Result<(), String> {
+        let coinbase = self.transactions.first().ok_or("Missing Coinbase")?;
+        self.verify_validator_signature_by(&coinbase.receiver)
+    }
+
+    pub fn verify_validator_signature_by(&self, validator: &str) -> Result<(), String> {
+        let pub_key_bytes = hex::decode(validator).map_err(|_| "Invalid validator address hex".to_string())?;
+        let public_key = VerifyingKey::from_sec1_bytes(&pub_key_bytes).map_err(|_| "Invalid validator public key".to_string())?;
+        let sig_bytes = hex::decode(&self.validator_signature).map_err(|_| "Invalid validator signature hex".to_string())?;
+        let signature = Signature::from_der(&sig_bytes).map_err(|_| "Invalid validator signature encoding".to_string())?;
+        public_key.verify(&self.validator_message(), &signature).map_err(|_| "Validator signature mismatch".to_string())
+    }
+
     pub fn mine(&mut self, difficulty: usize) {
         // Hybrid Consensus: Apply Bonus Locally
         let bonus = (self.validator_stake / 10_000_000_000) as u32; 
@@ -140,3 +268,56 @@ impl Block {
         println!("Block mined: {}", self.hash);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staker(seed: u8) -> (SigningKey, String) {
+        let key = SigningKey::from_slice(&[seed; 32]).unwrap();
+        let address = hex::encode(key.verifying_key().to_sec1_bytes());
+        (key, address)
+    }
+
+    // Signed stake claim on `parent` (the timestamp tells conflicting headers apart)
+    fn claim(key: &SigningKey, parent: &str, index: u64, stake: u64, timestamp: u64) -> BlockHeader {
+        let mut block = Block::new(index, parent.to_string(), Vec::new(), 0x207fffff, stake);
+        block.timestamp = timestamp;
+        block.hash = block.calculate_hash();
+        block.sign_validator(key);
+        block.header()
+    }
+
+    #[test]
+    fn evidence_needs_two_signed_claims_on_one_parent() {
+        let (key, offender) = staker(3);
+        let (other_key, other) = staker(4);
+        let a = claim(&key, "parent", 7, 500, 1_000);
+        let b = claim(&key, "parent", 7, 500, 1_001);
+        let evidence = EquivocationEvidence { header_a: a.clone(), header_b: b.clone() };
+        assert_eq!(evidence.verify(&offender), Ok(()));
+        assert_eq!(evidence.height(), 7);
+        assert!(evidence.verify(&other).is_err()); // Signed by someone else
+
+        let same = EquivocationEvidence { header_a: a.clone(), header_b: a.clone() };
+        assert_eq!(same.verify(&offender), Err("Headers are identical".to_string()));
+        let forks = EquivocationEvidence { header_a: a.clone(), header_b: claim(&key, "other-parent", 7, 500, 1_001) };
+        assert!(forks.verify(&offender).is_err());
+        let heights = EquivocationEvidence { header_a: a.clone(), header_b: claim(&key, "parent", 8, 500, 1_001) };
+        assert!(heights.verify(&offender).is_err());
+        let unstaked = EquivocationEvidence { header_a: a.clone(), header_b: claim(&key, "parent", 7, 0, 1_001) };
+        assert!(unstaked.verify(&offender).is_err());
+        let mixed = EquivocationEvidence { header_a: a.clone(), header_b: claim(&other_key, "parent", 7, 500, 1_001) };
+        assert!(mixed.verify(&offender).is_err());
+
+        // Headers must hash to what was signed
+        let mut edited = b;
+        edited.validator_stake = 499;
+        assert!(EquivocationEvidence { header_a: a.clone(), header_b: edited.clone() }.verify(&offender).is_err());
+        edited.hash = edited.to_block().calculate_hash();
+        assert_eq!(
+            EquivocationEvidence { header_a: a, header_b: edited }.verify(&offender),
+            Err("Validator signature mismatch".to_string())
+        );
+    }
+}
@@ -1,19 +1,66 @@
 #![allow(dead_code)]
-use crate::block::Block;
+use crate::block::{Block, EquivocationEvidence};
 use crate::transaction::{Transaction, TxType};
 use crate::db::Database;
-use crate::script::VirtualMachine;
-use std::collections::{HashMap, BTreeMap, HashSet};
+use crate::script::{PriceOracle, VirtualMachine};
+use crate::params::{ChainParams, Deployment, DeploymentState, VERSIONBITS_TOP_BITS};
+use crate::sigcache::SignatureCache;
+use crate::dex::{fee_for, pair_id, OrderBookDepth, OrderLock, OrderParams, OrderType, PriceLevel, PriceObservation, RouteQuote, SwapParams, SwapRecord, Trade, TradingPair, Twap, DEFAULT_QUOTE, MAX_PRICE_OBSERVATIONS, MAX_SWAP_HOPS, PRICE_SCALE};
+use crate::governance::{GovParams, Proposal, ProposalContent, ProposalKind, ProposalStatus, Tally, VoteOption, ACTIVATION_DELAY, MIN_PROPOSER_STAKE, VOTING_PERIOD};
+use k256::ecdsa::SigningKey;
+use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, BTreeMap, BTreeSet, HashSet, VecDeque};
+
+// Consensus: Block Limits (Serialized JSON bytes / Signature Operations)
+pub const MAX_BLOCK_SIZE: usize = 4_000_000;
+pub const MAX_BLOCK_SIGOPS: u64 = 20_000;
+
+// Policy: Mempool Admission Limits (per Transaction)
+pub const MAX_TX_SIZE: usize = 100_000;
+pub const MAX_TX_SIGOPS: u64 = 1_000;
+
+// Staking: Unstaked VLT stays locked for this many blocks (~1 Day at 60s blocks)
+pub const UNBONDING_PERIOD: u64 = 1_440;
+
+// Fees: Dev Wallet receiving the governance-set share (dev_fee_percent) of block fees
+pub const DEV_WALLET: &str = "024dea39ce2e873d5be2d8e092044a7dbd9cfa2dadcba5d32e9b141b7361422d56";
+
+// Delegation: Validator commission cap in basis points (100%)
+pub const MAX_COMMISSION_BPS: u64 = 10_000;
+// Delegation: a validator with delegators raises its commission by at most this much, once per UNBONDING_PERIOD
+pub const MAX_COMMISSION_RAISE_BPS: u64 = 100;
+
+// Finality: stakers attest every FINALITY_INTERVAL-th block, 2/3 of total stake makes it final
+pub const FINALITY_INTERVAL: u64 = 100;
+// Checkpoints that gather no supermajority within this many blocks are dropped
+pub const FINALITY_WINDOW: u64 = 10 * FINALITY_INTERVAL;
+
+// Slashing: share of the offender's bonded VLT (staked + unbonding) taken per proven equivocation
+pub const SLASH_PERCENT: u64 = 50;
+// Share of the penalty paid to the reporter, the rest is burned
+pub const SLASH_REPORTER_PERCENT: u64 = 10;
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Order {
-    pub id: String,
+    pub id: String, // Hash of the PlaceOrder transaction
     pub creator: String,
-    pub token: String,
+    pub token: String, // Base asset
+    #[serde(default)]
+    pub quote: String,
+    #[serde(default)]
+    pub pair: String, // "BASE/QUOTE"
     pub side: String, // "BUY" or "SELL"
-    pub price: u64,
-    pub amount: u64, // Remaining amount
+    pub price: u64, // Quote units per base unit
+    pub amount: u64, // Remaining amount (base units)
     pub timestamp: u64,
+    #[serde(default)]
+    pub sequence: u64, // Chain-assigned placement order (time priority)
+    #[serde(default)]
+    pub order_type: OrderType,
+    #[serde(default)]
+    pub expiry_height: Option<u64>,
+    #[serde(default)]
+    pub expiry_time: Option<u64>,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -23,16 +70,251 @@ pub struct Pool {
     pub reserve_a: u64,
     pub reserve_b: u64,
     pub total_shares: u64,
+    // Price oracle: sum of spot price * seconds, advanced before reserves change
+    #[serde(default, with = "crate::dex::u128_string")]
+    pub price_a_cumulative: u128, // token_b per token_a * 10^8
+    #[serde(default, with = "crate::dex::u128_string")]
+    pub price_b_cumulative: u128, // token_a per token_b * 10^8
+    #[serde(default)]
+    pub last_update: u64, // Timestamp the cumulatives were advanced to
+}
+
+// One swap hop: (PoolID, A -> B, Output)
+pub type SwapHop = (String, bool, u64);
+
+impl Pool {
+    // Spot prices (token_b per token_a, token_a per token_b) * 10^8, None while a reserve is empty
+    pub fn spot_prices(&self) -> Option<(u128, u128)> {
+        if self.reserve_a == 0 || self.reserve_b == 0 { return None; }
+        Some((
+            self.reserve_b as u128 * PRICE_SCALE / self.reserve_a as u128,
+            self.reserve_a as u128 * PRICE_SCALE / self.reserve_b as u128,
+        ))
+    }
+
+    // Cumulatives at `time` >= last_update if the reserves stay unchanged (wrap around like Uniswap v2)
+    pub fn cumulative_at(&self, time: u64) -> (u128, u128) {
+        let elapsed = time.saturating_sub(self.last_update) as u128;
+        match self.spot_prices() {
+            Some((pa, pb)) if elapsed > 0 => (
+                self.price_a_cumulative.wrapping_add(pa.wrapping_mul(elapsed)),
+                self.price_b_cumulative.wrapping_add(pb.wrapping_mul(elapsed)),
+            ),
+            _ => (self.price_a_cumulative, self.price_b_cumulative),
+        }
+    }
+
+    // Advances the cumulatives to `time`, false if it is not later than the last update
+    pub fn accumulate(&mut self, time: u64) -> bool {
+        if time <= self.last_update { return false; }
+        (self.price_a_cumulative, self.price_b_cumulative) = self.cumulative_at(time);
+        self.last_update = time;
+        true
+    }
+
+    // Constant product with the 0.3% LP fee
+    pub fn amount_out(&self, a_to_b: bool, amount_in: u64) -> u64 {
+        let (rin, rout) = if a_to_b { (self.reserve_a, self.reserve_b) } else { (self.reserve_b, self.reserve_a) };
+        let input_with_fee = amount_in as u128 * 997;
+        let denominator = rin as u128 * 1000 + input_with_fee;
+        if denominator == 0 { return 0; }
+        (input_with_fee * rout as u128 / denominator) as u64
+    }
+
+    // Input needed for exactly `amount_out` (rounded up), None if the pool cannot provide it
+    pub fn amount_in(&self, a_to_b: bool, amount_out: u64) -> Option<u64> {
+        let (rin, rout) = if a_to_b { (self.reserve_a, self.reserve_b) } else { (self.reserve_b, self.reserve_a) };
+        if amount_out >= rout || rin == 0 { return None; }
+        let numerator = rin as u128 * amount_out as u128 * 1000;
+        let denominator = (rout - amount_out) as u128 * 997;
+        u64::try_from(numerator / denominator + 1).ok()
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Unbonding {
+    pub amount: u64,
+    pub release_height: u64, // Spendable from this block on
+    #[serde(default)]
+    pub recipient: String, // Credited on release (Unstake: tx.receiver as before unbonding existed, empty = queue owner)
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct TokenSupply {
+    pub issued: u64,
+    pub burned: u64,
+}
+
+impl TokenSupply {
+    pub fn circulating(&self) -> u64 {
+        self.issued.saturating_sub(self.burned)
+    }
+}
+
+fn default_decimals() -> u8 { 8 }
+
+/// Token Metadata, set at issuance (IssueToken tx.data as JSON)
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TokenMetadata {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    #[serde(default)]
+    pub max_supply: Option<u64>, // Cap on total issuance, burns do not free room (None = uncapped)
+    #[serde(default)]
+    pub uri: String, // Logo / Metadata URI
+    #[serde(default)]
+    pub mintable: bool, // Issuer may Mint more until renounced
+    // Issuer controls, each opted into at issuance and renounceable on its own
+    #[serde(default)]
+    pub freezable: bool,
+    #[serde(default)]
+    pub pausable: bool,
+    #[serde(default)]
+    pub allowlistable: bool,
+    #[serde(default)]
+    pub clawbackable: bool,
+}
+
+impl Default for TokenMetadata {
+    fn default() -> Self {
+        TokenMetadata {
+            name: String::new(),
+            decimals: default_decimals(),
+            max_supply: None,
+            uri: String::new(),
+            mintable: false,
+            freezable: false,
+            pausable: false,
+            allowlistable: false,
+            clawbackable: false,
+        }
+    }
+}
+
+impl TokenMetadata {
+    pub fn has_control(&self, control: TokenControl) -> bool {
+        match control {
+            TokenControl::Freeze => self.freezable,
+            TokenControl::Pause => self.pausable,
+            TokenControl::Allowlist => self.allowlistable,
+            TokenControl::Clawback => self.clawbackable,
+        }
+    }
+
+    pub fn has_any_control(&self) -> bool {
+        self.freezable || self.pausable || self.allowlistable || self.clawbackable
+    }
+
+    fn drop_control(&mut self, control: TokenControl) {
+        match control {
+            TokenControl::Freeze => self.freezable = false,
+            TokenControl::Pause => self.pausable = false,
+            TokenControl::Allowlist => self.allowlistable = false,
+            TokenControl::Clawback => self.clawbackable = false,
+        }
+    }
+
+    // Tokens issued without metadata: fixed supply, 8 decimals, named after the symbol
+    pub fn from_issue(tx: &Transaction) -> Result<Self, String> {
+        let mut meta: TokenMetadata = if tx.data.is_empty() {
+            TokenMetadata::default()
+        } else {
+            serde_json::from_str(&tx.data).map_err(|_| "Malformed Token Metadata".to_string())?
+        };
+        if meta.name.is_empty() { meta.name = tx.token.clone(); }
+
+        if meta.name.len() > 32 { return Err("Name too long (Max 32)".to_string()); }
+        if meta.decimals > 18 { return Err("Decimals must be <= 18".to_string()); }
+        if meta.uri.len() > 256 { return Err("URI too long (Max 256)".to_string()); }
+        if meta.max_supply.is_some_and(|max| tx.amount > max) {
+            return Err("Initial supply exceeds max supply".to_string());
+        }
+        Ok(meta)
+    }
+}
+
+/// Issuer control a token can opt into at issuance
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum TokenControl {
+    Freeze,    // Freeze / Unfreeze
+    Pause,     // Pause / Unpause
+    Allowlist, // SetAllowlistMode / Allow / Disallow
+    Clawback,
+}
+
+/// State of the issuer controls of a token issued with at least one of them
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct TokenCompliance {
+    pub paused: bool,
+    pub allowlist_only: bool, // Only allowlisted addresses (and the issuer) may hold or move the token
+    pub frozen: BTreeSet<String>,
+    pub allowlist: BTreeSet<String>,
+}
+
+/// Spending right granted by an Approve tx (owner -> spender, per token)
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Allowance {
+    pub amount: u64, // Remaining amount the spender may move
+    pub expiry_height: Option<u64>, // Usable while height < expiry_height
+}
+
+/// Approve options (JSON in tx.data, empty = no expiry)
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ApproveParams {
+    #[serde(default)]
+    pub expiry_height: Option<u64>,
+}
+
+impl ApproveParams {
+    pub fn from_data(data: &str) -> Result<Self, String> {
+        if data.is_empty() {
+            return Ok(ApproveParams::default());
+        }
+        serde_json::from_str(data).map_err(|_| "Malformed Approve Parameters".to_string())
+    }
+}
+
+impl Allowance {
+    pub fn is_active(&self, height: u64) -> bool {
+        self.amount > 0 && self.expiry_height.is_none_or(|expiry| height < expiry)
+    }
+}
+
+/// TokenControl tx payload (JSON in tx.data), issued by the token issuer
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "action")]
+pub enum ComplianceAction {
+    Freeze { address: String },
+    Unfreeze { address: String },
+    Pause,
+    Unpause,
+    SetAllowlistMode { enabled: bool },
+    Allow { address: String },
+    Disallow { address: String },
+    Clawback { address: String, amount: u64 }, // Moves tokens from `address` back to the issuer
+    Renounce { control: TokenControl }, // Permanently drops one control
+}
+
+impl ComplianceAction {
+    // Control the issuer must still hold to take this action
+    pub fn control(&self) -> TokenControl {
+        match self {
+            ComplianceAction::Freeze { .. } | ComplianceAction::Unfreeze { .. } => TokenControl::Freeze,
+            ComplianceAction::Pause | ComplianceAction::Unpause => TokenControl::Pause,
+            ComplianceAction::SetAllowlistMode { .. } | ComplianceAction::Allow { .. } | ComplianceAction::Disallow { .. } => TokenControl::Allowlist,
+            ComplianceAction::Clawback { .. } => TokenControl::Clawback,
+            ComplianceAction::Renounce { control } => *control,
+        }
+    }
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-pub struct Candle {
-    pub time: u64,
-    pub open: u64,
-    pub high: u64,
-    pub low: u64,
-    pub close: u64,
-    pub volume: u64,
+pub struct FinalityCheckpoint {
+    pub height: u64,
+    pub hash: String,
+    pub attesters: BTreeSet<String>, // Stakers who signed an Attest tx for this block
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -49,17 +331,49 @@ pub struct ChainState {
     pub tokens: HashMap<String, String>, 
     pub nonces: HashMap<String, u64>,
     pub stakes: HashMap<String, u64>,
+    pub unbonding: HashMap<String, Vec<Unbonding>>, // Address -> Withdrawal Queue
+    pub token_meta: HashMap<String, TokenMetadata>,
+    pub token_supply: HashMap<String, TokenSupply>, // Token -> Issued / Burned (VLT: burns only, issuance is the Emission Schedule)
+    pub token_compliance: HashMap<String, TokenCompliance>, // Only tokens with active controls
+    pub allowances: HashMap<(String, String, String), Allowance>, // (Owner, Spender, Token) -> Allowance
+    pub delegations: HashMap<String, HashMap<String, u64>>, // Validator -> (Delegator -> Amount)
+    pub commissions: HashMap<String, u64>, // Validator -> Commission (Basis Points)
+    pub commission_raised: HashMap<String, u64>, // Validator -> Height of its last raise while delegated to
+    pub height: u64, // Height of the block currently being applied
+    pub time: u64, // Timestamp of the block currently being applied
+    pub slashed_offences: HashSet<(String, u64)>, // (Offender, Height) already punished
+    pub slashed_burned: u64, // Total VLT burned by Slashing
 
+    // Finality
+    pub finality_checkpoints: BTreeMap<u64, FinalityCheckpoint>, // Open (not yet final) checkpoints
+    pub finalized: Option<(u64, String)>, // Latest finalized (Height, Hash)
+
+    // Governance
+    pub gov_params: GovParams,
+    pub proposals: BTreeMap<u64, Proposal>,
+    pub next_proposal_id: u64,
+
+    pub pairs: BTreeMap<String, TradingPair>, // Listed pairs ("BASE/QUOTE" -> Tick & Lot Sizes)
     pub orders: HashMap<String, Order>, // OrderID -> Order
+    pub next_order_seq: u64, // Monotonic, never reused
+    pub block_trades: Vec<Trade>, // Fills of the block currently being applied (persisted by Blockchain)
+    pub block_swaps: Vec<SwapRecord>, // AMM swaps of the block currently being applied (candles)
     // Optimization: BTreeMaps for O(log N) matching
-    // Key: (Token, Price, Timestamp) -> Value: OrderID
-    // Token is needed because we match per pair.
+    // Key: (PairID, Price, Sequence) -> Value: OrderID
+    // PairID is needed because we match per pair. Sequence keeps keys unique and gives time priority.
     pub bids: BTreeMap<(String, u64, u64), String>, 
     pub asks: BTreeMap<(String, u64, u64), String>,
 
     pub pools: HashMap<String, Pool>,   // "TokenA/TokenB" -> Pool
-    pub candles: HashMap<String, Vec<Candle>>, // "Pair" -> History
     pub nfts: HashMap<String, NFT>, // NFT_ID -> NFT
+    pub price_observations: HashMap<String, VecDeque<PriceObservation>>, // PoolID -> Oracle history (oldest first)
+}
+
+// Scripts read TWAPs ending at the last block
+impl PriceOracle for ChainState {
+    fn twap(&self, pool: &str, window: u64) -> Option<u64> {
+        self.twap(pool, self.time.saturating_sub(window), self.time).ok().map(|t| t.price_a)
+    }
 }
 
 impl ChainState {
@@ -69,14 +383,37 @@ impl ChainState {
             tokens: HashMap::new(),
             nonces: HashMap::new(),
             stakes: HashMap::new(),
+            unbonding: HashMap::new(),
+            token_meta: HashMap::new(),
+            token_supply: HashMap::new(),
+            token_compliance: HashMap::new(),
+            allowances: HashMap::new(),
+            delegations: HashMap::new(),
+            commissions: HashMap::new(),
+            commission_raised: HashMap::new(),
+            height: 0,
+            time: 0,
+            slashed_offences: HashSet::new(),
+            slashed_burned: 0,
+
+            finality_checkpoints: BTreeMap::new(),
+            finalized: None,
+
+            gov_params: GovParams::default(),
+            proposals: BTreeMap::new(),
+            next_proposal_id: 1,
 
             orders: HashMap::new(),
+            pairs: BTreeMap::new(),
+            next_order_seq: 1,
+            block_trades: Vec::new(),
+            block_swaps: Vec::new(),
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
             
             pools: HashMap::new(),
-            candles: HashMap::new(),
             nfts: HashMap::new(),
+            price_observations: HashMap::new(),
         }
     }
 
@@ -93,206 +430,1800 @@ impl ChainState {
         user_tokens.insert(token.to_string(), amount);
     }
 
-    fn update_candle(&mut self, pair: &str, price: u64, volume: u64, timestamp: u64) {
-        // Timeframe: 1 Minute (60 seconds)
-        let timeframe = 60;
-        let time_slot = (timestamp / timeframe) * timeframe;
-        
-        let history = self.candles.entry(pair.to_string()).or_insert_with(Vec::new);
-        
-        if let Some(last) = history.last_mut() {
-            if last.time == time_slot {
-                // Update existing candle
-                if price > last.high { last.high = price; }
-                if price < last.low { last.low = price; }
-                last.close = price;
-                last.volume += volume;
-                return;
-            }
+    // Per-Block Hook: runs before a block's transactions are applied
+    pub fn begin_block(&mut self, block: &Block) {
+        let height = block.index;
+        self.height = height;
+        self.time = block.timestamp;
+        self.block_trades.clear();
+        self.block_swaps.clear();
+
+        // DEX: cancel and refund expired orders before this block's transactions
+        self.expire_orders();
+
+        // AMM: advance every pool's price accumulators to this block's time (reserves of the previous block)
+        let pool_ids: Vec<String> = self.pools.keys().cloned().collect();
+        for pool_id in pool_ids {
+            self.observe_pool(&pool_id);
         }
-        
-        // Create new candle
-        history.push(Candle {
-            time: time_slot,
-            open: price,
-            high: price,
-            low: price,
-            close: price,
-            volume,
-        });
-    }
 
-    pub fn apply_transaction(&mut self, tx: &Transaction) -> bool {
-        // 1. DEBIT
-        if tx.sender != "SYSTEM" {
-            // Determine what to debit
-            let fee_token = "VLT";
-            
-            // 1. Fee Debit Disabled by User Request
-            // Fees are no longer deducted.
-            let fee_paid_in_vlt = true; 
-            /*
-            let vlt_bal = self.get_balance(&tx.sender, "VLT");
-            let fee_paid_in_vlt = if vlt_bal >= tx.fee {
-                self.set_balance(&tx.sender, "VLT", vlt_bal - tx.fee);
-                true
-            } else {
-                false
-            };
+        // Finality: every FINALITY_INTERVAL-th block opens a checkpoint for attestations
+        if height > 0 && height.is_multiple_of(FINALITY_INTERVAL) {
+            self.finality_checkpoints.insert(height, FinalityCheckpoint { height, hash: block.hash.clone(), attesters: BTreeSet::new() });
+        }
+        let finalized_height = self.finalized.as_ref().map(|(h, _)| *h).unwrap_or(0);
+        self.finality_checkpoints.retain(|h, _| *h > finalized_height && h + FINALITY_WINDOW > height);
 
-            if !fee_paid_in_vlt {
-                // Fallback: Try paying in the Asset itself (if not VLT)
-                let token_bal = self.get_balance(&tx.sender, &tx.token);
-                if tx.token != "VLT" && token_bal >= tx.fee {
-                     self.set_balance(&tx.sender, &tx.token, token_bal - tx.fee);
-                } else {
-                     println!("CRITICAL: Failed to debit fee for {} (No VLT or {} balance)", tx.sender, tx.token);
-                     return false;
-                }
+        // Release matured Unbondings
+        let mut released = Vec::new();
+        for (addr, queue) in self.unbonding.iter_mut() {
+            for u in queue.iter().filter(|u| u.release_height <= height) {
+                let payee = if u.recipient.is_empty() { addr.clone() } else { u.recipient.clone() };
+                released.push((payee, u.amount));
             }
-            */
+            queue.retain(|u| u.release_height > height);
+        }
+        self.unbonding.retain(|_, queue| !queue.is_empty());
 
-            // 2. Debit Amount (Token)
-            if tx.tx_type == TxType::Transfer || tx.tx_type == TxType::Stake {
-                // For Stake, token is VLT, so we just deducted fee, now deduct amount.
-                // For Transfer, token could be anything.
-                let amount_token = &tx.token;
-                let current_amount_bal = self.get_balance(&tx.sender, amount_token);
-                
-                if let Some(new_amt_bal) = current_amount_bal.checked_sub(tx.amount) {
-                    self.set_balance(&tx.sender, amount_token, new_amt_bal);
-                } else {
-                     // println!("Failed to debit amount");
-                     return false;
-                }
-            } else if tx.tx_type == TxType::AddLiquidity {
-                 // Handled in separate logic block below? 
-                 // Actually AddLiquidity logic calls set_balance manually.
-                 // We should ensure we don't double debit.
-                 // The original code handled AddLiquidity separately in `apply_transaction` later?
-                 // No, original code had `match tx.tx_type` later for logic, but DEBIT was upfront.
-                 // Let's look at original DEBIT block.
-                 // It had `else if tx.tx_type == TxType::AddLiquidity` logic? 
-                 // No, the snippet shows `TxType::IssueToken`, `Stake`, `Transfer`.
-                 // AddLiquidity was NOT in the initial debit block I viewed?
-                 // Checking lines 518 in previous view... yes, AddLiquidity logic does the debit itself:
-                 // "self.set_balance(sender, token_a, old_a - amount_a);"
-                 // So we should NOT debit amount here for AddLiquidity.
+        for (addr, amount) in released {
+            let bal = self.get_balance(&addr, "VLT");
+            self.set_balance(&addr, "VLT", bal.saturating_add(amount));
+        }
+
+        self.process_governance(height);
+    }
+
+    // Governance: close voting at end_height, apply passed parameter changes at activation_height
+    fn process_governance(&mut self, height: u64) {
+        let ending: Vec<u64> = self.proposals.values()
+            .filter(|p| p.status == ProposalStatus::Voting && p.end_height <= height)
+            .map(|p| p.id)
+            .collect();
+        for id in ending {
+            let tally = self.tally_proposal(&self.proposals[&id]);
+            let passed = tally.passed();
+            if let Some(proposal) = self.proposals.get_mut(&id) {
+                proposal.status = if passed { ProposalStatus::Passed } else { ProposalStatus::Rejected };
+                proposal.tally = Some(tally);
+                println!("[Governance] Proposal #{} {:?}", id, proposal.status);
             }
-            
-            if tx.tx_type == TxType::Stake {
-                let current_stake = *self.stakes.get(&tx.sender).unwrap_or(&0);
-                if let Some(new_stake) = current_stake.checked_add(tx.amount) {
-                    self.stakes.insert(tx.sender.clone(), new_stake);
+        }
+
+        let activating: Vec<u64> = self.proposals.values()
+            .filter(|p| p.status == ProposalStatus::Passed && p.activation_height <= height)
+            .filter(|p| matches!(p.kind, ProposalKind::ParamChange { .. } | ProposalKind::FeeRecipient { .. }))
+            .map(|p| p.id)
+            .collect();
+        for id in activating {
+            if let Some(proposal) = self.proposals.get_mut(&id) {
+                match &proposal.kind {
+                    ProposalKind::ParamChange { param, value } => {
+                        self.gov_params.set(param, *value);
+                        println!("[Governance] Proposal #{} executed: {} = {}", id, param, value);
+                    }
+                    ProposalKind::FeeRecipient { address } => {
+                        self.gov_params.dex_fee_recipient = address.clone();
+                        println!("[Governance] Proposal #{} executed: dex_fee_recipient = {}", id, address);
+                    }
+                    ProposalKind::Text => {}
                 }
+                proposal.status = ProposalStatus::Executed;
             }
         }
+    }
 
-        // 2. CREDIT
-        if tx.tx_type == TxType::Transfer || tx.tx_type == TxType::IssueToken || tx.sender == "SYSTEM" {
-            let current_bal = self.get_balance(&tx.receiver, &tx.token);
-            if let Some(new_bal) = current_bal.checked_add(tx.amount) {
-                self.set_balance(&tx.receiver, &tx.token, new_bal);
+    // Stake-weighted Tally against the proposal's voting power snapshot (final once the proposal is closed)
+    pub fn tally_proposal(&self, proposal: &Proposal) -> Tally {
+        let mut tally = Tally { total_staked: proposal.total_voting_power, ..Default::default() };
+        for (voter, option) in &proposal.votes {
+            let weight = *proposal.weights.get(voter).unwrap_or(&0);
+            match option {
+                VoteOption::Yes => tally.yes += weight,
+                VoteOption::No => tally.no += weight,
+                VoteOption::Abstain => tally.abstain += weight,
             }
-        } else if tx.tx_type == TxType::Unstake {
-             // Return Stake
-             let current_stake = *self.stakes.get(&tx.sender).unwrap_or(&0);
-             if current_stake >= tx.amount {
-                  if let Some(new_stake) = current_stake.checked_sub(tx.amount) {
-                      self.stakes.insert(tx.sender.clone(), new_stake);
-                      // Credit VLT
-                      let bal = self.get_balance(&tx.receiver, "VLT");
-                      if let Some(new_bal) = bal.checked_add(tx.amount) {
-                          self.set_balance(&tx.receiver, "VLT", new_bal);
-                      }
-                  }
-             }
         }
+        tally
+    }
 
-        if tx.tx_type == TxType::IssueToken {
-            self.tokens.insert(tx.token.clone(), tx.sender.clone());
+    // Stake (ChainState.stakes) that attested to a checkpoint, and the total stake
+    pub fn attested_stake(&self, checkpoint: &FinalityCheckpoint) -> (u64, u64) {
+        let attested = checkpoint.attesters.iter().map(|a| *self.stakes.get(a).unwrap_or(&0)).sum();
+        (attested, self.stakes.values().sum())
+    }
+
+    // Attest tx: amount = checkpoint height, data = block hash
+    pub fn check_attestation(&self, tx: &Transaction) -> Result<(), String> {
+        let checkpoint = self.finality_checkpoints.get(&tx.amount).ok_or("Unknown or already finalized checkpoint".to_string())?;
+        if checkpoint.hash != tx.data {
+            return Err(format!("Attested hash does not match block #{}", tx.amount));
         }
-        
-        // Nonce
-        if tx.sender != "SYSTEM" {
-            self.nonces.insert(tx.sender.clone(), tx.nonce);
+        if *self.stakes.get(&tx.sender).unwrap_or(&0) == 0 {
+            return Err("Attester has no stake".to_string());
+        }
+        if checkpoint.attesters.contains(&tx.sender) {
+            return Err("Already attested".to_string());
+        }
+        Ok(())
+    }
+
+    fn apply_attestation(&mut self, tx: &Transaction) -> bool {
+        if let Err(e) = self.check_attestation(tx) {
+            println!("[Finality] Rejected Attestation: {}", e);
+            return false;
+        }
+        let (attested, total) = match self.finality_checkpoints.get_mut(&tx.amount) {
+            Some(checkpoint) => {
+                checkpoint.attesters.insert(tx.sender.clone());
+                let checkpoint = checkpoint.clone();
+                self.attested_stake(&checkpoint)
+            }
+            None => return false,
+        };
+
+        // 2/3 Supermajority: this block and all its ancestors are final
+        if total > 0 && attested as u128 * 3 >= total as u128 * 2 {
+            let height = tx.amount;
+            self.finalized = Some((height, tx.data.clone()));
+            self.finality_checkpoints.retain(|h, _| *h > height);
+            println!("[Finality] Block #{} finalized ({} / {} stake)", height, attested, total);
         }
         true
     }
-}
 
-pub struct Blockchain {
-    pub chain: Vec<Block>,
-    pub pending_transactions: Vec<Transaction>,
-    pub difficulty: u32,
-    pub state: ChainState,
-    pub db: Option<Database>, 
-}
+    pub fn check_proposal(&self, tx: &Transaction) -> Result<ProposalContent, String> {
+        let content: ProposalContent = serde_json::from_str(&tx.data).map_err(|_| "Malformed Proposal".to_string())?;
+        content.validate()?;
+        if *self.stakes.get(&tx.sender).unwrap_or(&0) < MIN_PROPOSER_STAKE {
+            return Err(format!("Proposer must stake at least {} units", MIN_PROPOSER_STAKE));
+        }
+        Ok(content)
+    }
 
-impl Blockchain {
-    pub fn new() -> Self {
-        let mut blockchain = Blockchain {
-            chain: Vec::new(),
-            pending_transactions: Vec::new(),
-            // Standard Difficulty (Difficulty 1)
-            // This is the correct setting for a Mainnet-like environment.
-            difficulty: 0x1d00ffff,
-            state: ChainState::new(),
-            db: Database::new("volt.db").ok(), 
-        };
+    // `height`: block the vote is (or will be) included in. Weight: own + delegated stake when the proposal was submitted.
+    pub fn check_vote(&self, tx: &Transaction, height: u64) -> Result<(VoteOption, u64), String> {
+        let option = VoteOption::from_receiver(&tx.receiver).ok_or("Invalid Vote Option".to_string())?;
+        let proposal = self.proposals.get(&tx.amount).ok_or("Unknown Proposal".to_string())?;
+        if proposal.status != ProposalStatus::Voting || height >= proposal.end_height {
+            return Err("Voting closed".to_string());
+        }
+        let weight = *proposal.voting_power.get(&tx.sender).unwrap_or(&0);
+        if weight == 0 {
+            return Err("No stake when the proposal was submitted".to_string());
+        }
+        Ok((option, weight))
+    }
 
-        if let Some(ref db) = blockchain.db {
-            if let Some(chain_data) = db.load_chain() {
-                blockchain.chain = chain_data;
-                // Restore Mempool
-                if let Ok(pending) = db.load_pending_txs() {
-                    blockchain.pending_transactions = pending;
-                    println!("[Chain] Restored {} pending transactions from DB", blockchain.pending_transactions.len());
-                }
-                blockchain.rebuild_state();
-            } else {
-                blockchain.create_genesis_block();
+    // Governance snapshot: every staker's own + delegated stake
+    pub fn voting_power(&self) -> BTreeMap<String, u64> {
+        self.stakes.keys().chain(self.delegations.keys())
+            .map(|addr| (addr.clone(), self.get_validator_stake(addr)))
+            .filter(|(_, power)| *power > 0)
+            .collect()
+    }
+
+    fn apply_proposal(&mut self, tx: &Transaction) -> bool {
+        let content = match self.check_proposal(tx) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("[Governance] Rejected Proposal: {}", e);
+                return false;
             }
-        } else {
-             blockchain.create_genesis_block();
-        }
-        blockchain
+        };
+
+        let id = self.next_proposal_id;
+        let end_height = self.height + VOTING_PERIOD;
+        let voting_power = self.voting_power();
+        let total_voting_power = voting_power.values().sum();
+        self.proposals.insert(id, Proposal {
+            id,
+            proposer: tx.sender.clone(),
+            title: content.title,
+            description: content.description,
+            kind: content.kind,
+            submit_height: self.height,
+            end_height,
+            activation_height: end_height + ACTIVATION_DELAY,
+            status: ProposalStatus::Voting,
+            votes: BTreeMap::new(),
+            weights: BTreeMap::new(),
+            voting_power,
+            total_voting_power,
+            tally: None,
+        });
+        self.next_proposal_id += 1;
+        true
     }
 
-    pub fn rebuild_state(&mut self) {
-        self.state = ChainState::new();
-        for block in &self.chain {
-            for tx in &block.transactions {
-                // If a historical transaction fails, we log it but continue (assume DB valid)
-                // In production, this might indicate corruption.
-                if !self.state.apply_transaction(tx) {
-                    println!("[Chain] Warning: Historical transaction application failed: {}", hex::encode(tx.get_hash()));
-                }
+    fn apply_vote(&mut self, tx: &Transaction) -> bool {
+        let (option, weight) = match self.check_vote(tx, self.height) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("[Governance] Rejected Vote: {}", e);
+                return false;
             }
+        };
+        if let Some(proposal) = self.proposals.get_mut(&tx.amount) {
+            // Re-voting replaces the previous vote and its weight
+            proposal.votes.insert(tx.sender.clone(), option);
+            proposal.weights.insert(tx.sender.clone(), weight);
         }
+        true
     }
-    
-    // Wrapper for API
-    pub fn apply_transaction_to_state(&mut self, tx: &Transaction) -> bool {
-        self.state.apply_transaction(tx)
+
+    // IssueToken: new symbol (3-8 chars, not VLT) with valid metadata
+    pub fn check_issue(&self, tx: &Transaction) -> Result<TokenMetadata, String> {
+        if tx.token == "VLT" { return Err("VLT cannot be issued".to_string()); }
+        if self.tokens.contains_key(&tx.token) { return Err("Token already exists".to_string()); }
+        if tx.token.len() < 3 || tx.token.len() > 8 { return Err("Symbol must be 3-8 characters".to_string()); }
+        TokenMetadata::from_issue(tx)
     }
 
-    pub fn get_balance(&self, address: &str, token: &str) -> u64 {
-        self.state.get_balance(address, token)
+    // Mint: only the issuer, while mint authority is held, within max supply. Receiver gets the tokens.
+    pub fn check_mint(&self, tx: &Transaction) -> Result<(), String> {
+        let issuer = self.tokens.get(&tx.token).ok_or("Unknown Token".to_string())?;
+        if issuer != &tx.sender { return Err("Only the issuer can mint".to_string()); }
+        let meta = self.token_meta.get(&tx.token).ok_or("Unknown Token".to_string())?;
+        if !meta.mintable { return Err("Mint authority renounced".to_string()); }
+        if tx.amount == 0 { return Err("Amount must be positive".to_string()); }
+        if let Some(max) = meta.max_supply {
+            let issued = self.token_supply.get(&tx.token).map(|s| s.issued).unwrap_or(0);
+            if issued.saturating_add(tx.amount) > max {
+                return Err(format!("Exceeds max supply ({})", max));
+            }
+        }
+        Ok(())
     }
-    
-    // Helper to accessing state mutation
-    fn set_balance(&mut self, address: &str, token: &str, amount: u64) {
-        self.state.set_balance(address, token, amount);
+
+    pub fn check_renounce_mint(&self, tx: &Transaction) -> Result<(), String> {
+        if self.tokens.get(&tx.token) != Some(&tx.sender) { return Err("Only the issuer can renounce".to_string()); }
+        if !self.token_meta.get(&tx.token).is_some_and(|m| m.mintable) { return Err("Mint authority already renounced".to_string()); }
+        Ok(())
     }
 
-    fn create_genesis_block(&mut self) {
-        // PreMine: 5% of 21,000,000 VLT (Total Supply) = 1,050,000 VLT
-        // 1,050,000 * 100,000,000 = 105,000,000,000,000 Atomic Units
-        let premine_tx = Transaction {
+    // Token Compliance: every movement of an issued token passes here.
+    // `None` = no holder on that side (Mint source, Burn target, DEX escrow, AMM pool).
+    pub fn check_token_movement(&self, token: &str, from: Option<&str>, to: Option<&str>) -> Result<(), String> {
+        let Some(controls) = self.token_compliance.get(token) else { return Ok(()); };
+        if controls.paused {
+            return Err(format!("{} transfers are paused", token));
+        }
+        let issuer = self.tokens.get(token).map(|s| s.as_str());
+        for address in [from, to].into_iter().flatten() {
+            if controls.frozen.contains(address) {
+                return Err(format!("{} is frozen for {}", address, token));
+            }
+            if controls.allowlist_only && Some(address) != issuer && !controls.allowlist.contains(address) {
+                return Err(format!("{} is not allowlisted for {}", address, token));
+            }
+        }
+        Ok(())
+    }
+
+    // Movements carried by the transaction itself (DEX / AMM legs are checked where they happen)
+    pub fn check_tx_compliance(&self, tx: &Transaction) -> Result<(), String> {
+        match tx.tx_type {
+            TxType::Transfer => self.check_token_movement(&tx.token, Some(&tx.sender), Some(&tx.receiver)),
+            TxType::Burn => self.check_token_movement(&tx.token, Some(&tx.sender), None),
+            TxType::Mint => self.check_token_movement(&tx.token, None, Some(&tx.receiver)),
+            TxType::TransferFrom => self.check_token_movement(&tx.token, Some(&tx.data), Some(&tx.receiver)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_token_control(&self, tx: &Transaction) -> Result<ComplianceAction, String> {
+        if self.tokens.get(&tx.token) != Some(&tx.sender) {
+            return Err("Only the issuer can use token controls".to_string());
+        }
+        let action: ComplianceAction = serde_json::from_str(&tx.data).map_err(|_| "Malformed Compliance Action".to_string())?;
+        if !self.token_meta.get(&tx.token).is_some_and(|m| m.has_control(action.control())) {
+            return Err(format!("Token has no {:?} control", action.control()));
+        }
+        if let ComplianceAction::Clawback { address, amount } = &action {
+            if *amount == 0 || self.get_balance(address, &tx.token) < *amount {
+                return Err("Clawback exceeds holder balance".to_string());
+            }
+        }
+        Ok(action)
+    }
+
+    fn apply_token_control(&mut self, tx: &Transaction) -> bool {
+        let action = match self.check_token_control(tx) {
+            Ok(a) => a,
+            Err(e) => {
+                println!("[Compliance] Rejected: {}", e);
+                return false;
+            }
+        };
+
+        match action {
+            ComplianceAction::Clawback { address, amount } => {
+                let bal = self.get_balance(&address, &tx.token);
+                self.set_balance(&address, &tx.token, bal - amount);
+                let issuer_bal = self.get_balance(&tx.sender, &tx.token);
+                self.set_balance(&tx.sender, &tx.token, issuer_bal.saturating_add(amount));
+            }
+            ComplianceAction::Renounce { control } => {
+                let mut any_left = false;
+                if let Some(meta) = self.token_meta.get_mut(&tx.token) {
+                    meta.drop_control(control);
+                    any_left = meta.has_any_control();
+                }
+                // Lift whatever the renounced control had in force
+                if let Some(controls) = self.token_compliance.get_mut(&tx.token) {
+                    match control {
+                        TokenControl::Freeze => controls.frozen.clear(),
+                        TokenControl::Pause => controls.paused = false,
+                        TokenControl::Allowlist => {
+                            controls.allowlist_only = false;
+                            controls.allowlist.clear();
+                        }
+                        TokenControl::Clawback => {}
+                    }
+                }
+                if !any_left {
+                    self.token_compliance.remove(&tx.token);
+                }
+            }
+            action => {
+                let controls = self.token_compliance.entry(tx.token.clone()).or_default();
+                match action {
+                    ComplianceAction::Freeze { address } => { controls.frozen.insert(address); }
+                    ComplianceAction::Unfreeze { address } => { controls.frozen.remove(&address); }
+                    ComplianceAction::Pause => controls.paused = true,
+                    ComplianceAction::Unpause => controls.paused = false,
+                    ComplianceAction::SetAllowlistMode { enabled } => controls.allowlist_only = enabled,
+                    ComplianceAction::Allow { address } => { controls.allowlist.insert(address); }
+                    ComplianceAction::Disallow { address } => { controls.allowlist.remove(&address); }
+                    ComplianceAction::Clawback { .. } | ComplianceAction::Renounce { .. } => {}
+                }
+            }
+        }
+        true
+    }
+
+    // Allowances: Approve sets (not adds to) the spender's allowance, amount 0 revokes
+    pub fn check_approve(&self, tx: &Transaction, height: u64) -> Result<ApproveParams, String> {
+        if tx.receiver == tx.sender {
+            return Err("Cannot approve yourself".to_string());
+        }
+        if hex::decode(&tx.receiver).is_err() {
+            return Err("Invalid spender address".to_string());
+        }
+        if tx.token != "VLT" && !self.tokens.contains_key(&tx.token) {
+            return Err(format!("Unknown token {}", tx.token));
+        }
+        let params = ApproveParams::from_data(&tx.data)?;
+        if tx.amount > 0 && params.expiry_height.is_some_and(|expiry| expiry <= height) {
+            return Err("Expiry height already passed".to_string());
+        }
+        Ok(params)
+    }
+
+    fn apply_approve(&mut self, tx: &Transaction) -> bool {
+        let params = match self.check_approve(tx, self.height) {
+            Ok(p) => p,
+            Err(e) => {
+                println!("[Allowance] Rejected Approve: {}", e);
+                return false;
+            }
+        };
+        let key = (tx.sender.clone(), tx.receiver.clone(), tx.token.clone());
+        if tx.amount == 0 {
+            self.allowances.remove(&key);
+        } else {
+            self.allowances.insert(key, Allowance { amount: tx.amount, expiry_height: params.expiry_height });
+        }
+        true
+    }
+
+    pub fn get_allowance(&self, owner: &str, spender: &str, token: &str, height: u64) -> u64 {
+        match self.allowances.get(&(owner.to_string(), spender.to_string(), token.to_string())) {
+            Some(allowance) if allowance.is_active(height) => allowance.amount,
+            _ => 0,
+        }
+    }
+
+    // TransferFrom: signed by the spender, owner in tx.data
+    pub fn check_transfer_from(&self, tx: &Transaction, height: u64) -> Result<(), String> {
+        let owner = &tx.data;
+        if tx.amount == 0 {
+            return Err("Amount must be positive".to_string());
+        }
+        if self.get_allowance(owner, &tx.sender, &tx.token, height) < tx.amount {
+            return Err("Allowance exceeded or expired".to_string());
+        }
+        if self.get_balance(owner, &tx.token) < tx.amount {
+            return Err("Owner has insufficient balance".to_string());
+        }
+        Ok(())
+    }
+
+    fn apply_transfer_from(&mut self, tx: &Transaction) -> bool {
+        if let Err(e) = self.check_transfer_from(tx, self.height) {
+            println!("[Allowance] Rejected TransferFrom: {}", e);
+            return false;
+        }
+        let owner = tx.data.clone();
+        let key = (owner.clone(), tx.sender.clone(), tx.token.clone());
+        if let Some(allowance) = self.allowances.get_mut(&key) {
+            allowance.amount -= tx.amount;
+            if allowance.amount == 0 {
+                self.allowances.remove(&key);
+            }
+        }
+        let owner_bal = self.get_balance(&owner, &tx.token);
+        self.set_balance(&owner, &tx.token, owner_bal - tx.amount);
+        let receiver_bal = self.get_balance(&tx.receiver, &tx.token);
+        self.set_balance(&tx.receiver, &tx.token, receiver_bal.saturating_add(tx.amount));
+        true
+    }
+
+    // Order Book: VLT, issued tokens and LP shares of existing pools can be traded
+    pub fn is_known_asset(&self, token: &str) -> bool {
+        token == "VLT"
+            || self.tokens.contains_key(token)
+            || token.strip_prefix("LP-").is_some_and(|pool| self.pools.contains_key(pool))
+    }
+
+    // Listed pair, or the implicit TOKEN/VLT market with default sizes
+    pub fn get_pair(&self, base: &str, quote: &str) -> Option<TradingPair> {
+        if let Some(pair) = self.pairs.get(&pair_id(base, quote)) {
+            return Some(pair.clone());
+        }
+        if quote == DEFAULT_QUOTE && base != DEFAULT_QUOTE && self.is_known_asset(base) {
+            return Some(TradingPair::new(base, quote));
+        }
+        None
+    }
+
+    pub fn check_list_pair(&self, tx: &Transaction) -> Result<TradingPair, String> {
+        let mut pair: TradingPair = serde_json::from_str(&tx.data).map_err(|_| "Malformed Pair".to_string())?;
+        pair.lister = tx.sender.clone();
+        pair.validate()?;
+        for asset in [&pair.base, &pair.quote] {
+            if !self.is_known_asset(asset) {
+                return Err(format!("Unknown asset {}", asset));
+            }
+        }
+        if self.pairs.contains_key(&pair.id()) {
+            return Err(format!("Pair {} already listed", pair.id()));
+        }
+        Ok(pair)
+    }
+
+    fn apply_list_pair(&mut self, tx: &Transaction) -> bool {
+        match self.check_list_pair(tx) {
+            Ok(pair) => {
+                self.pairs.insert(pair.id(), pair);
+                true
+            }
+            Err(e) => {
+                println!("[DEX] Rejected Pair: {}", e);
+                false
+            }
+        }
+    }
+
+    // Compliance: a trader sends one asset of the pair and receives the other
+    fn check_trader(&self, pair: &TradingPair, address: &str) -> Result<(), String> {
+        self.check_token_movement(&pair.base, Some(address), None)?;
+        self.check_token_movement(&pair.quote, Some(address), None)
+    }
+
+    // Pair, side and escrow of a PlaceOrder (no balance check)
+    pub fn order_lock(&self, tx: &Transaction) -> Result<OrderLock, String> {
+        let side = match tx.receiver.as_str() {
+            "DEX_BUY" => "BUY",
+            "DEX_SELL" => "SELL",
+            _ => return Err("Invalid order side".to_string()),
+        };
+        let params = OrderParams::from_data(&tx.data)?;
+        let pair = self.get_pair(&tx.token, &params.quote)
+            .ok_or_else(|| format!("Unknown pair {}", pair_id(&tx.token, &params.quote)))?;
+        let price = params.order_price(tx.price)?;
+        pair.validate_order(price, tx.amount, params.order_type)?;
+        let limit = params.limit_price(side, price)?;
+        // BUY locks limit * amount quote, SELL locks amount base
+        let (asset, amount) = if side == "BUY" {
+            (pair.quote.clone(), TradingPair::quote_value(limit, tx.amount).ok_or("Order value overflow".to_string())?)
+        } else {
+            (pair.base.clone(), tx.amount)
+        };
+        Ok(OrderLock { pair, side, params, limit, asset, amount })
+    }
+
+    // Resting orders an incoming order would match: best price first, lowest sequence first per level
+    fn matching_makers(&self, pid: &str, side: &str, limit: u64) -> Vec<(String, u64, u64)> {
+        let pid = pid.to_string();
+        let mut makers: Vec<(String, u64, u64)> = if side == "BUY" {
+            self.asks.range((pid.clone(), 0, 0)..=(pid, limit, u64::MAX))
+                .map(|(k, id)| (id.clone(), k.1, k.2))
+                .collect()
+        } else {
+            self.bids.range((pid.clone(), limit, 0)..=(pid, u64::MAX, u64::MAX))
+                .map(|(k, id)| (id.clone(), k.1, k.2))
+                .collect()
+        };
+        if side == "SELL" {
+            makers.sort_by_key(|(_, price, seq)| (std::cmp::Reverse(*price), *seq));
+        }
+        makers
+    }
+
+    // Level-2 depth: up to `depth` price levels per side, best first
+    pub fn order_book(&self, pid: &str, depth: usize) -> OrderBookDepth {
+        let key = pid.to_string();
+        let side_levels = |entries: &mut dyn Iterator<Item = (&(String, u64, u64), &String)>| {
+            let mut levels: Vec<PriceLevel> = Vec::new();
+            for ((_, price, _), id) in entries {
+                let amount = self.orders.get(id).map(|o| o.amount).unwrap_or(0);
+                match levels.last_mut() {
+                    Some(level) if level.price == *price => {
+                        level.amount += amount;
+                        level.orders += 1;
+                    }
+                    _ => {
+                        if levels.len() == depth { break; }
+                        levels.push(PriceLevel { price: *price, amount, orders: 1 });
+                    }
+                }
+            }
+            levels
+        };
+        let bids = side_levels(&mut self.bids.range((key.clone(), 0, 0)..=(key.clone(), u64::MAX, u64::MAX)).rev());
+        let asks = side_levels(&mut self.asks.range((key.clone(), 0, 0)..=(key.clone(), u64::MAX, u64::MAX)));
+        let best_bid = bids.first().map(|l| l.price);
+        let best_ask = asks.first().map(|l| l.price);
+        OrderBookDepth {
+            pair: key,
+            spread: best_bid.zip(best_ask).map(|(bid, ask)| ask.saturating_sub(bid)),
+            bids,
+            asks,
+            best_bid,
+            best_ask,
+        }
+    }
+
+    // Market dependent conditions. Checked by the mempool and again when the block is applied,
+    // where a failing order is killed (no funds locked) instead of invalidating the block.
+    pub fn check_order_conditions(&self, tx: &Transaction, lock: &OrderLock, height: u64, time: u64) -> Result<(), String> {
+        if lock.params.is_expired(height, time) {
+            return Err("Order expired".to_string());
+        }
+        let makers = self.matching_makers(&lock.pair.id(), lock.side, lock.limit);
+        match lock.params.order_type {
+            OrderType::PostOnly if !makers.is_empty() => Err("Post-only order would match".to_string()),
+            OrderType::FillOrKill => {
+                let fillable: u64 = makers.iter()
+                    .filter_map(|(id, _, _)| self.orders.get(id))
+                    .filter(|m| self.check_trader(&lock.pair, &m.creator).is_ok())
+                    .map(|m| m.amount)
+                    .sum();
+                if fillable < tx.amount {
+                    return Err(format!("Fill-or-kill order not fillable ({} of {})", fillable, tx.amount));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_place_order(&self, tx: &Transaction) -> Result<OrderLock, String> {
+        let lock = self.order_lock(tx)?;
+        self.check_trader(&lock.pair, &tx.sender)?;
+        if self.get_balance(&tx.sender, &lock.asset) < lock.amount {
+            return Err(format!("Insufficient {} balance", lock.asset));
+        }
+        Ok(lock)
+    }
+
+    fn apply_place_order(&mut self, tx: &Transaction) -> bool {
+        let lock = match self.check_place_order(tx) {
+            Ok(l) => l,
+            Err(e) => {
+                println!("[DEX] Rejected Order: {}", e);
+                return false;
+            }
+        };
+        if let Err(e) = self.check_order_conditions(tx, &lock, self.height, self.time) {
+            println!("[DEX] Order killed: {}", e);
+            return true;
+        }
+        let OrderLock { pair, side, params, limit, asset, amount } = lock;
+        let pid = pair.id();
+
+        // 1. Lock Funds
+        let bal = self.get_balance(&tx.sender, &asset);
+        self.set_balance(&tx.sender, &asset, bal - amount);
+
+        // 2. Create Order Object
+        let sequence = self.next_order_seq;
+        self.next_order_seq += 1;
+        let mut order = Order {
+            id: hex::encode(tx.get_hash()), // Tx hash (merkle / mempool / DB index ID), unique: (sender, nonce) never repeats
+            creator: tx.sender.clone(),
+            token: pair.base.clone(),
+            quote: pair.quote.clone(),
+            pair: pid.clone(),
+            side: side.to_string(),
+            price: limit,
+            amount: tx.amount,
+            timestamp: tx.timestamp,
+            sequence,
+            order_type: params.order_type,
+            expiry_height: params.expiry_height,
+            expiry_time: params.expiry_time,
+        };
+
+        // 3. MATCHING ENGINE
+        // BUY matches ASKS (lowest price first), SELL matches BIDS (highest price first).
+        // IDs are collected to avoid holding the BTreeMap borrow.
+        let makers = self.matching_makers(&pid, side, order.price);
+        let mut fills = 0;
+        let mut killed = false;
+        for (maker_id, maker_price, _maker_seq) in makers {
+            if order.amount == 0 { break; }
+            let Some(maker) = self.orders.get(&maker_id).cloned() else { continue; };
+            // Makers frozen / paused / removed from the allowlist since placing cannot trade
+            if self.check_trader(&pair, &maker.creator).is_err() { continue; }
+
+            let trade_amt = order.amount.min(maker.amount);
+            // <= the escrow of the bid side, an overflow kills the rest of the order
+            let Some(value) = TradingPair::quote_value(maker_price, trade_amt) else {
+                println!("[DEX] Order killed: fill value overflow");
+                killed = true;
+                break;
+            };
+            let (buyer, seller) = if side == "BUY" { (&order.creator, &maker.creator) } else { (&maker.creator, &order.creator) };
+
+            // Fees come out of what each side receives (Seller: quote, Buyer: base)
+            let (maker_bps, taker_bps) = (self.gov_params.dex_maker_fee_bps, self.gov_params.dex_taker_fee_bps);
+            let (buyer_bps, seller_bps) = if side == "BUY" { (taker_bps, maker_bps) } else { (maker_bps, taker_bps) };
+            let seller_fee = fee_for(value, seller_bps);
+            let buyer_fee = fee_for(trade_amt, buyer_bps);
+
+            // Settlement: Seller gets quote, Buyer gets base (fees never exceed the amounts they come from)
+            let s_bal = self.get_balance(seller, &pair.quote);
+            self.set_balance(seller, &pair.quote, s_bal.saturating_add(value.saturating_sub(seller_fee)));
+            let b_bal = self.get_balance(buyer, &pair.base);
+            self.set_balance(buyer, &pair.base, b_bal.saturating_add(trade_amt.saturating_sub(buyer_fee)));
+            self.collect_dex_fee(&pid, &pair.quote, seller_fee);
+            self.collect_dex_fee(&pid, &pair.base, buyer_fee);
+            let (maker_fee, maker_fee_asset, taker_fee, taker_fee_asset) = if side == "BUY" {
+                (seller_fee, &pair.quote, buyer_fee, &pair.base)
+            } else {
+                (buyer_fee, &pair.base, seller_fee, &pair.quote)
+            };
+
+            // Taker BUY locked at its own limit: refund the price improvement
+            if side == "BUY" && order.price > maker_price {
+                // Part of the checked escrow (limit * amount), cannot overflow
+                let refund = (order.price - maker_price).saturating_mul(trade_amt);
+                let t_bal = self.get_balance(&order.creator, &pair.quote);
+                self.set_balance(&order.creator, &pair.quote, t_bal.saturating_add(refund));
+            }
+
+            self.block_trades.push(Trade {
+                id: format!("{}:{}", order.id, fills),
+                pair: pid.clone(),
+                maker_order: maker_id.clone(),
+                taker_order: order.id.clone(),
+                maker: maker.creator.clone(),
+                taker: order.creator.clone(),
+                taker_side: side.to_string(),
+                price: maker_price,
+                amount: trade_amt,
+                quote_amount: value,
+                maker_fee,
+                taker_fee,
+                maker_fee_asset: maker_fee_asset.clone(),
+                taker_fee_asset: taker_fee_asset.clone(),
+                block_height: self.height,
+                timestamp: self.time,
+                tx_hash: order.id.clone(),
+            });
+            fills += 1;
+            order.amount -= trade_amt;
+
+            // Update Maker
+            if maker.amount == trade_amt {
+                self.orders.remove(&maker_id);
+                let key = (pid.clone(), maker.price, maker.sequence);
+                if side == "BUY" { self.asks.remove(&key); } else { self.bids.remove(&key); }
+            } else if let Some(m) = self.orders.get_mut(&maker_id) {
+                m.amount -= trade_amt;
+            }
+        }
+
+        // 4. Save Remainder (IOC / FOK / Market and killed remainders are refunded)
+        if order.amount > 0 && (killed || !order.order_type.rests_on_book()) {
+            self.refund_order(&order);
+        } else if order.amount > 0 {
+            let key = (pid, order.price, order.sequence);
+            if side == "BUY" {
+                self.bids.insert(key, order.id.clone());
+            } else {
+                self.asks.insert(key, order.id.clone());
+            }
+            self.orders.insert(order.id.clone(), order);
+        }
+        true
+    }
+
+    // AMM: AddLiquidity carries "TokenA/TokenB" in token, amount A in amount, amount B in price
+    pub fn check_add_liquidity(&self, tx: &Transaction) -> Result<(String, String, u64), String> {
+        let parts: Vec<&str> = tx.token.split('/').collect();
+        if parts.len() != 2 || parts[0] == parts[1] {
+            return Err("Pool must be TokenA/TokenB".to_string());
+        }
+        let (token_a, token_b) = (parts[0], parts[1]);
+        let (amount_a, amount_b) = (tx.amount, tx.price);
+        if self.get_balance(&tx.sender, token_a) < amount_a || self.get_balance(&tx.sender, token_b) < amount_b {
+            return Err("Insufficient balance".to_string());
+        }
+        self.check_token_movement(token_a, Some(&tx.sender), None)?;
+        self.check_token_movement(token_b, Some(&tx.sender), None)?;
+
+        let shares = match self.pools.get(&tx.token) {
+            Some(p) if p.total_shares > 0 => {
+                let s_a = amount_a as u128 * p.total_shares as u128 / p.reserve_a.max(1) as u128;
+                let s_b = amount_b as u128 * p.total_shares as u128 / p.reserve_b.max(1) as u128;
+                s_a.min(s_b) as u64
+            }
+            _ => ((amount_a as f64 * amount_b as f64).sqrt()) as u64,
+        };
+        if shares == 0 {
+            return Err("Deposit too small".to_string());
+        }
+        Ok((token_a.to_string(), token_b.to_string(), shares))
+    }
+
+    fn apply_add_liquidity(&mut self, tx: &Transaction) -> bool {
+        let (token_a, token_b, shares) = match self.check_add_liquidity(tx) {
+            Ok(r) => r,
+            Err(e) => {
+                println!("[AMM] Rejected Add Liquidity: {}", e);
+                return false;
+            }
+        };
+        let sender = &tx.sender;
+        let (amount_a, amount_b) = (tx.amount, tx.price);
+
+        // 1. Debit User
+        let old_a = self.get_balance(sender, &token_a);
+        self.set_balance(sender, &token_a, old_a - amount_a);
+        let old_b = self.get_balance(sender, &token_b);
+        self.set_balance(sender, &token_b, old_b - amount_b);
+
+        // 2. Update Pool
+        self.pools.entry(tx.token.clone()).or_insert(Pool {
+            token_a,
+            token_b,
+            reserve_a: 0,
+            reserve_b: 0,
+            total_shares: 0,
+            price_a_cumulative: 0,
+            price_b_cumulative: 0,
+            last_update: 0,
+        });
+        self.observe_pool(&tx.token);
+        let pool = self.pools.get_mut(&tx.token).unwrap();
+        pool.reserve_a += amount_a;
+        pool.reserve_b += amount_b;
+        pool.total_shares += shares;
+
+        // 3. Credit LP Tokens
+        let lp_token = format!("LP-{}", tx.token);
+        let old_lp = self.get_balance(sender, &lp_token);
+        self.set_balance(sender, &lp_token, old_lp + shares);
+        true
+    }
+
+    // RemoveLiquidity: pool ID in token, LP shares in amount
+    pub fn check_remove_liquidity(&self, tx: &Transaction) -> Result<(String, String, u64, u64), String> {
+        let pool = self.pools.get(&tx.token).ok_or("Unknown pool".to_string())?;
+        let shares = tx.amount;
+        if shares == 0 || self.get_balance(&tx.sender, &format!("LP-{}", tx.token)) < shares || pool.total_shares < shares {
+            return Err("Insufficient LP shares".to_string());
+        }
+        let amount_a = (shares as u128 * pool.reserve_a as u128 / pool.total_shares as u128) as u64;
+        let amount_b = (shares as u128 * pool.reserve_b as u128 / pool.total_shares as u128) as u64;
+        if amount_a == 0 && amount_b == 0 {
+            return Err("Withdrawal too small".to_string());
+        }
+        self.check_token_movement(&pool.token_a, None, Some(&tx.sender))?;
+        self.check_token_movement(&pool.token_b, None, Some(&tx.sender))?;
+        Ok((pool.token_a.clone(), pool.token_b.clone(), amount_a, amount_b))
+    }
+
+    fn apply_remove_liquidity(&mut self, tx: &Transaction) -> bool {
+        let (token_a, token_b, amount_a, amount_b) = match self.check_remove_liquidity(tx) {
+            Ok(r) => r,
+            Err(e) => {
+                println!("[AMM] Rejected Remove Liquidity: {}", e);
+                return false;
+            }
+        };
+        let sender = &tx.sender;
+        let shares = tx.amount;
+
+        // 1. Debit LP
+        let lp_token = format!("LP-{}", tx.token);
+        let old_lp = self.get_balance(sender, &lp_token);
+        self.set_balance(sender, &lp_token, old_lp - shares);
+
+        // 2. Update Pool
+        self.observe_pool(&tx.token);
+        if let Some(pool) = self.pools.get_mut(&tx.token) {
+            pool.total_shares -= shares;
+            pool.reserve_a -= amount_a;
+            pool.reserve_b -= amount_b;
+        }
+
+        // 3. Credit Assets
+        let old_a = self.get_balance(sender, &token_a);
+        self.set_balance(sender, &token_a, old_a + amount_a);
+        let old_b = self.get_balance(sender, &token_b);
+        self.set_balance(sender, &token_b, old_b + amount_b);
+        true
+    }
+
+    // Pool trading `token_in` against `token_out`: (PoolID, A -> B direction)
+    pub fn pool_for(&self, token_in: &str, token_out: &str) -> Option<(String, bool)> {
+        let forward = format!("{}/{}", token_in, token_out);
+        if self.pools.contains_key(&forward) {
+            return Some((forward, true));
+        }
+        let reverse = format!("{}/{}", token_out, token_in);
+        self.pools.contains_key(&reverse).then_some((reverse, false))
+    }
+
+    fn validate_path(path: &[String]) -> Result<(), String> {
+        if path.len() < 2 || path.len() > MAX_SWAP_HOPS + 1 {
+            return Err(format!("Path must have 1 to {} hops", MAX_SWAP_HOPS));
+        }
+        let unique: BTreeSet<&String> = path.iter().collect();
+        if unique.len() != path.len() {
+            return Err("Path visits a token twice".to_string());
+        }
+        Ok(())
+    }
+
+    // Exact input: hops of a swap along `path` as (PoolID, A -> B, Output) on the current reserves
+    pub fn simulate_path(&self, path: &[String], amount_in: u64) -> Result<Vec<(String, bool, u64)>, String> {
+        ChainState::validate_path(path)?;
+        let mut hops = Vec::new();
+        let mut amount = amount_in;
+        for step in path.windows(2) {
+            let (pool_id, a_to_b) = self.pool_for(&step[0], &step[1])
+                .ok_or_else(|| format!("No pool for {} -> {}", step[0], step[1]))?;
+            amount = self.pools[&pool_id].amount_out(a_to_b, amount);
+            if amount == 0 {
+                return Err(format!("No output at {}", pool_id));
+            }
+            hops.push((pool_id, a_to_b, amount));
+        }
+        Ok(hops)
+    }
+
+    // Exact output: required input and the hops, computed backwards from `amount_out`
+    pub fn simulate_path_exact_out(&self, path: &[String], amount_out: u64) -> Result<(u64, Vec<SwapHop>), String> {
+        ChainState::validate_path(path)?;
+        if amount_out == 0 {
+            return Err("Output must be positive".to_string());
+        }
+        let mut hops = Vec::new();
+        let mut amount = amount_out;
+        for step in path.windows(2).rev() {
+            let (pool_id, a_to_b) = self.pool_for(&step[0], &step[1])
+                .ok_or_else(|| format!("No pool for {} -> {}", step[0], step[1]))?;
+            let input = self.pools[&pool_id].amount_in(a_to_b, amount)
+                .ok_or_else(|| format!("Insufficient liquidity at {}", pool_id))?;
+            hops.push((pool_id, a_to_b, amount));
+            amount = input;
+        }
+        hops.reverse();
+        Ok((amount, hops))
+    }
+
+    // Swap (single pool: token = PoolID, receiver = SWAP_A_TO_B / SWAP_B_TO_A) or RouteSwap (token = input,
+    // path in data). Bounds are read from the signed data: exact input (amount = input, min_output,
+    // tx.price only for legacy swaps without it) or exact output (amount = output, max_input).
+    pub fn swap_params(&self, tx: &Transaction) -> Result<(Vec<String>, SwapParams), String> {
+        let params = SwapParams::from_data(&tx.data)?;
+        if tx.tx_type == TxType::RouteSwap {
+            if params.path.first() != Some(&tx.token) {
+                return Err("Path must start with the input token".to_string());
+            }
+            return Ok((params.path.clone(), params));
+        }
+        let pool = self.pools.get(&tx.token).ok_or("Unknown pool".to_string())?;
+        let path = if tx.receiver == "SWAP_A_TO_B" {
+            vec![pool.token_a.clone(), pool.token_b.clone()]
+        } else {
+            vec![pool.token_b.clone(), pool.token_a.clone()]
+        };
+        Ok((path, params))
+    }
+
+    pub fn check_swap_deadline(&self, tx: &Transaction, height: u64, time: u64) -> Result<(), String> {
+        let params = SwapParams::from_data(&tx.data)?;
+        if params.is_expired(height, time) {
+            return Err("Swap deadline passed".to_string());
+        }
+        Ok(())
+    }
+
+    // (Input, Hops) of a swap on the current reserves
+    pub fn check_swap(&self, tx: &Transaction) -> Result<(u64, Vec<SwapHop>), String> {
+        let (path, params) = self.swap_params(tx)?;
+        let (token_in, token_out) = (&path[0], &path[path.len() - 1]);
+        self.check_token_movement(token_in, Some(&tx.sender), None)?;
+        self.check_token_movement(token_out, None, Some(&tx.sender))?;
+        // Intermediate tokens only pass through pools, but not while paused
+        for token in &path[1..path.len() - 1] {
+            self.check_token_movement(token, None, None)?;
+        }
+        let (input, hops) = if params.exact_output {
+            let max_input = params.max_input.ok_or("Exact output swap without max_input".to_string())?;
+            let (input, hops) = self.simulate_path_exact_out(&path, tx.amount)?;
+            if input > max_input {
+                return Err(format!("Input {} above maximum {}", input, max_input));
+            }
+            (input, hops)
+        } else {
+            let min_output = params.min_output.unwrap_or(tx.price);
+            let hops = self.simulate_path(&path, tx.amount)?;
+            let output = hops.last().map(|h| h.2).unwrap_or(0);
+            if output < min_output {
+                return Err(format!("Output {} below minimum {}", output, min_output));
+            }
+            (tx.amount, hops)
+        };
+        if input == 0 || self.get_balance(&tx.sender, token_in) < input {
+            return Err(format!("Insufficient {} balance", token_in));
+        }
+        Ok((input, hops))
+    }
+
+    fn apply_swap(&mut self, tx: &Transaction) -> bool {
+        // Past its deadline the swap is dropped (nonce consumed, no funds moved) instead of invalidating the block
+        if let Err(e) = self.check_swap_deadline(tx, self.height, self.time) {
+            println!("[AMM] Swap not executed: {}", e);
+            return true;
+        }
+        let (amount_in, hops) = match self.check_swap(tx) {
+            Ok(h) => h,
+            Err(e) => {
+                println!("[AMM] Rejected Swap: {}", e);
+                return false;
+            }
+        };
+        let (path, _) = self.swap_params(tx).unwrap_or_default();
+        let sender = &tx.sender;
+
+        // 1. Debit Input
+        let bal_in = self.get_balance(sender, &path[0]);
+        self.set_balance(sender, &path[0], bal_in - amount_in);
+
+        // 2. Update Pools hop by hop
+        let mut input = amount_in;
+        for (pool_id, a_to_b, output) in &hops {
+            self.observe_pool(pool_id);
+            if let Some(pool) = self.pools.get_mut(pool_id) {
+                if *a_to_b {
+                    pool.reserve_a += input;
+                    pool.reserve_b -= output;
+                } else {
+                    pool.reserve_b += input;
+                    pool.reserve_a -= output;
+                }
+            }
+            // Rough price: input per output * 10^8
+            self.block_swaps.push(SwapRecord {
+                pool: pool_id.clone(),
+                price: (input as u128 * 100_000_000 / *output as u128) as u64,
+                volume: *output,
+                timestamp: self.time,
+            });
+            input = *output;
+        }
+
+        // 3. Credit Output
+        let token_out = &path[path.len() - 1];
+        let bal_out = self.get_balance(sender, token_out);
+        self.set_balance(sender, token_out, bal_out + input);
+        true
+    }
+
+    // Best route over all simple paths of up to MAX_SWAP_HOPS pools (read-only).
+    // Exact input: most output for `amount`. Exact output: least input for `amount` out.
+    pub fn best_route(&self, token_in: &str, token_out: &str, amount: u64, exact_output: bool) -> Option<RouteQuote> {
+        let mut best: Option<RouteQuote> = None;
+        let mut stack: Vec<Vec<String>> = vec![vec![token_in.to_string()]];
+        while let Some(path) = stack.pop() {
+            let last = path.last().unwrap().clone();
+            if last == token_out {
+                let simulated = if exact_output {
+                    self.simulate_path_exact_out(&path, amount)
+                } else {
+                    self.simulate_path(&path, amount).map(|hops| (amount, hops))
+                };
+                if let Ok((amount_in, hops)) = simulated {
+                    let quote = self.quote_route(&path, amount_in, &hops);
+                    let better = best.as_ref().is_none_or(|b| {
+                        if exact_output { quote.amount_in < b.amount_in } else { quote.amount_out > b.amount_out }
+                    });
+                    if better {
+                        best = Some(quote);
+                    }
+                }
+                continue;
+            }
+            if path.len() > MAX_SWAP_HOPS { continue; }
+            for pool in self.pools.values() {
+                let next = if pool.token_a == last { &pool.token_b } else if pool.token_b == last { &pool.token_a } else { continue };
+                if !path.contains(next) {
+                    let mut extended = path.clone();
+                    extended.push(next.clone());
+                    stack.push(extended);
+                }
+            }
+        }
+        best
+    }
+
+    pub fn quote_route(&self, path: &[String], amount_in: u64, hops: &[(String, bool, u64)]) -> RouteQuote {
+        // Spot output: amount_in at each pool's marginal price, after the 0.3% fee
+        let mut spot = amount_in as f64;
+        for (pool_id, a_to_b, _) in hops {
+            let pool = &self.pools[pool_id];
+            let (rin, rout) = if *a_to_b { (pool.reserve_a, pool.reserve_b) } else { (pool.reserve_b, pool.reserve_a) };
+            spot = spot * 0.997 * rout as f64 / rin.max(1) as f64;
+        }
+        let amount_out = hops.last().map(|h| h.2).unwrap_or(0);
+        let impact = if spot > 0.0 { (1.0 - amount_out as f64 / spot).max(0.0) } else { 0.0 };
+        RouteQuote {
+            path: path.to_vec(),
+            pools: hops.iter().map(|h| h.0.clone()).collect(),
+            amount_in,
+            amounts_out: hops.iter().map(|h| h.2).collect(),
+            amount_out,
+            price_impact_bps: (impact * 10_000.0).round() as u64,
+        }
+    }
+
+    // Condition scripts of a transaction (OpTwap reads this state), checked on top of its signature at mempool
+    // admission and again in block validation against the parent block's state, so they are consensus rules.
+    // Only a locking script other than the standard P2PKH output lock is a condition: that lock and the
+    // <sig> <pubkey> unlocking script written by `sign()` are covered by the ECDSA signature check.
+    pub fn check_scripts(&self, tx: &Transaction) -> Result<(), String> {
+        if tx.script_pub_key.ops.is_empty() || tx.script_pub_key.is_p2pkh() {
+            return Ok(());
+        }
+        let mut vm = VirtualMachine::with_oracle(self);
+        // 1. Unlocking Script (its items stay on the stack for the locking script)
+        if !vm.run(&tx.script_sig, tx) {
+            return Err("ScriptSig execution failed".to_string());
+        }
+        // 2. Locking Script: must end with TRUE on the stack
+        if !vm.execute(&tx.script_pub_key, tx) {
+            return Err("ScriptPubKey execution failed".to_string());
+        }
+        Ok(())
+    }
+
+    // Price oracle: advances the pool's accumulators to the block time and records an observation
+    fn observe_pool(&mut self, pool_id: &str) {
+        let time = self.time;
+        let Some(pool) = self.pools.get_mut(pool_id) else { return };
+        if !pool.accumulate(time) { return; }
+        let history = self.price_observations.entry(pool_id.to_string()).or_default();
+        history.push_back(PriceObservation {
+            time,
+            price_a_cumulative: pool.price_a_cumulative,
+            price_b_cumulative: pool.price_b_cumulative,
+        });
+        if history.len() > MAX_PRICE_OBSERVATIONS {
+            history.pop_front();
+        }
+    }
+
+    // Cumulatives at any `time` covered by the oracle history (exact: prices are constant between observations)
+    pub fn cumulative_at(&self, pool_id: &str, time: u64) -> Option<(u128, u128)> {
+        let pool = self.pools.get(pool_id)?;
+        if time >= pool.last_update {
+            return Some(pool.cumulative_at(time));
+        }
+        let history = self.price_observations.get(pool_id)?;
+        let idx = history.partition_point(|o| o.time <= time);
+        if idx == 0 {
+            return None; // Before the oldest observation
+        }
+        let prev = &history[idx - 1];
+        let (next_time, next_a, next_b) = match history.get(idx) {
+            Some(next) => (next.time, next.price_a_cumulative, next.price_b_cumulative),
+            None => (pool.last_update, pool.price_a_cumulative, pool.price_b_cumulative),
+        };
+        let span = next_time.saturating_sub(prev.time).max(1) as u128;
+        let elapsed = (time - prev.time) as u128;
+        let price_a = next_a.wrapping_sub(prev.price_a_cumulative) / span;
+        let price_b = next_b.wrapping_sub(prev.price_b_cumulative) / span;
+        Some((
+            prev.price_a_cumulative.wrapping_add(price_a.wrapping_mul(elapsed)),
+            prev.price_b_cumulative.wrapping_add(price_b.wrapping_mul(elapsed)),
+        ))
+    }
+
+    // Time-weighted average prices over [start, end], end no later than the last block
+    pub fn twap(&self, pool_id: &str, start: u64, end: u64) -> Result<Twap, String> {
+        if !self.pools.contains_key(pool_id) {
+            return Err("Unknown pool".to_string());
+        }
+        if end <= start {
+            return Err("Window must end after it starts".to_string());
+        }
+        if end > self.time {
+            return Err("Window ends after the last block".to_string());
+        }
+        let (start_a, start_b) = self.cumulative_at(pool_id, start).ok_or("No price history at window start".to_string())?;
+        let (end_a, end_b) = self.cumulative_at(pool_id, end).ok_or("No price history at window end".to_string())?;
+        let span = (end - start) as u128;
+        Ok(Twap {
+            pool: pool_id.to_string(),
+            start,
+            end,
+            price_a: u64::try_from(end_a.wrapping_sub(start_a) / span).unwrap_or(u64::MAX),
+            price_b: u64::try_from(end_b.wrapping_sub(start_b) / span).unwrap_or(u64::MAX),
+        })
+    }
+
+    // Order book fee: the LP share goes into the same pair's AMM pool (if any), the rest to the fee recipient
+    fn collect_dex_fee(&mut self, pid: &str, asset: &str, fee: u64) {
+        if fee == 0 { return; }
+        let mut lp_share = (fee as u128 * self.gov_params.dex_fee_lp_percent as u128 / 100) as u64;
+        if lp_share > 0 && self.pools.contains_key(pid) {
+            self.observe_pool(pid);
+        }
+        match self.pools.get_mut(pid) {
+            Some(pool) if pool.token_a == asset => pool.reserve_a = pool.reserve_a.saturating_add(lp_share),
+            Some(pool) if pool.token_b == asset => pool.reserve_b = pool.reserve_b.saturating_add(lp_share),
+            _ => lp_share = 0,
+        }
+        let recipient = self.gov_params.dex_fee_recipient.clone();
+        let bal = self.get_balance(&recipient, asset);
+        self.set_balance(&recipient, asset, bal.saturating_add(fee - lp_share));
+    }
+
+    // CancelOrder: tx.token holds the Order ID
+    pub fn check_cancel_order(&self, tx: &Transaction) -> Result<&Order, String> {
+        let order = self.orders.get(&tx.token).ok_or("Unknown order".to_string())?;
+        if order.creator != tx.sender {
+            return Err("Not the order creator".to_string());
+        }
+        Ok(order)
+    }
+
+    fn apply_cancel_order(&mut self, tx: &Transaction) -> bool {
+        if let Err(e) = self.check_cancel_order(tx) {
+            println!("[DEX] Rejected Cancel: {}", e);
+            return false;
+        }
+        self.close_order(&tx.token);
+        true
+    }
+
+    // Removes a resting order from the book and refunds its remaining escrow
+    fn close_order(&mut self, id: &str) -> Option<Order> {
+        let order = self.orders.remove(id)?;
+        // Cleanup Indices
+        let key = (order.pair.clone(), order.price, order.sequence);
+        if order.side == "BUY" {
+            self.bids.remove(&key);
+        } else {
+            self.asks.remove(&key);
+        }
+        self.refund_order(&order);
+        Some(order)
+    }
+
+    fn refund_order(&mut self, order: &Order) {
+        let (asset, amount) = if order.side == "BUY" {
+            (&order.quote, order.price * order.amount)
+        } else {
+            (&order.token, order.amount)
+        };
+        let bal = self.get_balance(&order.creator, asset);
+        self.set_balance(&order.creator, asset, bal + amount);
+    }
+
+    fn expire_orders(&mut self) {
+        let mut expired: Vec<String> = self.orders.values()
+            .filter(|o| o.expiry_height.is_some_and(|h| self.height >= h) || o.expiry_time.is_some_and(|t| self.time >= t))
+            .map(|o| o.id.clone())
+            .collect();
+        if expired.is_empty() { return; }
+        expired.sort();
+        for id in &expired {
+            self.close_order(id);
+        }
+        println!("[DEX] Expired {} orders at height {}", expired.len(), self.height);
+    }
+
+    pub fn holder_count(&self, token: &str) -> usize {
+        self.balances.values().filter(|t| t.get(token).is_some_and(|b| *b > 0)).count()
+    }
+
+    // VLT escrowed by open BUY orders and AMM pool reserves
+    pub fn locked_vlt(&self) -> u64 {
+        // BUY escrows quote, SELL escrows base
+        let in_orders: u64 = self.orders.values()
+            .map(|o| match o.side.as_str() {
+                "BUY" if o.quote == "VLT" => o.price.saturating_mul(o.amount),
+                "SELL" if o.token == "VLT" => o.amount,
+                _ => 0,
+            })
+            .sum();
+        let in_pools: u64 = self.pools.values()
+            .map(|p| {
+                let a = if p.token_a == "VLT" { p.reserve_a } else { 0 };
+                let b = if p.token_b == "VLT" { p.reserve_b } else { 0 };
+                a + b
+            })
+            .sum();
+        in_orders + in_pools
+    }
+
+    pub fn get_unbonding_total(&self, address: &str) -> u64 {
+        self.unbonding.get(address).map(|q| q.iter().map(|u| u.amount).sum()).unwrap_or(0)
+    }
+
+    pub fn get_delegated_total(&self, validator: &str) -> u64 {
+        self.delegations.get(validator).map(|d| d.values().sum()).unwrap_or(0)
+    }
+
+    // Stake backing a validator's block bonus: own Stake + Delegations
+    pub fn get_validator_stake(&self, validator: &str) -> u64 {
+        self.stakes.get(validator).unwrap_or(&0).saturating_add(self.get_delegated_total(validator))
+    }
+
+    // Staking payouts for one block: `inflation` split pro rata over all bonded VLT.
+    // Each validator keeps its own share plus its commission on the delegated share, the rest goes to delegators.
+    pub fn staking_payouts(&self, inflation: u64) -> Vec<(String, u64)> {
+        let total_bonded: u128 = self.stakes.values().map(|v| *v as u128).sum::<u128>()
+            + self.delegations.values().flat_map(|d| d.values()).map(|v| *v as u128).sum::<u128>();
+        if total_bonded == 0 { return Vec::new(); }
+
+        let mut validators: Vec<&String> = self.stakes.keys().chain(self.delegations.keys()).collect();
+        validators.sort();
+        validators.dedup();
+
+        let mut payouts: BTreeMap<String, u64> = BTreeMap::new();
+        for validator in validators {
+            let own = *self.stakes.get(validator).unwrap_or(&0) as u128;
+            let delegated = self.get_delegated_total(validator) as u128;
+            let backing = own + delegated;
+            if backing == 0 { continue; }
+
+            let pool = backing * inflation as u128 / total_bonded;
+            let own_part = pool * own / backing;
+            let delegated_part = pool - own_part;
+            let commission_bps = (*self.commissions.get(validator).unwrap_or(&0)).min(MAX_COMMISSION_BPS) as u128;
+            let commission = delegated_part * commission_bps / MAX_COMMISSION_BPS as u128;
+
+            *payouts.entry(validator.clone()).or_insert(0) += (own_part + commission) as u64;
+            if let Some(delegators) = self.delegations.get(validator) {
+                let distributable = delegated_part - commission;
+                for (delegator, amount) in delegators {
+                    *payouts.entry(delegator.clone()).or_insert(0) += (distributable * *amount as u128 / delegated) as u64;
+                }
+            }
+        }
+
+        payouts.into_iter().filter(|(_, share)| *share > 0).collect()
+    }
+
+    // SetCommission: amount = new commission (bps). Lowering is always allowed, raising is rate limited once
+    // delegators are exposed to it.
+    pub fn check_set_commission(&self, tx: &Transaction) -> Result<(), String> {
+        if tx.amount > MAX_COMMISSION_BPS {
+            return Err(format!("Commission above {} bps", MAX_COMMISSION_BPS));
+        }
+        let current = *self.commissions.get(&tx.sender).unwrap_or(&0);
+        if tx.amount <= current || self.get_delegated_total(&tx.sender) == 0 {
+            return Ok(());
+        }
+        if tx.amount - current > MAX_COMMISSION_RAISE_BPS {
+            return Err(format!("Commission raise above {} bps (Current {})", MAX_COMMISSION_RAISE_BPS, current));
+        }
+        if let Some(last) = self.commission_raised.get(&tx.sender) {
+            if self.height < last + UNBONDING_PERIOD {
+                return Err(format!("Commission raised at #{}, next raise from #{}", last, last + UNBONDING_PERIOD));
+            }
+        }
+        Ok(())
+    }
+
+    // Slashing: check the Evidence against the offender (tx.receiver). Returns (Penalty, Equivocation Height).
+    pub fn check_slash(&self, tx: &Transaction) -> Result<(u64, u64), String> {
+        let evidence: EquivocationEvidence = serde_json::from_str(&tx.data).map_err(|_| "Malformed Evidence".to_string())?;
+        evidence.verify(&tx.receiver)?;
+        if tx.sender == tx.receiver {
+            return Err("Offender cannot report itself".to_string());
+        }
+
+        let height = evidence.height();
+        if self.slashed_offences.contains(&(tx.receiver.clone(), height)) {
+            return Err(format!("Offender already slashed for height {}", height));
+        }
+
+        let bonded = *self.stakes.get(&tx.receiver).unwrap_or(&0) as u128
+            + self.get_unbonding_total(&tx.receiver) as u128
+            + self.get_delegated_total(&tx.receiver) as u128;
+        let penalty = (bonded * SLASH_PERCENT as u128 / 100) as u64;
+        if penalty == 0 {
+            return Err("Offender has no bonded stake".to_string());
+        }
+        Ok((penalty, height))
+    }
+
+    fn apply_slash(&mut self, tx: &Transaction) -> bool {
+        let (_, height) = match self.check_slash(tx) {
+            Ok(res) => res,
+            Err(e) => {
+                println!("[Slashing] Rejected: {}", e);
+                return false;
+            }
+        };
+
+        // Own bonded VLT: take from active Stake first, then from the Unbonding queue (newest first)
+        let own_bonded = *self.stakes.get(&tx.receiver).unwrap_or(&0) as u128 + self.get_unbonding_total(&tx.receiver) as u128;
+        let mut penalty = (own_bonded * SLASH_PERCENT as u128 / 100) as u64;
+        let stake = *self.stakes.get(&tx.receiver).unwrap_or(&0);
+        let from_stake = stake.min(penalty);
+        if stake - from_stake > 0 {
+            self.stakes.insert(tx.receiver.clone(), stake - from_stake);
+        } else {
+            self.stakes.remove(&tx.receiver);
+        }
+
+        let mut remaining = penalty - from_stake;
+        if remaining > 0 {
+            if let Some(queue) = self.unbonding.get_mut(&tx.receiver) {
+                for entry in queue.iter_mut().rev() {
+                    let cut = entry.amount.min(remaining);
+                    entry.amount -= cut;
+                    remaining -= cut;
+                    if remaining == 0 { break; }
+                }
+                queue.retain(|u| u.amount > 0);
+                if queue.is_empty() {
+                    self.unbonding.remove(&tx.receiver);
+                }
+            }
+        }
+
+        // Delegators backing the offender share the risk
+        if let Some(delegators) = self.delegations.get_mut(&tx.receiver) {
+            for amount in delegators.values_mut() {
+                let cut = (*amount as u128 * SLASH_PERCENT as u128 / 100) as u64;
+                *amount -= cut;
+                penalty += cut;
+            }
+            delegators.retain(|_, amount| *amount > 0);
+            if delegators.is_empty() {
+                self.delegations.remove(&tx.receiver);
+            }
+        }
+
+        // Reward the Reporter, burn the rest
+        let reward = penalty * SLASH_REPORTER_PERCENT / 100;
+        let bal = self.get_balance(&tx.sender, "VLT");
+        self.set_balance(&tx.sender, "VLT", bal.saturating_add(reward));
+        self.slashed_burned += penalty - reward;
+        self.slashed_offences.insert((tx.receiver.clone(), height));
+
+        println!("[Slashing] {} slashed {} (Reporter reward {})", tx.receiver, penalty, reward);
+        true
+    }
+
+    pub fn apply_transaction(&mut self, tx: &Transaction) -> bool {
+        // 0. Token Compliance (Freeze / Pause / Allowlist)
+        if let Err(e) = self.check_tx_compliance(tx) {
+            println!("[Compliance] Rejected: {}", e);
+            return false;
+        }
+
+        // Issue checks run before any balance changes
+        let issue_meta = if tx.tx_type == TxType::IssueToken {
+            match self.check_issue(tx) {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    println!("[Token] Rejected Issue: {}", e);
+                    return false;
+                }
+            }
+        } else {
+            None
+        };
+
+        // 1. DEBIT
+        if tx.sender != "SYSTEM" {
+            // Determine what to debit
+            let fee_token = "VLT";
+            
+            // 1. Fee Debit Disabled by User Request
+            // Fees are no longer deducted.
+            let fee_paid_in_vlt = true; 
+            /*
+            let vlt_bal = self.get_balance(&tx.sender, "VLT");
+            let fee_paid_in_vlt = if vlt_bal >= tx.fee {
+                self.set_balance(&tx.sender, "VLT", vlt_bal - tx.fee);
+                true
+            } else {
+                false
+            };
+
+            if !fee_paid_in_vlt {
+                // Fallback: Try paying in the Asset itself (if not VLT)
+                let token_bal = self.get_balance(&tx.sender, &tx.token);
+                if tx.token != "VLT" && token_bal >= tx.fee {
+                     self.set_balance(&tx.sender, &tx.token, token_bal - tx.fee);
+                } else {
+                     println!("CRITICAL: Failed to debit fee for {} (No VLT or {} balance)", tx.sender, tx.token);
+                     return false;
+                }
+            }
+            */
+
+            // 2. Debit Amount (Token)
+            if tx.tx_type == TxType::Transfer || tx.tx_type == TxType::Stake || tx.tx_type == TxType::Delegate || tx.tx_type == TxType::Burn {
+                // For Stake, token is VLT, so we just deducted fee, now deduct amount.
+                // For Transfer, token could be anything.
+                let amount_token = &tx.token;
+                let current_amount_bal = self.get_balance(&tx.sender, amount_token);
+                
+                if let Some(new_amt_bal) = current_amount_bal.checked_sub(tx.amount) {
+                    self.set_balance(&tx.sender, amount_token, new_amt_bal);
+                } else {
+                     // println!("Failed to debit amount");
+                     return false;
+                }
+            } else if tx.tx_type == TxType::AddLiquidity {
+                 // Handled in separate logic block below? 
+                 // Actually AddLiquidity logic calls set_balance manually.
+                 // We should ensure we don't double debit.
+                 // The original code handled AddLiquidity separately in `apply_transaction` later?
+                 // No, original code had `match tx.tx_type` later for logic, but DEBIT was upfront.
+                 // Let's look at original DEBIT block.
+                 // It had `else if tx.tx_type == TxType::AddLiquidity` logic? 
+                 // No, the snippet shows `TxType::IssueToken`, `Stake`, `Transfer`.
+                 // AddLiquidity was NOT in the initial debit block I viewed?
+                 // Checking lines 518 in previous view... yes, AddLiquidity logic does the debit itself:
+                 // "self.set_balance(sender, token_a, old_a - amount_a);"
+                 // So we should NOT debit amount here for AddLiquidity.
+            }
+            
+            if tx.tx_type == TxType::Stake {
+                let current_stake = *self.stakes.get(&tx.sender).unwrap_or(&0);
+                if let Some(new_stake) = current_stake.checked_add(tx.amount) {
+                    self.stakes.insert(tx.sender.clone(), new_stake);
+                }
+            } else if tx.tx_type == TxType::Delegate {
+                let delegated = self.delegations.entry(tx.receiver.clone()).or_default().entry(tx.sender.clone()).or_insert(0);
+                *delegated = delegated.saturating_add(tx.amount);
+            }
+        }
+
+        // 2. CREDIT
+        if tx.tx_type == TxType::Transfer || tx.tx_type == TxType::IssueToken || tx.sender == "SYSTEM" {
+            let current_bal = self.get_balance(&tx.receiver, &tx.token);
+            if let Some(new_bal) = current_bal.checked_add(tx.amount) {
+                self.set_balance(&tx.receiver, &tx.token, new_bal);
+            }
+        } else if tx.tx_type == TxType::Unstake {
+             // Remove Stake, queue the VLT for release after the Unbonding Period
+             let current_stake = *self.stakes.get(&tx.sender).unwrap_or(&0);
+             if current_stake >= tx.amount {
+                  if let Some(new_stake) = current_stake.checked_sub(tx.amount) {
+                      self.stakes.insert(tx.sender.clone(), new_stake);
+                      // Queued under the staker (slashable while unbonding), paid to the receiver
+                      self.unbonding.entry(tx.sender.clone()).or_default().push(Unbonding {
+                          amount: tx.amount,
+                          release_height: self.height + UNBONDING_PERIOD,
+                          recipient: tx.receiver.clone(),
+                      });
+                  }
+             }
+        } else if tx.tx_type == TxType::Undelegate {
+             // Delegated VLT goes through the same Unbonding Period as Stake
+             let delegated = self.delegations.get(&tx.receiver).and_then(|d| d.get(&tx.sender)).copied().unwrap_or(0);
+             if delegated < tx.amount { return false; }
+             if let Some(delegators) = self.delegations.get_mut(&tx.receiver) {
+                 if delegated == tx.amount {
+                     delegators.remove(&tx.sender);
+                 } else {
+                     delegators.insert(tx.sender.clone(), delegated - tx.amount);
+                 }
+                 if delegators.is_empty() {
+                     self.delegations.remove(&tx.receiver);
+                 }
+             }
+             self.unbonding.entry(tx.sender.clone()).or_default().push(Unbonding {
+                 amount: tx.amount,
+                 release_height: self.height + UNBONDING_PERIOD,
+                 recipient: tx.sender.clone(),
+             });
+        } else if tx.tx_type == TxType::SetCommission {
+             if let Err(e) = self.check_set_commission(tx) {
+                 println!("[Staking] Rejected Commission: {}", e);
+                 return false;
+             }
+             if tx.amount > *self.commissions.get(&tx.sender).unwrap_or(&0) && self.get_delegated_total(&tx.sender) > 0 {
+                 self.commission_raised.insert(tx.sender.clone(), self.height);
+             }
+             self.commissions.insert(tx.sender.clone(), tx.amount);
+        }
+
+        if let Some(meta) = issue_meta {
+            self.tokens.insert(tx.token.clone(), tx.sender.clone());
+            if meta.has_any_control() {
+                self.token_compliance.insert(tx.token.clone(), TokenCompliance::default());
+            }
+            self.token_meta.insert(tx.token.clone(), meta);
+            self.token_supply.entry(tx.token.clone()).or_default().issued += tx.amount;
+        } else if tx.tx_type == TxType::Mint {
+            if let Err(e) = self.check_mint(tx) {
+                println!("[Token] Rejected Mint: {}", e);
+                return false;
+            }
+            let bal = self.get_balance(&tx.receiver, &tx.token);
+            self.set_balance(&tx.receiver, &tx.token, bal.saturating_add(tx.amount));
+            self.token_supply.entry(tx.token.clone()).or_default().issued += tx.amount;
+        } else if tx.tx_type == TxType::RenounceMint {
+            if let Err(e) = self.check_renounce_mint(tx) {
+                println!("[Token] Rejected Renounce: {}", e);
+                return false;
+            }
+            if let Some(meta) = self.token_meta.get_mut(&tx.token) {
+                meta.mintable = false;
+            }
+        } else if tx.tx_type == TxType::Burn {
+            // Debited above, the amount leaves circulation
+            self.token_supply.entry(tx.token.clone()).or_default().burned += tx.amount;
+        }
+
+        if tx.tx_type == TxType::Slash && !self.apply_slash(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::TokenControl && !self.apply_token_control(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::ListPair && !self.apply_list_pair(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::PlaceOrder && !self.apply_place_order(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::CancelOrder && !self.apply_cancel_order(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::AddLiquidity && !self.apply_add_liquidity(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::RemoveLiquidity && !self.apply_remove_liquidity(tx) {
+            return false;
+        }
+        if (tx.tx_type == TxType::Swap || tx.tx_type == TxType::RouteSwap) && !self.apply_swap(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::Approve && !self.apply_approve(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::TransferFrom && !self.apply_transfer_from(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::Attest && !self.apply_attestation(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::Propose && !self.apply_proposal(tx) {
+            return false;
+        }
+        if tx.tx_type == TxType::Vote && !self.apply_vote(tx) {
+            return false;
+        }
+        
+        // Nonce
+        if tx.sender != "SYSTEM" {
+            self.nonces.insert(tx.sender.clone(), tx.nonce);
+        }
+        true
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SupplyReport {
+    pub height: u64,
+    pub premine: u64,
+    pub subsidy: u64,     // Block rewards due by the Emission Schedule up to `height`
+    pub minted: u64,      // Actually minted by SYSTEM txs (Premine, Coinbase incl. fees, Dev Fee, Staking)
+    pub burned: u64,
+    pub total: u64,       // minted - burned
+    pub staked: u64,      // Stakes + Delegations
+    pub unbonding: u64,
+    pub locked: u64,      // Held by open DEX orders and AMM pools
+    pub circulating: u64, // total - staked - unbonding - locked
+    pub max_supply: u64,  // Premine + all scheduled subsidies (fee & staking mints excluded)
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SupplyAudit {
+    pub height: u64,
+    pub expected: u64, // Total supply (minted - burned)
+    pub balances: u64,
+    pub staked: u64,
+    pub unbonding: u64,
+    pub locked: u64,
+    pub difference: i64, // (balances + staked + unbonding + locked) - expected
+    pub unscheduled_mint: i64, // minted - premine - scheduled subsidy (fees & staking inflation)
+    pub ok: bool,
+}
+
+pub struct Blockchain {
+    pub chain: Vec<Block>,
+    pub pending_transactions: Vec<Transaction>,
+    pub difficulty: u32,
+    pub state: ChainState,
+    pub db: Option<Database>, 
+    pub params: ChainParams,
+    pub sig_cache: Arc<SignatureCache>, // Shared with P2P pre-validation (outside the chain lock)
+    deployment_cache: Mutex<HashMap<(String, String), DeploymentState>>, // (Deployment, Hash closing the previous window) -> State
+}
+
+impl Blockchain {
+    pub fn new() -> Self {
+        let mut blockchain = Blockchain {
+            chain: Vec::new(),
+            pending_transactions: Vec::new(),
+            // Standard Difficulty (Difficulty 1)
+            // This is the correct setting for a Mainnet-like environment.
+            difficulty: 0x1d00ffff,
+            state: ChainState::new(),
+            db: Database::new("volt.db").ok(), 
+            params: ChainParams::mainnet(),
+            sig_cache: Arc::new(SignatureCache::new()),
+            deployment_cache: Mutex::new(HashMap::new()),
+        };
+
+        if let Some(ref db) = blockchain.db {
+            if let Some(chain_data) = db.load_chain() {
+                blockchain.chain = chain_data;
+                // Restore Mempool
+                if let Ok(pending) = db.load_pending_txs() {
+                    blockchain.pending_transactions = pending;
+                    println!("[Chain] Restored {} pending transactions from DB", blockchain.pending_transactions.len());
+                }
+                blockchain.rebuild_state();
+
+                let audit = blockchain.audit_supply();
+                if !audit.ok {
+                    println!("[Audit] WARNING: Supply mismatch at #{} (Accounted - Expected = {})", audit.height, audit.difference);
+                }
+            } else {
+                blockchain.create_genesis_block();
+            }
+        } else {
+             blockchain.create_genesis_block();
+        }
+        blockchain
+    }
+
+    // Full replay from Genesis. Also re-indexes the DEX trade history (it may belong to a replaced chain).
+    pub fn rebuild_state(&mut self) {
+        if let Some(ref db) = self.db {
+            let _ = db.clear_trades();
+        }
+        self.state = Blockchain::replay_state(&self.chain, self.db.as_ref());
+    }
+
+    fn replay_state(blocks: &[Block], trade_index: Option<&Database>) -> ChainState {
+        let mut state = ChainState::new();
+        for block in blocks {
+            state.begin_block(block);
+            for tx in &block.transactions {
+                // If a historical transaction fails, we log it but continue (assume DB valid)
+                // In production, this might indicate corruption.
+                if !state.apply_transaction(tx) {
+                    println!("[Chain] Warning: Historical transaction application failed: {}", hex::encode(tx.get_hash()));
+                }
+            }
+            if let Some(db) = trade_index {
+                let _ = db.save_trades(&state.block_trades);
+                let _ = db.save_swaps(&state.block_swaps);
+            }
+        }
+        state
+    }
+
+    // Supply breakdown after block `height` (older heights are replayed from Genesis)
+    pub fn supply_at(&self, height: u64) -> SupplyReport {
+        let tip = self.chain.len() as u64 - 1;
+        if height >= tip {
+            return self.supply_report(&self.state, tip);
+        }
+        let state = Blockchain::replay_state(&self.chain[..=height as usize], None);
+        self.supply_report(&state, height)
+    }
+
+    fn supply_report(&self, state: &ChainState, height: u64) -> SupplyReport {
+        let emission = &self.params.emission;
+        let minted: u64 = self.chain[..=height as usize].iter()
+            .flat_map(|b| b.transactions.iter())
+            .filter(|tx| tx.sender == "SYSTEM" && tx.token == "VLT")
+            .map(|tx| tx.amount)
+            .sum();
+        let burned = state.slashed_burned + state.token_supply.get("VLT").map(|t| t.burned).unwrap_or(0);
+        let total = minted.saturating_sub(burned);
+        let staked = state.stakes.values().sum::<u64>() + state.delegations.values().flat_map(|d| d.values()).sum::<u64>();
+        let unbonding: u64 = state.unbonding.values().flatten().map(|u| u.amount).sum();
+        let locked = state.locked_vlt();
+
+        SupplyReport {
+            height,
+            premine: emission.premine,
+            subsidy: emission.subsidy_until(height),
+            minted,
+            burned,
+            total,
+            staked,
+            unbonding,
+            locked,
+            circulating: total.saturating_sub(staked + unbonding + locked),
+            max_supply: emission.max_supply(),
+        }
+    }
+
+    // Consistency Audit: every minted (and not burned) VLT must be held somewhere in the state
+    pub fn audit_supply(&self) -> SupplyAudit {
+        let report = self.supply_at(u64::MAX);
+        let balances: u64 = self.state.balances.values().filter_map(|t| t.get("VLT")).sum();
+        let accounted = balances as u128 + report.staked as u128 + report.unbonding as u128 + report.locked as u128;
+        let difference = accounted as i128 - report.total as i128;
+
+        SupplyAudit {
+            height: report.height,
+            expected: report.total,
+            balances,
+            staked: report.staked,
+            unbonding: report.unbonding,
+            locked: report.locked,
+            difference: difference as i64,
+            unscheduled_mint: (report.minted as i128 - report.premine as i128 - report.subsidy as i128) as i64,
+            ok: difference == 0,
+        }
+    }
+    
+    // Wrapper for API
+    pub fn apply_transaction_to_state(&mut self, tx: &Transaction) -> bool {
+        self.state.apply_transaction(tx)
+    }
+
+    pub fn get_balance(&self, address: &str, token: &str) -> u64 {
+        self.state.get_balance(address, token)
+    }
+    
+    // Helper to accessing state mutation
+    fn set_balance(&mut self, address: &str, token: &str, amount: u64) {
+        self.state.set_balance(address, token, amount);
+    }
+
+    fn create_genesis_block(&mut self) {
+        // PreMine (Emission Schedule): 1,050,000 VLT = 105,000,000,000,000 Atomic Units
+        let premine_tx = Transaction {
             sender: String::from("SYSTEM"),
-            receiver: String::from("024dea39ce2e873d5be2d8e092044a7dbd9cfa2dadcba5d32e9b141b7361422d56"),
-            amount: 105_000_000_000_000, 
+            receiver: self.params.emission.premine_address.clone(),
+            amount: self.params.emission.premine,
             signature: String::from("GENESIS"),
             timestamp: 0,
             token: String::from("VLT"),
@@ -302,910 +2233,2352 @@ impl Blockchain {
             price: 0,
             script_pub_key: crate::script::Script::new(),
             script_sig: crate::script::Script::new(),
+            data: String::new(),
+        };
+
+        // Genesis carries the chain's starting difficulty (Standard 0x1d00ffff)
+        let mut genesis_block = Block::new(0, String::from("0"), vec![premine_tx], self.difficulty as usize, 0);
+        
+        // FIX: Enforce Deterministic Genesis Timestamp and Hash for network compatibility
+        genesis_block.timestamp = 1767077203;
+        genesis_block.hash = genesis_block.calculate_hash();
+
+        self.chain.push(genesis_block.clone());
+        if let Some(ref db) = self.db {
+            let _ = db.save_block(&genesis_block);
+        }
+        // Fix: Apply Genesis transactions to State so balance shows up immediately
+        self.rebuild_state();
+    }
+
+    pub fn create_transaction(&mut self, transaction: Transaction) -> bool {
+        // DoS Protection: Per-Transaction Size & SigOp Limits
+        let tx_size = transaction.serialized_size();
+        if tx_size > MAX_TX_SIZE {
+            println!("Rejected: Transaction too large ({} bytes, Max {})", tx_size, MAX_TX_SIZE);
+            return false;
+        }
+        let tx_sigops = transaction.sig_op_count();
+        if tx_sigops > MAX_TX_SIGOPS {
+            println!("Rejected: Too many signature operations ({}, Max {})", tx_sigops, MAX_TX_SIGOPS);
+            return false;
+        }
+
+        // Signature first (cached: block validation will not re-verify), scripts only add conditions
+        if let Err(e) = self.sig_cache.verify(&transaction) {
+             println!("Transaction verification failed: {}", e);
+             return false;
+        }
+        // Phase 28: Smart Scripting Validation (re-run against the parent state when the block arrives)
+        if let Err(e) = self.state.check_scripts(&transaction) {
+             println!("{}", e);
+             return false;
+        }
+
+        if transaction.sender != "SYSTEM" {
+             let current_nonce = *self.state.nonces.get(&transaction.sender).unwrap_or(&0);
+             if transaction.nonce <= current_nonce {
+                 println!("Error: Invalid Nonce");
+                 return false;
+             }
+
+             // Governance: Minimum Fee
+             if transaction.fee < self.state.gov_params.min_fee {
+                 println!("Rejected: Fee too low. Required: {}, Provided: {}", self.state.gov_params.min_fee, transaction.fee);
+                 return false;
+             }
+
+             if let Err(e) = self.state.check_tx_compliance(&transaction) {
+                 println!("Rejected: {}", e);
+                 return false;
+             }
+        }
+
+        match transaction.tx_type {
+            TxType::IssueToken => {
+                 if let Err(e) = self.state.check_issue(&transaction) {
+                     println!("Rejected Issue: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Mint => {
+                 if let Err(e) = self.state.check_mint(&transaction) {
+                     println!("Rejected Mint: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Approve => {
+                 if let Err(e) = self.state.check_approve(&transaction, self.chain.len() as u64) {
+                     println!("Rejected Approve: {}", e);
+                     return false;
+                 }
+            },
+            TxType::TransferFrom => {
+                 if let Err(e) = self.state.check_transfer_from(&transaction, self.chain.len() as u64) {
+                     println!("Rejected TransferFrom: {}", e);
+                     return false;
+                 }
+                 // Double Spend: allowance and owner balance already claimed by pending txs
+                 let owner = &transaction.data;
+                 let pending_allowance: u64 = self.pending_transactions.iter()
+                     .filter(|t| t.tx_type == TxType::TransferFrom && &t.data == owner && t.sender == transaction.sender && t.token == transaction.token)
+                     .map(|t| t.amount)
+                     .sum();
+                 let pending_owner: u64 = self.pending_transactions.iter()
+                     .filter(|t| t.token == transaction.token && ((&t.sender == owner && (t.tx_type == TxType::Transfer || t.tx_type == TxType::Burn)) || (t.tx_type == TxType::TransferFrom && &t.data == owner)))
+                     .map(|t| t.amount)
+                     .sum();
+                 let height = self.chain.len() as u64;
+                 if self.state.get_allowance(owner, &transaction.sender, &transaction.token, height) < transaction.amount + pending_allowance
+                     || self.get_balance(owner, &transaction.token) < transaction.amount + pending_owner {
+                     println!("Rejected TransferFrom: Allowance or owner balance claimed by pending transactions");
+                     return false;
+                 }
+            },
+            TxType::TokenControl => {
+                 if let Err(e) = self.state.check_token_control(&transaction) {
+                     println!("Rejected Token Control: {}", e);
+                     return false;
+                 }
+            },
+            TxType::RenounceMint => {
+                 if let Err(e) = self.state.check_renounce_mint(&transaction) {
+                     println!("Rejected Renounce: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Burn => {
+                 if transaction.token == "VLT" { return false; } // Can prevent burning VLT if desired, or allow it.
+                 // Pending transfers and burns of this token spend from the same balance
+                 let pending_spent: u64 = self.pending_transactions.iter()
+                     .filter(|t| t.token == transaction.token && ((t.sender == transaction.sender && (t.tx_type == TxType::Transfer || t.tx_type == TxType::Burn)) || (t.tx_type == TxType::TransferFrom && t.data == transaction.sender)))
+                     .map(|t| t.amount)
+                     .sum();
+                 if self.get_balance(&transaction.sender, &transaction.token) < transaction.amount + pending_spent { return false; }
+            },
+            TxType::ListPair => {
+                 if let Err(e) = self.state.check_list_pair(&transaction) {
+                     println!("Rejected Pair: {}", e);
+                     return false;
+                 }
+            },
+            TxType::PlaceOrder => {
+                 // Funds are locked and matched when the block is applied
+                 let lock = match self.state.check_place_order(&transaction) {
+                     Ok(l) => l,
+                     Err(e) => {
+                         println!("Rejected Order: {}", e);
+                         return false;
+                     }
+                 };
+                 let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+                 if let Err(e) = self.state.check_order_conditions(&transaction, &lock, self.chain.len() as u64, now) {
+                     println!("Rejected Order: {}", e);
+                     return false;
+                 }
+                 // Double Spend: escrow already claimed by pending orders
+                 let pending_locked: u64 = self.pending_transactions.iter()
+                     .filter(|t| t.sender == transaction.sender && t.tx_type == TxType::PlaceOrder)
+                     .filter_map(|t| self.state.order_lock(t).ok())
+                     .filter(|l| l.asset == lock.asset)
+                     .map(|l| l.amount)
+                     .sum();
+                 if self.get_balance(&transaction.sender, &lock.asset) < lock.amount + pending_locked {
+                     println!("Rejected Order: {} balance claimed by pending orders", lock.asset);
+                     return false;
+                 }
+            },
+            TxType::CancelOrder => {
+                 if let Err(e) = self.state.check_cancel_order(&transaction) {
+                     println!("Rejected Cancel: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Transfer => {
+                let bal = self.get_balance(&transaction.sender, &transaction.token);
+                
+                // V2: Dynamic Fee enforcement
+                // Rule: 0.1% of Amount + 1 VLT per pending tx
+                // ATOMIC UNITS:
+                // 1 VLT = 100,000,000
+                // 0.1% = amount / 1000
+                // 1 VLT congestion = 100,000,000 per tx
+                
+                let congestion_count = self.pending_transactions.len() as u64;
+                let congestion_surcharge = congestion_count * 100_000_000; // 1 VLT per tx
+                
+                let amount_factor = transaction.amount / 1000; // 0.1%
+                let min_fee = amount_factor + congestion_surcharge;
+                
+                // Enforce minimum of 0.001 VLT (100,000 units) base
+                let base_min = 100_000; 
+                let effective_min_fee = if min_fee < base_min { base_min } else { min_fee };
+                
+                /*
+                if transaction.fee < effective_min_fee {
+                    println!("Rejected: Fee too low. Required: {}, Provided: {}", effective_min_fee, transaction.fee);
+                    return false;
+                }
+                */
+
+                // Fee excluded from requirement
+                let required = transaction.amount; // + transaction.fee;
+                
+                // Fix Double Spend: Check pending transactions
+                let pending_spent: u64 = self.pending_transactions.iter()
+                    .filter(|t| t.token == transaction.token && ((t.sender == transaction.sender && (t.tx_type == TxType::Transfer || t.tx_type == TxType::Burn)) || (t.tx_type == TxType::TransferFrom && t.data == transaction.sender)))
+                    .map(|t| t.amount) // + t.fee)
+                    .sum();
+
+                if bal < (required + pending_spent) { 
+                    println!("Rejected: Double Spend / Insufficient Funds (Pending: {}, New: {}, Bal: {})", pending_spent, required, bal);
+                    return false; 
+                }
+            },
+            TxType::Stake => {
+                 if transaction.token != "VLT" { return false; }
+                 let bal = self.get_balance(&transaction.sender, "VLT");
+                 if bal < transaction.amount { return false; }
+            },
+            TxType::Unstake => {
+                 // Check if staked amount >= amount
+                 let current_stake = *self.state.stakes.get(&transaction.sender).unwrap_or(&0);
+                 if current_stake < transaction.amount { return false; }
+            },
+            TxType::Delegate => {
+                 if transaction.token != "VLT" || transaction.amount == 0 { return false; }
+                 if transaction.receiver == transaction.sender { return false; } // Use Stake instead
+                 let valid_validator = hex::decode(&transaction.receiver).ok()
+                     .is_some_and(|bytes| k256::ecdsa::VerifyingKey::from_sec1_bytes(&bytes).is_ok());
+                 if !valid_validator { return false; }
+                 let bal = self.get_balance(&transaction.sender, "VLT");
+                 if bal < transaction.amount { return false; }
+            },
+            TxType::Undelegate => {
+                 let delegated = self.state.delegations.get(&transaction.receiver)
+                     .and_then(|d| d.get(&transaction.sender)).copied().unwrap_or(0);
+                 if delegated < transaction.amount { return false; }
+            },
+            TxType::SetCommission => {
+                 if let Err(e) = self.state.check_set_commission(&transaction) {
+                     println!("Rejected Commission: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Attest => {
+                 if let Err(e) = self.state.check_attestation(&transaction) {
+                     println!("Rejected Attestation: {}", e);
+                     return false;
+                 }
+                 // Gossip dedup: one pending attestation per staker and checkpoint
+                 if self.pending_transactions.iter().any(|t| t.tx_type == TxType::Attest && t.sender == transaction.sender && t.amount == transaction.amount) {
+                     return false;
+                 }
+            },
+            TxType::Propose => {
+                 if let Err(e) = self.state.check_proposal(&transaction) {
+                     println!("Rejected Proposal: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Vote => {
+                 if let Err(e) = self.state.check_vote(&transaction, self.chain.len() as u64) {
+                     println!("Rejected Vote: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Slash => {
+                 let height = match self.state.check_slash(&transaction) {
+                     Ok((_, height)) => height,
+                     Err(e) => {
+                         println!("Rejected Slash: {}", e);
+                         return false;
+                     }
+                 };
+                 // One pending report per offender and height
+                 let duplicate = self.pending_transactions.iter()
+                     .filter(|t| t.tx_type == TxType::Slash && t.receiver == transaction.receiver)
+                     .filter_map(|t| serde_json::from_str::<EquivocationEvidence>(&t.data).ok())
+                     .any(|e| e.height() == height);
+                 if duplicate {
+                     println!("Rejected Slash: Evidence already pending");
+                     return false;
+                 }
+            },
+            TxType::AddLiquidity => {
+                 if let Err(e) = self.state.check_add_liquidity(&transaction) {
+                     println!("Rejected Add Liquidity: {}", e);
+                     return false;
+                 }
+            },
+            TxType::RemoveLiquidity => {
+                 if let Err(e) = self.state.check_remove_liquidity(&transaction) {
+                     println!("Rejected Remove Liquidity: {}", e);
+                     return false;
+                 }
+            },
+            TxType::Swap | TxType::RouteSwap => {
+                 // Slippage and deadline are re-checked when the block is applied
+                 let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+                 if let Err(e) = self.state.check_swap_deadline(&transaction, self.chain.len() as u64, now) {
+                     println!("Rejected Swap: {}", e);
+                     return false;
+                 }
+                 if let Err(e) = self.state.check_swap(&transaction) {
+                     println!("Rejected Swap: {}", e);
+                     return false;
+                 }
+            },
+            TxType::IssueNFT => {
+                 // Check if NFT exists
+                 if self.state.nfts.contains_key(&transaction.token) { return false; }
+                 
+                 // Sender matches creator (logic simplified: anyone can mint uniquely named NFT)
+                 // Token field = NFT ID
+                 // Receiver field = URI (Metadata)
+                 
+                 let nft = NFT {
+                     id: transaction.token.clone(),
+                     owner: transaction.sender.clone(),
+                     uri: transaction.receiver.clone(), // HACK: Reusing receiver field for URI
+                     created_at: transaction.timestamp,
+                 };
+                 self.state.nfts.insert(nft.id.clone(), nft);
+            },
+            TxType::TransferNFT => {
+                 if let Some(nft) = self.state.nfts.get_mut(&transaction.token) {
+                     if nft.owner != transaction.sender { return false; }
+                     nft.owner = transaction.receiver.clone();
+                 } else { return false; }
+            },
+            TxType::BurnNFT => {
+                 if let Some(nft) = self.state.nfts.get(&transaction.token) {
+                     if nft.owner != transaction.sender { return false; }
+                 } else { return false; }
+                 self.state.nfts.remove(&transaction.token);
+            }
+        }
+
+        self.pending_transactions.push(transaction);
+        true
+    }
+
+    // Block Template Selection: Highest fee-per-byte first, bounded by MAX_BLOCK_SIZE / MAX_BLOCK_SIGOPS.
+    // Transactions of one sender stay in nonce order, so a tx is only eligible once its predecessor is in.
+    fn select_template_transactions(&self) -> Vec<Transaction> {
+        // Reserve room for the header and System Txs (Coinbase + Dev Fee + one payout per Staker)
+        let system_tx = Transaction::new(String::from("SYSTEM"), DEV_WALLET.to_string(), u64::MAX, "VLT".to_string(), 0);
+        let delegator_count: usize = self.state.delegations.values().map(|d| d.len()).sum();
+        let system_count = 2 + self.state.stakes.len() + delegator_count;
+        let header_reserve = 1_000;
+        let max_size = MAX_BLOCK_SIZE.saturating_sub(header_reserve + system_count * system_tx.serialized_size());
+
+        // Per-Sender queues ordered by nonce: (Tx, Size)
+        let mut sorted: Vec<&Transaction> = self.pending_transactions.iter().collect();
+        sorted.sort_by_key(|tx| tx.nonce);
+        let mut queues: HashMap<&str, VecDeque<(&Transaction, usize)>> = HashMap::new();
+        for tx in sorted {
+            queues.entry(tx.sender.as_str()).or_default().push_back((tx, tx.serialized_size()));
+        }
+
+        let mut selected = Vec::new();
+        let mut block_size = 0;
+        let mut block_sigops = 0;
+
+        loop {
+            // Best fee rate among the queue heads (fee_a / size_a vs fee_b / size_b, cross-multiplied)
+            let best = queues.iter()
+                .filter_map(|(sender, q)| q.front().map(|head| (*sender, *head)))
+                .max_by(|(_, (a, a_size)), (_, (b, b_size))| {
+                    (a.fee as u128 * *b_size as u128).cmp(&(b.fee as u128 * *a_size as u128))
+                        .then(b.timestamp.cmp(&a.timestamp))
+                });
+            let (sender, (tx, tx_size)) = match best {
+                Some(b) => b,
+                None => break,
+            };
+
+            // Script conditions may no longer hold (e.g. TWAP moved): the block would be rejected
+            if self.state.check_scripts(tx).is_err() {
+                queues.remove(sender);
+                continue;
+            }
+
+            let tx_sigops = tx.sig_op_count();
+            if block_size + tx_size > max_size || block_sigops + tx_sigops > MAX_BLOCK_SIGOPS {
+                // Later nonces of this sender depend on this tx, skip them all
+                queues.remove(sender);
+                continue;
+            }
+
+            if let Some(q) = queues.get_mut(sender) {
+                q.pop_front();
+            }
+            block_size += tx_size;
+            block_sigops += tx_sigops;
+            selected.push(tx.clone());
+        }
+
+        selected
+    }
+
+    // validator_key: staking key of miner_address, required to claim the stake bonus once "stakesig" is active
+    pub fn mine_pending_transactions(&mut self, miner_address: String, validator_key: Option<&SigningKey>) {
+        let height = self.chain.len() as u64;
+        let mut reward = self.calculate_reward(height);
+
+        // Phase 12: Fee Split Logic
+        let mut total_fees: u64 = 0;
+
+        let mut txs = self.select_template_transactions();
+        
+        for tx in &txs {
+             total_fees += tx.fee;
+        }
+        
+        if total_fees > 0 {
+             let dev_share = total_fees * self.state.gov_params.dev_fee_percent / 100; // Dev Tax (Governance)
+             let miner_share = total_fees - dev_share;
+             
+             // 1. Add Miner Share to Block Reward
+             reward += miner_share;
+             
+             // 2. Create Dev Tx
+             // Note: We create this from "SYSTEM" to avoid signature checks, but logically it comes from the fees.
+             // Since fees are deducted from sender in apply_transaction, we need to MINT this dev share?
+             // NO. apply_transaction deducts (amount + fee) from sender.
+             // It credits 'amount' to receiver.
+             // The 'fee' vanishes from state unless we credit it somewhere.
+             // So yes, we MINT the fee destination here.
+             
+             if dev_share > 0 {
+                  let dev_tx = Transaction::new(String::from("SYSTEM"), DEV_WALLET.to_string(), dev_share, "VLT".to_string(), 0);
+                  txs.push(dev_tx);
+             }
+        }
+        
+        // Hybrid Consensus: Stake Claim (must be committed & signed once "stakesig" is active)
+        let stake_sig_active = self.is_deployment_active("stakesig", height);
+        let can_sign = validator_key.is_some_and(|k| hex::encode(k.verifying_key().to_sec1_bytes()) == miner_address);
+        let my_stake = if can_sign || !stake_sig_active { self.state.get_validator_stake(&miner_address) } else { 0 };
+        let mut reward_tx = Transaction::new(String::from("SYSTEM"), miner_address.clone(), reward, "VLT".to_string(), 0);
+        if stake_sig_active { reward_tx.price = my_stake; } // Coinbase Stake Commitment
+        txs.insert(0, reward_tx); 
+
+        // Staking & Delegation Payouts (pro rata, validator commission on delegated share)
+        let staking_inflation = self.state.gov_params.staking_inflation;
+        for (recipient, share) in self.state.staking_payouts(staking_inflation) {
+             let stake_tx = Transaction::new(String::from("SYSTEM"), recipient, share, "VLT".to_string(), 0);
+             txs.push(stake_tx);
+        }
+
+        let previous_block = self.chain.last().unwrap();
+        let difficulty = self.get_next_difficulty();
+        
+        println!("Mining block {} [Difficulty: {}, Reward: {:.8} VLT]...", previous_block.index + 1, difficulty, reward as f64 / 100_000_000.0);
+
+        let mut new_block = Block::new(
+            previous_block.index + 1,
+            previous_block.hash.clone(),
+            txs,
+            difficulty as usize,
+            my_stake
+        );
+        new_block.version = self.compute_block_version(new_block.index);
+        new_block.hash = new_block.calculate_hash();
+
+        new_block.mine(difficulty as usize);
+        if let Some(key) = validator_key {
+            if can_sign && my_stake > 0 {
+                new_block.sign_validator(key);
+            }
+        }
+        
+        // Pass 2: Apply to state
+        self.state.begin_block(&new_block);
+        for tx in &new_block.transactions {
+            self.state.apply_transaction(tx);
+        }
+
+        self.chain.push(new_block.clone());
+        if let Some(ref db) = self.db {
+            let _ = db.save_block(&new_block);
+            let _ = db.save_trades(&self.state.block_trades);
+            let _ = db.save_swaps(&self.state.block_swaps);
+        }
+
+        // Keep transactions that did not fit into this block
+        let confirmed: HashSet<Vec<u8>> = new_block.transactions.iter().map(|tx| tx.get_hash()).collect();
+        self.pending_transactions.retain(|tx| !confirmed.contains(&tx.get_hash()));
+    }
+
+    // Stratum cannot sign for remote miners: once "stakesig" is active a staked miner's claim would need a
+    // validator signature the template cannot carry, so such miners are refused instead of losing their stake weight
+    pub fn check_stratum_miner(&self, miner_address: &str) -> Result<u64, String> {
+        let stake = self.state.get_validator_stake(miner_address);
+        if stake > 0 && self.is_deployment_active("stakesig", self.chain.len() as u64) {
+            return Err(format!("{} has bonded stake; its stake claim must be signed, mine from the node wallet or use an unstaked address", miner_address));
+        }
+        Ok(stake)
+    }
+
+    pub fn get_mining_candidate(&self, miner_address: String) -> Result<Block, String> {
+        let my_stake = self.check_stratum_miner(&miner_address)?;
+        let _height = self.chain.len() as u64;
+        let mut reward = self.calculate_reward(_height);
+
+        // Fee-per-byte selection within block size & sigop limits (System txs reserved)
+        let mut txs = self.select_template_transactions();
+
+        // Fee Split Logic
+        let mut total_fees: u64 = 0;
+        for tx in &txs { total_fees += tx.fee; }
+
+        if total_fees > 0 {
+             let dev_share = total_fees * self.state.gov_params.dev_fee_percent / 100;
+             let miner_share = total_fees - dev_share;
+             reward += miner_share;
+             
+             if dev_share > 0 {
+                 let dev_tx = Transaction::new(String::from("SYSTEM"), DEV_WALLET.to_string(), dev_share, "VLT".to_string(), 0);
+                 txs.push(dev_tx);
+             }
+        }
+
+        let reward_tx = Transaction::new(String::from("SYSTEM"), miner_address.clone(), reward, "VLT".to_string(), 0);
+        txs.insert(0, reward_tx);
+
+        // Staking & Delegation Payouts (pro rata, validator commission on delegated share)
+        let staking_inflation = self.state.gov_params.staking_inflation;
+        for (recipient, share) in self.state.staking_payouts(staking_inflation) {
+             let stake_tx = Transaction::new(String::from("SYSTEM"), recipient, share, "VLT".to_string(), 0);
+             txs.push(stake_tx);
+        }
+
+        let previous_block = self.chain.last().unwrap();
+        let difficulty = self.get_next_difficulty();
+
+        // my_stake already captured above
+        let mut candidate = Block::new(
+            previous_block.index + 1,
+            previous_block.hash.clone(),
+            txs,
+            difficulty as usize,
+            my_stake
+        );
+        // Version Bits Signalling
+        candidate.version = self.compute_block_version(candidate.index);
+        candidate.hash = candidate.calculate_hash();
+        Ok(candidate)
+    }
+
+    // Consensus checks of `block` on top of `chain` (its ancestors) with `state` after the parent block.
+    // Shared by submit_block and chain replacement. Signatures are verified separately (batched, cached).
+    fn check_block(&self, chain: &[Block], state: &ChainState, block: &Block) -> Result<(), String> {
+         let calculated = block.calculate_hash();
+         let last = chain.last().ok_or("[Security] No parent block".to_string())?;
+
+         // Hybrid Consensus Validation
+         
+         // 0. DoS Protection: Block Size & SigOp Limits
+         if block.transactions.is_empty() {
+             return Err("[Security] Block Rejected: Missing Coinbase".to_string());
+         }
+         let block_size = block.serialized_size();
+         if block_size > MAX_BLOCK_SIZE {
+             return Err(format!("[Security] Block Rejected: Too large ({} bytes, Max {})", block_size, MAX_BLOCK_SIZE));
+         }
+         let block_sigops = block.sig_op_count();
+         if block_sigops > MAX_BLOCK_SIGOPS {
+             return Err(format!("[Security] Block Rejected: Too many signature operations ({}, Max {})", block_sigops, MAX_BLOCK_SIGOPS));
+         }
+
+         // 0.5 Checkpoints
+         if !self.params.checkpoint_matches(block.index, &block.hash) {
+             return Err(format!("[Security] Block Rejected: Checkpoint mismatch at #{}", block.index));
+         }
+
+         if block.previous_hash != last.hash || block.index != last.index + 1 {
+             return Err("[Security] Invalid Previous Hash or Index".to_string());
+         }
+
+         // 1. Verify Claimed Stake
+         let miner_addr = &block.transactions[0].receiver; // Coinbase receiver is the miner
+         let actual_stake = state.get_validator_stake(miner_addr); // Own Stake + Delegations
+         if block.validator_stake > actual_stake {
+             return Err(format!("[Hybrid] Invalid Stake Claim: Claimed {}, Actual {}", block.validator_stake, actual_stake));
+         }
+
+         // 1.5 Stake Commitment (Coinbase) & Validator Signature (Deployment "stakesig")
+         if block.validator_stake > 0 && self.is_deployment_active_on(chain, "stakesig", block.index) {
+             if block.transactions[0].price != block.validator_stake {
+                 return Err(format!("[Hybrid] Stake Claim not committed in Coinbase: Claimed {}, Committed {}", block.validator_stake, block.transactions[0].price));
+             }
+             if let Err(e) = block.verify_validator_signature() {
+                 return Err(format!("[Hybrid] Invalid Validator Signature: {}", e));
+             }
+         }
+
+         // 2. Apply Difficulty Bonus (Reduction)
+         // Rule: Every 100 VLT (10,000,000,000 units) Stake reduces required zeros by 1.
+         // Cap: Max reduction of 5 zeros (Requires 500 VLT).
+         let bonus = (block.validator_stake / 10_000_000_000) as u32; // 100 VLT = 1 Level
+         let bonus_capped = bonus.min(5); 
+         
+         // Fix: Handle Bits vs Legacy Diff
+         let mut required_diff = 4; // Default
+         
+         if block.difficulty >= 0x1d00ffff {
+             // It's Bits.
+             // If >= 0x207fffff (Diff 0), we require 0 zeros.
+             if block.difficulty >= 0x207fffff {
+                 required_diff = 0;
+             } else if block.difficulty >= 0x1f00ffff {
+                 required_diff = 1;
+             } else {
+                 required_diff = 4; // Standard
+             }
+         } else {
+             // Legacy Small Int Difficulty
+             required_diff = block.difficulty.saturating_sub(bonus_capped);
+             if required_diff < 1 { required_diff = 1; }
+         }
+
+         let target_prefix = "0".repeat(required_diff as usize);
+         
+         
+         // 2.5 Verify Merkle Root Integrity (Anti-Corruption)
+         let calculated_merkle = Block::calculate_merkle_root(&block.transactions);
+         if block.merkle_root != calculated_merkle {
+             return Err(format!("[Security] Block Rejected: Merkle Root Mismatch. Header: {}, Body: {}", block.merkle_root, calculated_merkle));
+         }
+
+         if block.hash != calculated || !block.hash.starts_with(&target_prefix) {
+             return Err(format!("[Hybrid] PoW Failed. Required Prefix Length: {} (Target: {})", required_diff, target_prefix));
+         }
+         
+         // 3. Verify Transactions (Fees, Duplicates)
+         let mut total_fees = 0;
+         let mut seen_txs = std::collections::HashSet::new();
+         let mut total_system_mint = 0;
+
+         for (i, tx) in block.transactions.iter().enumerate() {
+             // Track System Minting (Coinbase + Staking + Dev)
+             if tx.sender == "SYSTEM" {
+                 total_system_mint += tx.amount;
+             }
+
+             if i == 0 { continue; } // Skip Coinbase
+             
+             // FIX: Prevent Duplicate Txs in same block
+             let tx_hash = tx.get_hash();
+             if seen_txs.contains(&tx_hash) {
+                 return Err("[Security] Rejected Block: Duplicate Transaction Detected".to_string());
+             }
+             seen_txs.insert(tx_hash);
+
+             total_fees += tx.fee;
+         }
+
+         // 3.5 Script conditions (e.g. OpTwap bounds) hold against the parent block's state
+         for tx in block.transactions.iter().skip(1) {
+             if let Err(e) = state.check_scripts(tx) {
+                 return Err(format!("[Security] Block Rejected: {}", e));
+             }
+         }
+
+         // 4. Verify Total Emission (Inflation Protection)
+         let expected_base_reward = self.calculate_reward(block.index);
+         let staking_inflation = state.gov_params.staking_inflation; // Must match mining logic
+         let max_allowed = expected_base_reward + total_fees + staking_inflation;
+         
+         if total_system_mint > max_allowed {
+              return Err(format!("[Security] Inflation Detected! Total Minted: {}, Max Allowed: {}", total_system_mint, max_allowed));
+         }
+
+         // 5. Verify Timestamp (Time Warp Protection)
+         let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+         if block.timestamp < last.timestamp {
+             return Err(format!("[Security] Timestamp Invalid: Time Reversal. Last: {}, New: {}", last.timestamp, block.timestamp));
+         }
+         if block.timestamp > now + 7200 { // 2 Hours Drift
+             return Err("[Security] Timestamp Invalid: Too far in future".to_string());
+         }
+
+         // 6. Transaction Replay Protection
+         for tx in &block.transactions {
+             if tx.sender == "SYSTEM" { continue; }
+             let stored_nonce = *state.nonces.get(&tx.sender).unwrap_or(&0);
+             if tx.nonce <= stored_nonce {
+                 return Err(format!("[Security] Replay Attack Detected: Tx Nonce {} <= Stored {}", tx.nonce, stored_nonce));
+             }
+         }
+         Ok(())
+    }
+
+    pub fn submit_block(&mut self, block: Block) -> bool {
+         if let Err(e) = self.check_block(&self.chain, &self.state, &block) {
+             println!("{}", e);
+             return false;
+         }
+
+         // Critical: Verify Signatures (Parallel, skips those already proven at mempool admission)
+         let to_verify: Vec<&Transaction> = block.transactions.iter().skip(1).collect();
+         if let Err(e) = self.sig_cache.verify_batch(&to_verify) {
+             println!("[Security] Invalid Signature: {}", e);
+             return false;
+         }
+         
+         // Apply on a scratch copy: per-block effects (unbonding releases, order expiry, governance)
+         // and the block's transactions only reach self.state once the whole block is valid
+         let mut scratch = self.state.clone();
+         scratch.begin_block(&block);
+         for tx in &block.transactions {
+             if !scratch.apply_transaction(tx) {
+                 println!("[Consensus] Error: Transaction Application Failed during block submission");
+                 return false;
+             }
+         }
+         self.state = scratch;
+
+         self.chain.push(block.clone());
+         if let Some(ref db) = self.db {
+             let _ = db.save_block(&block);
+             let _ = db.save_trades(&self.state.block_trades);
+             let _ = db.save_swaps(&self.state.block_swaps);
+         }
+         
+         // Fix: Remove confirmed transactions from pending pool to prevent replay/stuck
+         let confirmed: HashSet<Vec<u8>> = block.transactions.iter().map(|tx| tx.get_hash()).collect();
+         self.pending_transactions.retain(|tx| !confirmed.contains(&tx.get_hash()));
+
+         true
+    }
+
+
+    fn get_next_difficulty(&self) -> u32 {
+        let last_block = self.chain.last().unwrap();
+        
+        // Retarget every N blocks (Chain Params)
+        let retarget_interval = self.params.retarget_interval;
+        let target_seconds_per_block = self.params.target_block_time;
+        let target_timespan = retarget_interval * target_seconds_per_block;
+
+        if (last_block.index + 1) % retarget_interval != 0 {
+            return last_block.difficulty as u32;
+        }
+
+        // Find the first block of this epoch
+        // Be careful with index underflow
+        let first_block_index = if last_block.index >= retarget_interval {
+            last_block.index - retarget_interval + 1
+        } else {
+            0
+        };
+        
+        let first_block = &self.chain[first_block_index as usize];
+        
+        // Calculate actual timespan
+        let actual_timespan = last_block.timestamp.saturating_sub(first_block.timestamp);
+        
+        // Dampening (Max 4x, Min 1/4x)
+        let actual_timespan = if actual_timespan < target_timespan / 4 {
+             target_timespan / 4
+        } else if actual_timespan > target_timespan * 4 {
+             target_timespan * 4
+        } else {
+             actual_timespan
         };
 
-        // Use Standard Difficulty 0x1d00ffff for Genesis to match chain config
-        let mut genesis_block = Block::new(0, String::from("0"), vec![premine_tx], 0x1d00ffff, 0);
-        
-        // FIX: Enforce Deterministic Genesis Timestamp and Hash for network compatibility
-        genesis_block.timestamp = 1767077203;
-        genesis_block.hash = genesis_block.calculate_hash();
+        // 3. Fallback for new chains
+        if self.chain.len() < retarget_interval as usize {
+            return 0x1f00ffff; // Genesis difficulty
+        }
+        
+        // Calculate new target
+        // Target = OldTarget * ActualTime / TargetTime
+        // We need to convert Bits -> Target (u256) -> Op -> Bits
+        // Since we don't have U256 lib easily exposed here, we do a simplified float math
+        // or just manipulation of the exponent.
+        
+        // Simplified Logic for "Bits":
+        // Bits = (Exponent << 24) | Mantissa
+        // Val = Mantissa * 256^(Exponent - 3)
+        // NewVal = OldVal * (Actual / Target)
+        
+        // Let's use f64 for approximation (Standard in many altcoins)
+        let last_diff_bits = last_block.difficulty as u32;
+        let exponent = (last_diff_bits >> 24) & 0xff;
+        let mantissa = last_diff_bits & 0x00ffffff;
+        
+        let mut value = mantissa as f64 * 256f64.powf((exponent as i32 - 3) as f64);
+        
+        value = value * (actual_timespan as f64 / target_timespan as f64);
+        
+        // Convert back to Bits
+        // Find rough exponent
+        if value <= 0.0 { return 0x207fffff; }
+        
+        let mut new_exponent = 0;
+        let mut tmp = value;
+        while tmp >= 256.0 * 256.0 * 256.0 { // Keep mantissa in 3 bytes (approx) 
+             // Ideally we find log256
+             tmp /= 256.0;
+             new_exponent += 1;
+        }
+        // Base is 3 bytes (0x00ffffff)
+        new_exponent += 3;
+        
+        // Re-normalize mantissa
+        let new_mantissa = (value / 256f64.powf((new_exponent - 3) as f64)) as u32;
+        
+        // Cap at Min Difficulty
+        // 0x20 is max exponent (32 bytes)
+        if new_exponent > 0x20 { return 0x207fffff; }
+        if new_exponent == 0x20 && new_mantissa > 0x7fffff { return 0x207fffff; }
+        
+        // Assemble
+        let new_bits = ((new_exponent as u32) << 24) | (new_mantissa & 0x00ffffff);
+        println!("[Retarget] Block {}: Timespan {}s (Target {}s) -> Diff Adjusted to {:x}", last_block.index + 1, actual_timespan, target_timespan, new_bits);
+        
+        new_bits
+    }
+
+    // --- Version Bits (BIP9-style Soft Fork Activation) ---
+    // State transitions happen at window boundaries (window = retarget interval).
+    // The state returned applies to the block at `height`.
+    pub fn deployment_state(&self, deployment: &Deployment, height: u64) -> DeploymentState {
+        self.deployment_state_on(&self.chain, deployment, height)
+    }
+
+    // Same for the block at `height` on top of `chain` (a reorg candidate or our own chain). Window states are
+    // cached by the hash of the block closing the previous window, so each window is evaluated once per branch.
+    fn deployment_state_on(&self, chain: &[Block], deployment: &Deployment, height: u64) -> DeploymentState {
+        let window = self.params.retarget_interval;
+        let period_start = height - (height % window);
+        let cache_key = |start: u64| chain.get(start as usize - 1).map(|b| (deployment.name.clone(), b.hash.clone()));
+        let mut cache = self.deployment_cache.lock().unwrap();
 
-        self.chain.push(genesis_block.clone());
+        // Walk back to the latest window with a known state (Genesis window: Defined)
+        let mut state = DeploymentState::Defined;
+        let mut uncached = Vec::new();
+        let mut start = period_start;
+        while start > 0 {
+            if let Some(known) = cache_key(start).and_then(|k| cache.get(&k).copied()) {
+                state = known;
+                break;
+            }
+            uncached.push(start);
+            start -= window;
+        }
+
+        for next_start in uncached.into_iter().rev() {
+            let start = next_start - window;
+            state = match state {
+                DeploymentState::Defined => {
+                    if next_start >= deployment.timeout_height { DeploymentState::Failed }
+                    else if next_start >= deployment.start_height { DeploymentState::Started }
+                    else { DeploymentState::Defined }
+                },
+                DeploymentState::Started => {
+                    if Blockchain::count_signalling(chain, deployment, start, next_start) >= self.params.activation_threshold {
+                        DeploymentState::LockedIn
+                    } else if next_start >= deployment.timeout_height {
+                        DeploymentState::Failed
+                    } else {
+                        DeploymentState::Started
+                    }
+                },
+                DeploymentState::LockedIn => DeploymentState::Active,
+                final_state => final_state, // Active / Failed are terminal
+            };
+            // Windows beyond the chain's tip are not final yet
+            if let Some(key) = cache_key(next_start) {
+                cache.insert(key, state);
+            }
+        }
+        state
+    }
+
+    // Number of blocks in [from, to) whose header version signals the deployment
+    fn count_signalling(chain: &[Block], deployment: &Deployment, from: u64, to: u64) -> u64 {
+        let end = (to as usize).min(chain.len());
+        let begin = (from as usize).min(end);
+        chain[begin..end].iter().filter(|b| deployment.is_signalled_by(b.version)).count() as u64
+    }
+
+    pub fn is_deployment_active(&self, name: &str, height: u64) -> bool {
+        self.is_deployment_active_on(&self.chain, name, height)
+    }
+
+    fn is_deployment_active_on(&self, chain: &[Block], name: &str, height: u64) -> bool {
+        match self.params.deployment(name) {
+            Some(d) => self.deployment_state_on(chain, d, height) == DeploymentState::Active,
+            None => false,
+        }
+    }
+
+    // Header version for a new block: signal every deployment that is Started or LockedIn
+    pub fn compute_block_version(&self, height: u64) -> u32 {
+        let mut version = VERSIONBITS_TOP_BITS;
+        for d in &self.params.deployments {
+            match self.deployment_state(d, height) {
+                DeploymentState::Started | DeploymentState::LockedIn => version |= d.mask(),
+                _ => {}
+            }
+        }
+        version
+    }
+
+    // API: Deployment states with signalling progress in the current window
+    pub fn get_deployments_info(&self) -> Vec<serde_json::Value> {
+        let height = self.chain.len() as u64;
+        let window = self.params.retarget_interval;
+        let period_start = height - (height % window);
+
+        self.params.deployments.iter().map(|d| {
+            serde_json::json!({
+                "name": d.name,
+                "bit": d.bit,
+                "start_height": d.start_height,
+                "timeout_height": d.timeout_height,
+                "state": self.deployment_state(d, height),
+                "window_start": period_start,
+                "window_size": window,
+                "signalling": Blockchain::count_signalling(&self.chain, d, period_start, height),
+                "threshold": self.params.activation_threshold,
+            })
+        }).collect()
+    }
+
+    pub fn calculate_reward(&self, height: u64) -> u64 {
+        // Emission Schedule (Chain Params): 50 VLT halving every 105,000 blocks
+        self.params.emission.block_reward(height)
+    }
+    
+    pub fn is_chain_valid(&self) -> bool {
+        for i in 1..self.chain.len() {
+            let current = &self.chain[i];
+            let previous = &self.chain[i - 1];
+            if current.hash != current.calculate_hash() { return false; }
+            if current.previous_hash != previous.hash { return false; }
+        }
+        true
+    }
+
+    pub fn save(&self) {
         if let Some(ref db) = self.db {
-            let _ = db.save_block(&genesis_block);
+            let _ = db.save_pending_txs(&self.pending_transactions);
+            // flushed by db call
         }
-        // Fix: Apply Genesis transactions to State so balance shows up immediately
-        self.rebuild_state();
     }
 
-    pub fn create_transaction(&mut self, transaction: Transaction) -> bool {
-        // Phase 28: Smart Scripting Validation
-        if !transaction.script_sig.ops.is_empty() {
-             let mut vm = VirtualMachine::new();
-             // 1. Run Unlocking Script (Inputs)
-             if !vm.execute(&transaction.script_sig, &transaction) {
-                 println!("ScriptSig execution failed");
-                 return false;
-             }
-             // 2. Run Locking Script (Logic)
-             // In a full UTXO (P2SH), we would load this from the UTXO set.
-             // Here, we support "Self-contained Scripts" for Proof-of-Concept.
-             // The ScriptPub defines the constraint (e.g., CheckSig).
-             if !vm.execute(&transaction.script_pub_key, &transaction) {
-                 println!("ScriptPubKey execution failed");
-                 return false;
-             }
-             
-             // 3. Authorization Success? (Stack Top == 1)
-             // (vm.execute returns true if success, but we should double check semantics if needed)
-             // For now, vm.execute() returns true if the script ran without error AND top is True.
-             // So we are good.
-        } else {
-             // Legacy Validation
-             if !transaction.verify() {
-                 println!("Transaction verification failed");
-                 return false;
-             }
-        }
+    pub fn load() -> Self {
+        Blockchain::new()
+    }
+
+    // Nonce of the address's latest transaction, pending ones included (next tx uses this + 1)
+    pub fn pending_nonce(&self, address: &str) -> u64 {
+        let confirmed = *self.state.nonces.get(address).unwrap_or(&0);
+        self.pending_transactions.iter()
+            .filter(|t| t.sender == address)
+            .map(|t| t.nonce)
+            .fold(confirmed, u64::max)
+    }
+
+    // Latest open checkpoint `address` can still attest to (not attested on chain or in the mempool)
+    pub fn open_checkpoint_for(&self, address: &str) -> Option<(u64, String)> {
+        if *self.state.stakes.get(address).unwrap_or(&0) == 0 { return None; }
+        self.state.finality_checkpoints.values().rev()
+            .find(|c| !c.attesters.contains(address)
+                && !self.pending_transactions.iter().any(|t| t.tx_type == TxType::Attest && t.sender == address && t.amount == c.height))
+            .map(|c| (c.height, c.hash.clone()))
+    }
+
+    // Transactions of a chain candidate whose signatures need checking. Assume-Valid: those in the
+    // assumed-valid block and its ancestors are skipped.
+    pub fn candidate_signatures<'a>(candidate: &'a [Block], assume_valid: Option<&String>) -> Vec<&'a Transaction> {
+        let assume_valid_height = assume_valid
+            .and_then(|h| candidate.iter().find(|b| &b.hash == h))
+            .map(|b| b.index);
+        candidate.iter()
+            .skip(1)
+            .filter(|b| assume_valid_height.is_none_or(|h| b.index > h))
+            .flat_map(|b| b.transactions.iter().skip(1))
+            .collect()
+    }
+
+    pub fn attempt_chain_replacement(&mut self, candidate: Vec<Block>) -> bool {
+         if candidate.len() <= self.chain.len() { return false; }
+         
+         println!("[Consensus] Validating remote chain candidate (Height: {})...", candidate.len());
+         
+         // 1. Genesis Check
+         if candidate.is_empty() || candidate[0].hash != self.chain[0].hash {
+             println!("[Consensus] Rejecting: Incompatible Genesis.");
+             return false;
+         }
+
+         // 1.4 Finality: never revert a block finalized by a 2/3-stake supermajority
+         if let Some((height, hash)) = &self.state.finalized {
+             if candidate.get(*height as usize).map(|b| &b.hash) != Some(hash) {
+                 println!("[Finality] Rejecting: Candidate reverts finalized block #{}", height);
+                 return false;
+             }
+         }
+
+         // 1.5 Checkpoints: every checkpoint the candidate reaches must match (no forks below a checkpoint)
+         for (height, hash) in &self.params.checkpoints {
+             if let Some(b) = candidate.get(*height as usize) {
+                 if &b.hash != hash {
+                     println!("[Consensus] Rejecting: Checkpoint mismatch at #{}", height);
+                     return false;
+                 }
+             }
+         }
+
+         // 2. Signatures (parallel). Callers pre-verify them outside the chain lock, leaving only cache hits here.
+         let to_verify = Blockchain::candidate_signatures(&candidate, self.params.assume_valid.as_ref());
+         if let Err(e) = self.sig_cache.verify_batch(&to_verify) {
+             println!("[Consensus] Rejecting: Invalid Tx Signature: {}", e);
+             return false;
+         }
+
+         // 3. Every block passes the same checks as submit_block, against the candidate's own state
+         // after its parent (stake claims, commitments, validator signatures, PoW, emission, nonces)
+         let mut state = Blockchain::replay_state(&candidate[..1], None);
+         let mut trades = Vec::new();
+         let mut swaps = Vec::new();
+         for i in 1..candidate.len() {
+             let block = &candidate[i];
+             if let Err(e) = self.check_block(&candidate[..i], &state, block) {
+                 println!("[Consensus] Rejecting block #{}: {}", block.index, e);
+                 return false;
+             }
+             state.begin_block(block);
+             for tx in &block.transactions {
+                 if !state.apply_transaction(tx) {
+                     println!("[Consensus] Rejecting block #{}: Transaction Application Failed", block.index);
+                     return false;
+                 }
+             }
+             trades.extend(state.block_trades.iter().cloned());
+             swaps.extend(state.block_swaps.iter().cloned());
+         }
+
+         println!("[Consensus] Remote chain accepted.");
+         self.chain = candidate;
+         self.state = state;
+         // Save to DB (the trade index belonged to the replaced chain)
+         if let Some(ref db) = self.db {
+             let _ = db.save_chain(&self.chain);
+             let _ = db.clear_trades();
+             let _ = db.save_trades(&trades);
+             let _ = db.save_swaps(&swaps);
+         }
+         true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::governance::DEX_FEE_ACCOUNT;
+    use crate::script::{OpCode, Script};
+    use crate::params::Deployment;
+
+    const ALICE: &str = "02a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1";
+    const BOB: &str = "02b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0";
+
+    fn issue(sender: &str, symbol: &str, supply: u64, meta: &str, nonce: u64) -> Transaction {
+        let mut tx = Transaction::new_token_issue(sender.to_string(), symbol.to_string(), supply, nonce);
+        tx.data = meta.to_string();
+        tx
+    }
+
+    fn supply(state: &ChainState, token: &str) -> TokenSupply {
+        state.token_supply.get(token).cloned().unwrap_or_default()
+    }
+
+    // Genesis only, nothing persisted. Easiest target (any hash passes) and no Genesis checkpoint.
+    fn test_chain() -> Blockchain {
+        let mut params = ChainParams::mainnet();
+        params.checkpoints.clear();
+        let mut bc = Blockchain {
+            chain: Vec::new(),
+            pending_transactions: Vec::new(),
+            difficulty: 0x207fffff,
+            state: ChainState::new(),
+            db: None,
+            params,
+            sig_cache: Arc::new(SignatureCache::new()),
+            deployment_cache: Mutex::new(HashMap::new()),
+        };
+        bc.create_genesis_block();
+        bc
+    }
+
+    // Appends `txs` as the next block, 60s after the tip (no PoW, no signatures). False if a tx is rejected.
+    fn push_block(bc: &mut Blockchain, txs: Vec<Transaction>) -> bool {
+        let tip = bc.chain.last().unwrap();
+        let mut block = Block::new(tip.index + 1, tip.hash.clone(), txs, 0x1d00ffff, 0);
+        block.timestamp = tip.timestamp + 60;
+        block.hash = block.calculate_hash();
+        bc.state.begin_block(&block);
+        let applied = block.transactions.iter().all(|tx| bc.state.apply_transaction(tx));
+        bc.chain.push(block);
+        applied
+    }
+
+    fn key(seed: u8) -> (SigningKey, String) {
+        let key = SigningKey::from_slice(&[seed; 32]).unwrap();
+        let address = hex::encode(key.verifying_key().to_sec1_bytes());
+        (key, address)
+    }
+
+    // Template for `miner` (plus `extra` txs) submitted like a block from the network
+    fn mine_template(bc: &mut Blockchain, miner: &str, extra: Vec<Transaction>) -> bool {
+        let mut block = bc.get_mining_candidate(miner.to_string()).unwrap();
+        block.transactions.extend(extra);
+        block.merkle_root = Block::calculate_merkle_root(&block.transactions);
+        block.hash = block.calculate_hash();
+        bc.submit_block(block)
+    }
+
+    fn add_liquidity(sender: &str, pool: &str, amount_a: u64, amount_b: u64, nonce: u64) -> Transaction {
+        let mut tx = Transaction::new(sender.to_string(), "AMM".to_string(), amount_a, pool.to_string(), nonce);
+        tx.tx_type = TxType::AddLiquidity;
+        tx.price = amount_b;
+        tx
+    }
+
+    fn pool(token_a: &str, token_b: &str, reserve_a: u64, reserve_b: u64) -> Pool {
+        Pool {
+            token_a: token_a.to_string(),
+            token_b: token_b.to_string(),
+            reserve_a,
+            reserve_b,
+            total_shares: 1,
+            price_a_cumulative: 0,
+            price_b_cumulative: 0,
+            last_update: 0,
+        }
+    }
+
+    // AAA -> BBB -> CCC over two pools, ALICE holds 100_000 AAA
+    fn route_state() -> ChainState {
+        let mut state = ChainState::new();
+        state.pools.insert("AAA/BBB".to_string(), pool("AAA", "BBB", 1_000_000, 2_000_000));
+        state.pools.insert("BBB/CCC".to_string(), pool("BBB", "CCC", 3_000_000, 1_500_000));
+        state.set_balance(ALICE, "AAA", 100_000);
+        state
+    }
+
+    fn path(tokens: &[&str]) -> Vec<String> {
+        tokens.iter().map(|t| t.to_string()).collect()
+    }
+
+    // Everything held of `token`: balances, order escrow and pool reserves
+    fn held(state: &ChainState, token: &str) -> u64 {
+        let balances: u64 = state.balances.values().filter_map(|t| t.get(token)).sum();
+        let in_orders: u64 = state.orders.values()
+            .map(|o| match o.side.as_str() {
+                "BUY" if o.quote == token => o.price * o.amount,
+                "SELL" if o.token == token => o.amount,
+                _ => 0,
+            })
+            .sum();
+        let in_pools: u64 = state.pools.values()
+            .map(|p| if p.token_a == token { p.reserve_a } else if p.token_b == token { p.reserve_b } else { 0 })
+            .sum();
+        balances + in_orders + in_pools
+    }
+
+    #[test]
+    fn issue_credits_issuer_and_rejects_invalid_symbols() {
+        let mut state = ChainState::new();
+        assert!(state.apply_transaction(&issue(ALICE, "TKN", 1_000, r#"{"max_supply":1500,"mintable":true}"#, 1)));
+        assert_eq!(state.get_balance(ALICE, "TKN"), 1_000);
+        assert_eq!(supply(&state, "TKN").issued, 1_000);
+        assert_eq!(state.tokens.get("TKN").map(String::as_str), Some(ALICE));
+
+        // Rejected issues credit nothing
+        assert!(!state.apply_transaction(&issue(BOB, "TKN", 500, "", 1)));
+        assert!(!state.apply_transaction(&issue(BOB, "VLT", 500, "", 2)));
+        assert!(!state.apply_transaction(&issue(BOB, "AB", 500, "", 3)));
+        assert!(!state.apply_transaction(&issue(BOB, "CAP", 500, r#"{"max_supply":100}"#, 4)));
+        assert!(!state.apply_transaction(&issue(BOB, "BAD", 500, "{", 5)));
+        assert_eq!(state.get_balance(BOB, "TKN"), 0);
+        assert_eq!(state.get_balance(BOB, "CAP"), 0);
+        assert_eq!(supply(&state, "TKN").issued, 1_000);
+        assert!(!state.tokens.contains_key("CAP"));
+    }
+
+    #[test]
+    fn mint_and_burn_respect_the_max_supply() {
+        let mut state = ChainState::new();
+        assert!(state.apply_transaction(&issue(ALICE, "TKN", 1_000, r#"{"max_supply":1500,"mintable":true}"#, 1)));
+
+        // Only the issuer mints, to any receiver
+        assert!(!state.apply_transaction(&Transaction::new_mint(BOB.to_string(), "TKN".to_string(), BOB.to_string(), 100, 1)));
+        assert!(state.apply_transaction(&Transaction::new_mint(ALICE.to_string(), "TKN".to_string(), BOB.to_string(), 400, 2)));
+        assert_eq!(state.get_balance(BOB, "TKN"), 400);
+        assert_eq!(supply(&state, "TKN").issued, 1_400);
+
+        // Cap on total issuance
+        assert!(!state.apply_transaction(&Transaction::new_mint(ALICE.to_string(), "TKN".to_string(), ALICE.to_string(), 101, 3)));
+
+        // Burns leave circulation but do not free room under the cap
+        assert!(!state.apply_transaction(&Transaction::new_burn(BOB.to_string(), "TKN".to_string(), 401, 2)));
+        assert!(state.apply_transaction(&Transaction::new_burn(BOB.to_string(), "TKN".to_string(), 300, 3)));
+        assert_eq!(state.get_balance(BOB, "TKN"), 100);
+        assert_eq!(supply(&state, "TKN").burned, 300);
+        assert_eq!(supply(&state, "TKN").circulating(), 1_100);
+        assert!(!state.apply_transaction(&Transaction::new_mint(ALICE.to_string(), "TKN".to_string(), ALICE.to_string(), 101, 4)));
+        assert!(state.apply_transaction(&Transaction::new_mint(ALICE.to_string(), "TKN".to_string(), ALICE.to_string(), 100, 5)));
+        assert_eq!(supply(&state, "TKN").issued, 1_500);
+
+        // Renounced mint authority is final
+        assert!(state.apply_transaction(&Transaction::new_renounce_mint(ALICE.to_string(), "TKN".to_string(), 6)));
+        assert!(state.check_mint(&Transaction::new_mint(ALICE.to_string(), "TKN".to_string(), ALICE.to_string(), 1, 7)).is_err());
+    }
+
+    #[test]
+    fn uncapped_tokens_are_not_mintable_by_default() {
+        let mut state = ChainState::new();
+        assert!(state.apply_transaction(&issue(ALICE, "FIX", 1_000, "", 1)));
+        assert!(!state.apply_transaction(&Transaction::new_mint(ALICE.to_string(), "FIX".to_string(), ALICE.to_string(), 1, 2)));
+        assert_eq!(supply(&state, "FIX").issued, 1_000);
+    }
+
+    #[test]
+    fn approve_expiry_is_signed_and_enforced() {
+        let mut state = ChainState::new();
+        state.set_balance(ALICE, "VLT", 1_000);
+        state.height = 5;
+
+        let approve = Transaction::new_approve(ALICE.to_string(), BOB.to_string(), "VLT".to_string(), 500, Some(10), 1);
+        assert_eq!(ApproveParams::from_data(&approve.data).map(|p| p.expiry_height), Ok(Some(10)));
+        assert!(state.apply_transaction(&approve));
+        assert_eq!(state.get_allowance(ALICE, BOB, "VLT", 9), 500);
+
+        // Spendable below the expiry height
+        assert!(state.apply_transaction(&Transaction::new_transfer_from(BOB.to_string(), ALICE.to_string(), BOB.to_string(), "VLT".to_string(), 200, 1)));
+        assert_eq!(state.get_balance(BOB, "VLT"), 200);
+        assert_eq!(state.get_allowance(ALICE, BOB, "VLT", 9), 300);
+
+        // Expired from the expiry height on
+        state.height = 10;
+        assert_eq!(state.get_allowance(ALICE, BOB, "VLT", 10), 0);
+        assert!(!state.apply_transaction(&Transaction::new_transfer_from(BOB.to_string(), ALICE.to_string(), BOB.to_string(), "VLT".to_string(), 100, 2)));
+        assert_eq!(state.get_balance(ALICE, "VLT"), 800);
+
+        // An expiry that already passed is rejected, revoking (amount 0) is not
+        assert!(!state.apply_transaction(&Transaction::new_approve(ALICE.to_string(), BOB.to_string(), "VLT".to_string(), 500, Some(10), 2)));
+        assert!(state.apply_transaction(&Transaction::new_approve(ALICE.to_string(), BOB.to_string(), "VLT".to_string(), 0, Some(10), 3)));
+        assert!(!state.allowances.contains_key(&(ALICE.to_string(), BOB.to_string(), "VLT".to_string())));
+
+        // Without an expiry the allowance stays active
+        assert!(state.apply_transaction(&Transaction::new_approve(ALICE.to_string(), BOB.to_string(), "VLT".to_string(), 50, None, 4)));
+        assert_eq!(state.get_allowance(ALICE, BOB, "VLT", u64::MAX), 50);
+    }
+
+    #[test]
+    fn partial_fills_refunds_and_fees_conserve_supply() {
+        let mut bc = test_chain();
+        let maker = bc.params.emission.premine_address.clone();
+        assert!(push_block(&mut bc, vec![
+            issue(&maker, "TKN", 1_000_000, "", 1),
+            Transaction::new(maker.clone(), BOB.to_string(), 1_000_000, "VLT".to_string(), 2),
+            add_liquidity(&maker, "TKN/VLT", 10_000, 50_000, 3),
+        ]));
+
+        bc.state.gov_params.dex_maker_fee_bps = 10;
+        bc.state.gov_params.dex_taker_fee_bps = 20;
+        bc.state.gov_params.dex_fee_lp_percent = 50;
+        let ioc = OrderParams { order_type: OrderType::ImmediateOrCancel, ..OrderParams::default() };
+        assert!(push_block(&mut bc, vec![
+            Transaction::new_order(maker.clone(), "TKN".to_string(), "SELL", 100_000, 5, 4),
+            // Partial fill of the ask at 5, price improvement refunded
+            Transaction::new_order(BOB.to_string(), "TKN".to_string(), "BUY", 40_000, 7, 1),
+            // Takes the rest of the ask, 40_000 rest on the book at 6
+            Transaction::new_order(BOB.to_string(), "TKN".to_string(), "BUY", 100_000, 6, 2),
+            // Nothing to match, fully refunded
+            Transaction::new_pair_order(BOB.to_string(), "TKN".to_string(), &ioc, "BUY", 10_000, 4, 3),
+        ]));
+
+        assert_eq!(bc.state.block_trades.len(), 2);
+        assert!(bc.state.asks.is_empty());
+        assert_eq!(bc.state.bids.len(), 1);
+        // Taker fees on 100_000 TKN (20 bps), maker fees on 500_000 VLT (10 bps), half to the pool
+        assert_eq!(bc.state.get_balance(BOB, "TKN"), 100_000 - 200);
+        assert_eq!(bc.state.get_balance(BOB, "VLT"), 1_000_000 - 500_000 - 240_000);
+        assert_eq!(bc.state.get_balance(DEX_FEE_ACCOUNT, "TKN"), 100);
+        assert_eq!(bc.state.get_balance(DEX_FEE_ACCOUNT, "VLT"), 250);
+        let pool = &bc.state.pools["TKN/VLT"];
+        assert_eq!((pool.reserve_a, pool.reserve_b), (10_100, 50_250));
+
+        assert_eq!(held(&bc.state, "TKN"), 1_000_000);
+        let audit = bc.audit_supply();
+        assert!(audit.ok, "VLT off by {}", audit.difference);
+        assert_eq!(audit.locked, 240_000 + 50_250);
+
+        // Cancelling the resting bid refunds its escrow
+        let bid = bc.state.orders.keys().next().cloned().unwrap();
+        assert!(push_block(&mut bc, vec![Transaction::new_cancel(BOB.to_string(), bid, 4)]));
+        assert_eq!(bc.state.get_balance(BOB, "VLT"), 1_000_000 - 500_000);
+        assert_eq!(held(&bc.state, "TKN"), 1_000_000);
+        assert!(bc.audit_supply().ok);
+    }
+
+    #[test]
+    fn exact_input_route_math() {
+        let state = route_state();
+        let route = path(&["AAA", "BBB", "CCC"]);
+        // 10_000 * 997 * 2_000_000 / (1_000_000 * 1000 + 10_000 * 997), then again through BBB/CCC
+        let hops = state.simulate_path(&route, 10_000).unwrap();
+        assert_eq!(hops, vec![("AAA/BBB".to_string(), true, 19_743), ("BBB/CCC".to_string(), true, 9_777)]);
+        let reverse = state.simulate_path(&path(&["CCC", "BBB", "AAA"]), 9_777).unwrap();
+        assert!(reverse.iter().all(|(_, a_to_b, _)| !a_to_b));
+        assert!(reverse[1].2 < 10_000); // The round trip pays the fee twice per pool
+
+        let params = SwapParams { path: route.clone(), ..SwapParams::default() };
+        let mut state = route_state();
+        assert!(!state.apply_transaction(&Transaction::new_route_swap(ALICE.to_string(), &params, 10_000, 9_778, 1)));
+        assert!(state.apply_transaction(&Transaction::new_route_swap(ALICE.to_string(), &params, 10_000, 9_777, 1)));
+        assert_eq!(state.get_balance(ALICE, "AAA"), 90_000);
+        assert_eq!(state.get_balance(ALICE, "CCC"), 9_777);
+        let (first, second) = (&state.pools["AAA/BBB"], &state.pools["BBB/CCC"]);
+        assert_eq!((first.reserve_a, first.reserve_b), (1_010_000, 1_980_257));
+        assert_eq!((second.reserve_a, second.reserve_b), (3_019_743, 1_490_223));
+    }
+
+    #[test]
+    fn exact_output_route_math() {
+        let state = route_state();
+        let route = path(&["AAA", "BBB", "CCC"]);
+        let (input, hops) = state.simulate_path_exact_out(&route, 5_000).unwrap();
+        assert_eq!(input, 5_073);
+        assert_eq!(hops, vec![("AAA/BBB".to_string(), true, 10_064), ("BBB/CCC".to_string(), true, 5_000)]);
+        // Least input that reaches the output (rounded up per hop)
+        assert!(state.simulate_path(&route, input).unwrap()[1].2 >= 5_000);
+        assert!(state.simulate_path(&route, input - 1).unwrap()[1].2 < 5_000);
+        assert!(state.simulate_path_exact_out(&route, 1_500_000).is_err());
+
+        let quote = state.best_route("AAA", "CCC", 5_000, true).unwrap();
+        assert_eq!((quote.amount_in, quote.amount_out), (5_073, 5_000));
+
+        let params = SwapParams { path: route.clone(), exact_output: true, ..SwapParams::default() };
+        let mut unbounded = Transaction::new_route_swap(ALICE.to_string(), &params, 5_000, 5_073, 1);
+        unbounded.data = serde_json::to_string(&params).unwrap();
+        assert!(state.check_swap(&unbounded).is_err());
+
+        let mut state = route_state();
+        assert!(!state.apply_transaction(&Transaction::new_route_swap(ALICE.to_string(), &params, 5_000, 5_072, 1)));
+        assert!(state.apply_transaction(&Transaction::new_route_swap(ALICE.to_string(), &params, 5_000, 5_073, 1)));
+        assert_eq!(state.get_balance(ALICE, "AAA"), 100_000 - 5_073);
+        assert_eq!(state.get_balance(ALICE, "CCC"), 5_000);
+    }
+
+    #[test]
+    fn best_route_picks_the_better_path_per_size() {
+        // A shallow direct AAA/CCC pool at a better spot price than the deep two-hop route
+        let mut state = route_state();
+        state.pools.insert("AAA/CCC".to_string(), pool("AAA", "CCC", 100_000, 120_000));
+
+        let small = state.best_route("AAA", "CCC", 1_000, false).unwrap();
+        assert_eq!(small.pools, vec!["AAA/CCC"]);
+        assert_eq!(small.amount_out, state.simulate_path(&path(&["AAA", "CCC"]), 1_000).unwrap()[0].2);
+
+        // Size moves the direct pool more than the deep ones
+        let large = state.best_route("AAA", "CCC", 50_000, false).unwrap();
+        assert_eq!((large.path.clone(), large.pools.clone()), (path(&["AAA", "BBB", "CCC"]), vec!["AAA/BBB".to_string(), "BBB/CCC".to_string()]));
+        assert_eq!(large.amounts_out, state.simulate_path(&large.path, 50_000).unwrap().into_iter().map(|h| h.2).collect::<Vec<_>>());
+        assert!(large.amount_out > state.simulate_path(&path(&["AAA", "CCC"]), 50_000).unwrap()[0].2);
+        assert!(large.price_impact_bps > small.price_impact_bps);
+
+        // Selling CCC into the direct pool gets its weak side, so the reverse goes through BBB
+        assert_eq!(state.best_route("CCC", "AAA", 1_000, false).unwrap().path, path(&["CCC", "BBB", "AAA"]));
+        assert!(state.best_route("AAA", "ZZZ", 1_000, false).is_none());
+    }
+
+    #[test]
+    fn routes_are_limited_to_max_hops() {
+        let mut state = ChainState::new();
+        for (a, b) in [("AAA", "BBB"), ("BBB", "CCC"), ("CCC", "DDD"), ("DDD", "EEE")] {
+            state.pools.insert(format!("{}/{}", a, b), pool(a, b, 1_000_000, 1_000_000));
+        }
+        assert_eq!(state.best_route("AAA", "DDD", 1_000, false).unwrap().pools.len(), MAX_SWAP_HOPS);
+        assert!(state.best_route("AAA", "EEE", 1_000, false).is_none());
+        assert!(state.best_route("AAA", "EEE", 1_000, true).is_none());
+
+        assert!(state.simulate_path(&path(&["AAA", "BBB", "CCC", "DDD", "EEE"]), 1_000).is_err());
+        assert!(state.simulate_path(&path(&["AAA"]), 1_000).is_err());
+        assert!(state.simulate_path(&path(&["AAA", "BBB", "AAA"]), 1_000).is_err()); // Visits AAA twice
+        assert!(state.simulate_path(&path(&["AAA", "CCC"]), 1_000).is_err()); // No pool
+    }
+
+    #[test]
+    fn twap_dampens_a_manipulated_block() {
+        let mut bc = test_chain();
+        let lp = bc.params.emission.premine_address.clone();
+        let route = SwapParams { path: path(&["VLT", "TKN"]), ..SwapParams::default() };
+        assert!(push_block(&mut bc, vec![
+            issue(&lp, "TKN", 1_000_000, "", 1),
+            Transaction::new(lp.clone(), BOB.to_string(), 9_000_000, "VLT".to_string(), 2),
+            add_liquidity(&lp, "TKN/VLT", 1_000_000, 1_000_000, 3),
+        ]));
+        for _ in 0..9 {
+            assert!(push_block(&mut bc, vec![]));
+        }
+        let start = bc.chain[1].timestamp;
+
+        // Block 11 moves the price ~100x, its own swaps only count from the next block on
+        assert!(push_block(&mut bc, vec![Transaction::new_route_swap(BOB.to_string(), &route, 9_000_000, 1, 1)]));
+        let manipulated = bc.state.pools["TKN/VLT"].spot_prices().unwrap().0 as u64;
+        assert!(manipulated > 90 * PRICE_SCALE as u64);
+        let end = bc.chain[11].timestamp;
+        assert_eq!(bc.state.twap("TKN/VLT", start, end).unwrap().price_a, PRICE_SCALE as u64);
+        assert_eq!(PriceOracle::twap(&bc.state, "TKN/VLT", end - start), Some(PRICE_SCALE as u64));
+
+        // Reverted in block 12: the manipulated price was held for one block time (60 of 660 seconds)
+        let tkn = bc.state.get_balance(BOB, "TKN");
+        let unwind = SwapParams { path: path(&["TKN", "VLT"]), ..SwapParams::default() };
+        assert!(push_block(&mut bc, vec![Transaction::new_route_swap(BOB.to_string(), &unwind, tkn, 1, 2)]));
+        let end = bc.chain[12].timestamp;
+        let twap = bc.state.twap("TKN/VLT", start, end).unwrap().price_a;
+        assert_eq!(twap, (600 * PRICE_SCALE as u64 + 60 * manipulated) / 660);
+        assert!(twap < manipulated / 9); // Weighted by time held, not by trade size
+        assert_eq!(bc.state.twap("TKN/VLT", end - 60, end).unwrap().price_a, manipulated);
+    }
+
+    #[test]
+    fn signed_transfer_is_admitted_mined_and_accepted() {
+        let mut bc = test_chain();
+        let (alice_key, alice) = key(1);
+        bc.state.set_balance(&alice, "VLT", 1_000_000);
+        let mut tx = Transaction::new(alice.clone(), BOB.to_string(), 250_000, "VLT".to_string(), 1);
+        tx.sign(&alice_key);
+
+        assert!(bc.create_transaction(tx.clone()));
+        assert!(mine_template(&mut bc, BOB, vec![]));
+        assert!(bc.chain[1].transactions.iter().any(|t| t.get_hash() == tx.get_hash()));
+        assert!(bc.pending_transactions.is_empty());
+        assert_eq!(bc.get_balance(&alice, "VLT"), 750_000);
+    }
+
+    #[test]
+    fn scripts_add_conditions_but_never_replace_the_signature() {
+        let mut bc = test_chain();
+        let (alice_key, alice) = key(1);
+        let (mallory_key, _) = key(2);
+        bc.state.set_balance(&alice, "VLT", 1_000_000);
+
+        // Self-satisfying script with someone else's signature
+        let mut forged = Transaction::new(alice.clone(), BOB.to_string(), 1_000_000, "VLT".to_string(), 1);
+        forged.sign(&mallory_key);
+        forged.script_sig = Script::new().push(OpCode::OpPush(vec![1]));
+        forged.script_pub_key = Script::new().push(OpCode::OpPush(vec![1]));
+        assert!(bc.state.check_scripts(&forged).is_ok());
+        assert!(!bc.create_transaction(forged.clone()));
+        assert!(!mine_template(&mut bc, BOB, vec![forged]));
+        assert_eq!(bc.get_balance(&alice, "VLT"), 1_000_000);
+
+        // A signed tx with a failing condition is refused, a holding one admitted
+        let mut guarded = Transaction::new(alice.clone(), BOB.to_string(), 100, "VLT".to_string(), 1);
+        guarded.script_pub_key = Script::new().push(OpCode::OpPush(vec![0]));
+        guarded.sign(&alice_key);
+        assert!(!bc.create_transaction(guarded.clone()));
+        guarded.script_pub_key = Script::new().push(OpCode::OpPush(vec![1]));
+        assert!(bc.create_transaction(guarded));
+    }
+
+    #[test]
+    fn wallet_signed_issue_goes_through_the_mempool() {
+        let mut bc = test_chain();
+        let (alice_key, alice) = key(1);
+        let mut tx = issue(&alice, "TKN", 5_000, r#"{"name":"Token","decimals":2}"#, 1);
+        tx.sign(&alice_key);
+        assert!(bc.create_transaction(tx));
+        assert!(mine_template(&mut bc, BOB, vec![]));
+        assert_eq!(bc.get_balance(&alice, "TKN"), 5_000);
+        assert_eq!(bc.state.token_meta["TKN"].decimals, 2);
+
+        // Symbol taken: refused at admission
+        let (bob_key, bob) = key(2);
+        let mut again = issue(&bob, "TKN", 1, "", 1);
+        again.sign(&bob_key);
+        assert!(!bc.create_transaction(again));
+    }
+
+    #[test]
+    fn wallet_signed_cancel_goes_through_the_mempool() {
+        let mut bc = test_chain();
+        let (alice_key, alice) = key(1);
+        let (bob_key, bob) = key(2);
+        bc.state.set_balance(&alice, "VLT", 10_000);
+        let mut tkn = issue(&bob, "TKN", 1_000, "", 1);
+        tkn.sign(&bob_key);
+        let mut order = Transaction::new_order(alice.clone(), "TKN".to_string(), "BUY", 10, 5, 1);
+        order.sign(&alice_key);
+        assert!(bc.create_transaction(tkn));
+        assert!(mine_template(&mut bc, &bob, vec![]));
+        assert!(bc.create_transaction(order.clone()));
+        assert!(mine_template(&mut bc, &bob, vec![]));
+        let id = hex::encode(order.get_hash());
+        assert!(bc.state.orders.contains_key(&id));
+        assert_eq!(bc.get_balance(&alice, "VLT"), 10_000 - 50);
+
+        // Only the creator can cancel
+        let mut theirs = Transaction::new_cancel(bob.clone(), id.clone(), 2);
+        theirs.sign(&bob_key);
+        assert!(!bc.create_transaction(theirs));
+        let mut cancel = Transaction::new_cancel(alice.clone(), id.clone(), 2);
+        cancel.sign(&alice_key);
+        assert!(bc.create_transaction(cancel));
+        assert!(mine_template(&mut bc, &bob, vec![]));
+        assert!(!bc.state.orders.contains_key(&id));
+        assert_eq!(bc.get_balance(&alice, "VLT"), 10_000);
+    }
+
+    #[test]
+    fn candidate_signatures_skip_coinbases_and_assume_valid_ancestors() {
+        let mut bc = test_chain();
+        for nonce in 1..=3 {
+            let coinbase = Transaction::new("SYSTEM".to_string(), BOB.to_string(), 0, "VLT".to_string(), 0);
+            push_block(&mut bc, vec![coinbase, Transaction::new(BOB.to_string(), ALICE.to_string(), 0, "VLT".to_string(), nonce)]);
+        }
+        assert_eq!(Blockchain::candidate_signatures(&bc.chain, None).len(), 3);
+        let assumed = bc.chain[2].hash.clone();
+        let pending = Blockchain::candidate_signatures(&bc.chain, Some(&assumed));
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].nonce, 3);
+        // An assume-valid hash the candidate does not contain skips nothing
+        assert_eq!(Blockchain::candidate_signatures(&bc.chain, Some(&"00".repeat(32))).len(), 3);
+    }
+
+    // Window of 4 blocks, 3 signalling blocks lock in. "soft" (bit 2) may start at 4 and times out at 20.
+    fn versionbits_chain(signalling: &[bool]) -> Blockchain {
+        let mut bc = test_chain();
+        bc.params.retarget_interval = 4;
+        bc.params.activation_threshold = 3;
+        bc.params.deployments = vec![Deployment { name: "soft".to_string(), bit: 2, start_height: 4, timeout_height: 20 }];
+        for signals in signalling {
+            let tip = bc.chain.last().unwrap();
+            let mut block = Block::new(tip.index + 1, tip.hash.clone(), vec![], 0x207fffff, 0);
+            block.timestamp = tip.timestamp + 60;
+            block.version = if *signals { VERSIONBITS_TOP_BITS | 1 << 2 } else { VERSIONBITS_TOP_BITS };
+            block.hash = block.calculate_hash();
+            bc.chain.push(block);
+        }
+        bc
+    }
+
+    fn soft_state(bc: &Blockchain, height: u64) -> DeploymentState {
+        bc.deployment_state(&bc.params.deployments[0], height)
+    }
+
+    #[test]
+    fn deployment_locks_in_and_activates() {
+        // Blocks 1-3 | 4-7 (3 of 4 signal) | 8-11 | 12-
+        let bc = versionbits_chain(&[false, false, false, true, true, false, true, false, false, false, false, false]);
+        assert_eq!(soft_state(&bc, 3), DeploymentState::Defined);
+        assert_eq!(soft_state(&bc, 4), DeploymentState::Started);
+        assert_eq!(soft_state(&bc, 7), DeploymentState::Started);
+        assert_eq!(soft_state(&bc, 8), DeploymentState::LockedIn);
+        assert_eq!(soft_state(&bc, 12), DeploymentState::Active);
+        assert_eq!(soft_state(&bc, 40), DeploymentState::Active);
+        assert!(bc.is_deployment_active("soft", 12));
+        assert!(!bc.is_deployment_active("soft", 11));
+        assert_eq!(bc.compute_block_version(8) & (1 << 2), 1 << 2); // Still signalled while LockedIn
+        assert_eq!(bc.compute_block_version(12), VERSIONBITS_TOP_BITS);
+    }
+
+    #[test]
+    fn deployment_fails_at_timeout() {
+        // 2 of 4 per window never reaches the threshold
+        let bc = versionbits_chain(&[[true, true, false, false]; 6].concat());
+        assert_eq!(soft_state(&bc, 16), DeploymentState::Started);
+        assert_eq!(soft_state(&bc, 20), DeploymentState::Failed);
+        assert_eq!(soft_state(&bc, 24), DeploymentState::Failed);
+
+        // Timeout before the start height: never started
+        let mut bc = versionbits_chain(&[true; 8]);
+        bc.params.deployments[0].start_height = 8;
+        bc.params.deployments[0].timeout_height = 4;
+        assert_eq!(soft_state(&bc, 4), DeploymentState::Failed);
+    }
+
+    #[test]
+    fn deployment_states_are_cached_per_branch() {
+        let locked = versionbits_chain(&[false, false, false, true, true, true, true, false]);
+        assert_eq!(soft_state(&locked, 8), DeploymentState::LockedIn);
+        assert_eq!(locked.deployment_cache.lock().unwrap().len(), 2); // Windows starting at 4 and 8
+
+        // Same heights on a branch without signalling: evaluated on its own hashes
+        let plain = versionbits_chain(&[false; 8]);
+        assert_eq!(locked.deployment_state_on(&plain.chain, &locked.params.deployments[0], 8), DeploymentState::Started);
+        assert_eq!(locked.deployment_cache.lock().unwrap().len(), 3); // Window 4 shared (same ancestors)
+        assert_eq!(soft_state(&locked, 8), DeploymentState::LockedIn);
+    }
+
+    // Windows of 2 blocks and one signalling block locks in: "stakesig" is Active from height 6
+    fn stakesig_chain() -> Blockchain {
+        let mut bc = test_chain();
+        bc.params.retarget_interval = 2;
+        bc.params.activation_threshold = 1;
+        bc.params.deployments.retain(|d| d.name == "stakesig");
+        bc
+    }
+
+    // Next block by `miner` claiming `claim` of stake, `commit` in the coinbase, validator signature by `signer`
+    fn claim_block(bc: &Blockchain, miner: &str, claim: u64, commit: u64, signer: Option<&SigningKey>, txs: Vec<Transaction>) -> Block {
+        let tip = bc.chain.last().unwrap();
+        let height = tip.index + 1;
+        let mut coinbase = Transaction::new("SYSTEM".to_string(), miner.to_string(), bc.calculate_reward(height), "VLT".to_string(), 0);
+        coinbase.price = commit;
+        let mut block = Block::new(height, tip.hash.clone(), [vec![coinbase], txs].concat(), 0x207fffff, claim);
+        block.timestamp = tip.timestamp + 60;
+        block.version = bc.compute_block_version(height);
+        block.hash = block.calculate_hash();
+        if let Some(key) = signer { block.sign_validator(key); }
+        block
+    }
+
+    // Validator bonds 40 VLT of its block 1 reward in block 2, blocks up to `height` claim nothing
+    fn bonded_validator(bc: &mut Blockchain, height: u64) -> (SigningKey, String) {
+        let (validator, address) = key(7);
+        let mut stake = Transaction::new_stake(address.clone(), 40 * 100_000_000, 1);
+        stake.sign(&validator);
+        assert!(bc.submit_block(claim_block(bc, &address, 0, 0, None, vec![])));
+        assert!(bc.submit_block(claim_block(bc, &address, 0, 0, None, vec![stake])));
+        unclaimed_blocks(bc, &address, height);
+        (validator, address)
+    }
+
+    // Blocks by `miner` without a stake claim up to `height`
+    fn unclaimed_blocks(bc: &mut Blockchain, miner: &str, height: u64) {
+        while (bc.chain.len() as u64) <= height {
+            assert!(bc.submit_block(claim_block(bc, miner, 0, 0, None, vec![])));
+        }
+    }
+
+    #[test]
+    fn stake_claims_are_bounded_and_signed_once_stakesig_is_active() {
+        let mut bc = stakesig_chain();
+        let (validator, address) = bonded_validator(&mut bc, 2);
+        let bonded = 40 * 100_000_000;
+        assert_eq!(bc.state.get_validator_stake(&address), bonded);
+
+        // Before activation: any claim up to the bonded stake, no commitment or signature needed
+        assert!(!bc.is_deployment_active("stakesig", 3));
+        assert!(!bc.submit_block(claim_block(&bc, &address, bonded + 1, 0, None, vec![])));
+        assert!(bc.submit_block(claim_block(&bc, &address, bonded, 0, None, vec![])));
+        unclaimed_blocks(&mut bc, &address, 5);
+
+        assert!(bc.is_deployment_active("stakesig", 6));
+        let (other, _) = key(8);
+        assert!(!bc.submit_block(claim_block(&bc, &address, bonded, 0, Some(&validator), vec![]))); // Not committed
+        assert!(!bc.submit_block(claim_block(&bc, &address, bonded, bonded, None, vec![]))); // Unsigned
+        assert!(!bc.submit_block(claim_block(&bc, &address, bonded, bonded, Some(&other), vec![]))); // Forged
+        let mut tampered = claim_block(&bc, &address, bonded - 1, bonded - 1, Some(&validator), vec![]);
+        tampered.validator_stake = bonded - 2; // Signature covers the claim
+        tampered.transactions[0].price = bonded - 2;
+        tampered.merkle_root = Block::calculate_merkle_root(&tampered.transactions);
+        tampered.hash = tampered.calculate_hash();
+        assert!(!bc.submit_block(tampered));
+        assert_eq!(bc.chain.len(), 6);
+
+        assert!(bc.submit_block(claim_block(&bc, &address, bonded, bonded, Some(&validator), vec![])));
+        assert!(bc.submit_block(claim_block(&bc, &address, 0, 0, None, vec![]))); // No claim, nothing to sign
+    }
+
+    #[test]
+    fn chain_replacement_checks_stake_claims() {
+        let mut local = stakesig_chain();
+        let mut remote = stakesig_chain();
+        let (validator, address) = bonded_validator(&mut remote, 2);
+        let bonded = 40 * 100_000_000;
+
+        // Overclaim before activation
+        let overclaim = claim_block(&remote, &address, bonded + 1, 0, None, vec![]);
+        let mut candidate = remote.chain.clone();
+        candidate.push(overclaim);
+        assert!(!local.attempt_chain_replacement(candidate));
+
+        // Unsigned claim after activation
+        unclaimed_blocks(&mut remote, &address, 5);
+        let unsigned = claim_block(&remote, &address, bonded, bonded, None, vec![]);
+        let mut candidate = remote.chain.clone();
+        candidate.push(unsigned);
+        assert!(!local.attempt_chain_replacement(candidate));
+        assert_eq!(local.chain.len(), 1);
+
+        assert!(remote.submit_block(claim_block(&remote, &address, bonded, bonded, Some(&validator), vec![])));
+        assert!(local.attempt_chain_replacement(remote.chain.clone()));
+        assert_eq!(local.chain.last().unwrap().hash, remote.chain.last().unwrap().hash);
+        assert_eq!(local.state.get_validator_stake(&address), bonded);
+        assert_eq!(local.state.get_balance(&address, "VLT"), remote.state.get_balance(&address, "VLT"));
+    }
+
+    // Bare state with the given bonded stakes (no chain behind it)
+    fn staked_state(stakes: &[(&str, u64)]) -> ChainState {
+        let mut state = ChainState::new();
+        for (staker, stake) in stakes {
+            state.stakes.insert(staker.to_string(), *stake);
+        }
+        state
+    }
+
+    fn enter_block(state: &mut ChainState, height: u64) -> String {
+        let mut block = Block::new(height, format!("parent-{}", height), vec![], 0x207fffff, 0);
+        block.timestamp = 1_800_000_000 + height * 60;
+        block.hash = block.calculate_hash();
+        state.begin_block(&block);
+        block.hash
+    }
+
+    fn attest(staker: &str, height: u64, hash: &str) -> Transaction {
+        Transaction::new_attestation(staker.to_string(), height, hash.to_string(), height)
+    }
+
+    #[test]
+    fn two_thirds_of_stake_finalizes_a_checkpoint() {
+        let carol = "02c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0";
+        let mut state = staked_state(&[(ALICE, 40), (BOB, 26), (carol, 34)]);
+        enter_block(&mut state, FINALITY_INTERVAL - 1);
+        assert!(state.finality_checkpoints.is_empty());
+        let hash = enter_block(&mut state, FINALITY_INTERVAL);
+        assert!(state.finality_checkpoints.contains_key(&FINALITY_INTERVAL));
+
+        assert!(!state.apply_transaction(&attest(ALICE, FINALITY_INTERVAL, "other-hash")));
+        assert!(!state.apply_transaction(&attest("02dddd", FINALITY_INTERVAL, &hash))); // No stake
+        assert!(state.apply_transaction(&attest(ALICE, FINALITY_INTERVAL, &hash)));
+        assert!(!state.apply_transaction(&attest(ALICE, FINALITY_INTERVAL, &hash))); // Already attested
+        assert!(state.apply_transaction(&attest(BOB, FINALITY_INTERVAL, &hash)));
+        assert_eq!(state.finalized, None); // 66 of 100
+
+        assert!(state.apply_transaction(&attest(carol, FINALITY_INTERVAL, &hash)));
+        assert_eq!(state.finalized, Some((FINALITY_INTERVAL, hash.clone())));
+        assert!(state.finality_checkpoints.is_empty());
+        assert!(state.check_attestation(&attest(carol, FINALITY_INTERVAL, &hash)).is_err());
+
+        // Exactly 2/3 is enough
+        let mut state = staked_state(&[(ALICE, 2), (BOB, 1)]);
+        let hash = enter_block(&mut state, 3 * FINALITY_INTERVAL);
+        assert!(state.apply_transaction(&attest(ALICE, 3 * FINALITY_INTERVAL, &hash)));
+        assert_eq!(state.finalized, Some((3 * FINALITY_INTERVAL, hash)));
+    }
+
+    #[test]
+    fn checkpoints_expire_after_the_finality_window() {
+        let mut state = staked_state(&[(ALICE, 1), (BOB, 1)]);
+        let first = enter_block(&mut state, FINALITY_INTERVAL);
+        enter_block(&mut state, FINALITY_INTERVAL + FINALITY_WINDOW - 1);
+        assert!(state.check_attestation(&attest(ALICE, FINALITY_INTERVAL, &first)).is_ok());
+        enter_block(&mut state, FINALITY_INTERVAL + FINALITY_WINDOW + 1);
+        assert!(state.check_attestation(&attest(ALICE, FINALITY_INTERVAL, &first)).is_err());
+
+        // Finalizing a checkpoint drops the older ones still open
+        let mut state = staked_state(&[(ALICE, 1)]);
+        let first = enter_block(&mut state, FINALITY_INTERVAL);
+        let second = enter_block(&mut state, 2 * FINALITY_INTERVAL);
+        assert!(state.check_attestation(&attest(ALICE, FINALITY_INTERVAL, &first)).is_ok());
+        assert!(state.apply_transaction(&attest(ALICE, 2 * FINALITY_INTERVAL, &second)));
+        assert!(state.check_attestation(&attest(ALICE, FINALITY_INTERVAL, &first)).is_err());
+    }
+
+    #[test]
+    fn reorgs_below_the_finalized_height_are_refused() {
+        let mut local = test_chain();
+        let mut fork = test_chain();
+        for _ in 0..3 {
+            assert!(local.submit_block(claim_block(&local, ALICE, 0, 0, None, vec![])));
+        }
+        assert!(fork.submit_block(claim_block(&fork, BOB, 0, 0, None, vec![])));
+        local.state.finalized = Some((2, local.chain[2].hash.clone()));
+
+        // Longer, but replaces finalized block #2
+        while fork.chain.len() < 6 {
+            assert!(fork.submit_block(claim_block(&fork, BOB, 0, 0, None, vec![])));
+        }
+        assert!(!local.attempt_chain_replacement(fork.chain.clone()));
+        assert_eq!(local.chain.len(), 4);
+
+        // Forks above the finalized block are still allowed
+        let mut extension = local.chain[..3].to_vec();
+        while extension.len() < 6 {
+            let tip = extension.last().unwrap();
+            let mut block = claim_block(&local, BOB, 0, 0, None, vec![]);
+            block.index = tip.index + 1;
+            block.previous_hash = tip.hash.clone();
+            block.timestamp = tip.timestamp + 30;
+            block.hash = block.calculate_hash();
+            extension.push(block);
+        }
+        assert!(local.attempt_chain_replacement(extension.clone()));
+        assert_eq!(local.chain.last().unwrap().hash, extension[5].hash);
+    }
+
+    // `count` distinct signed stake claims by `key` on the same parent at `height`
+    fn conflicting_claims(key: &SigningKey, height: u64, count: u64) -> Vec<crate::block::BlockHeader> {
+        (0..count).map(|i| {
+            let mut block = Block::new(height, format!("parent-{}", height), vec![], 0x207fffff, 1_000);
+            block.timestamp = 1_800_000_000 + i;
+            block.hash = block.calculate_hash();
+            block.sign_validator(key);
+            block.header()
+        }).collect()
+    }
+
+    fn report(reporter: &str, offender: &str, a: &crate::block::BlockHeader, b: &crate::block::BlockHeader, nonce: u64) -> Transaction {
+        let evidence = EquivocationEvidence { header_a: a.clone(), header_b: b.clone() };
+        Transaction::new_slash(reporter.to_string(), offender.to_string(), serde_json::to_string(&evidence).unwrap(), nonce)
+    }
+
+    #[test]
+    fn equivocation_is_slashed_once_per_height() {
+        let (key, offender) = key(5);
+        let mut state = staked_state(&[(offender.as_str(), 1_000)]);
+        state.delegations.entry(offender.clone()).or_default().insert(ALICE.to_string(), 400);
+        let claims = conflicting_claims(&key, 9, 3);
+
+        assert!(!state.apply_transaction(&report(BOB, BOB, &claims[0], &claims[1], 1))); // Not BOB's headers
+        assert!(!state.apply_transaction(&report(&offender, &offender, &claims[0], &claims[1], 1))); // Self-report
+        assert!(state.apply_transaction(&report(BOB, &offender, &claims[0], &claims[1], 1)));
+        // 50% of 1000 own + 50% of 400 delegated, 10% of it to the reporter
+        assert_eq!(state.stakes[&offender], 500);
+        assert_eq!(state.delegations[&offender][ALICE], 200);
+        assert_eq!(state.get_balance(BOB, "VLT"), 70);
+        assert_eq!(state.slashed_burned, 630);
+
+        // Same pair reversed, or a third header at the same height: already punished
+        assert!(!state.apply_transaction(&report(BOB, &offender, &claims[1], &claims[0], 2)));
+        assert!(!state.apply_transaction(&report(ALICE, &offender, &claims[0], &claims[2], 1)));
+        assert_eq!(state.stakes[&offender], 500);
+
+        // Another height is another offence
+        let later = conflicting_claims(&key, 10, 2);
+        assert!(state.apply_transaction(&report(ALICE, &offender, &later[0], &later[1], 1)));
+        assert_eq!(state.stakes[&offender], 250);
+    }
+
+    #[test]
+    fn fully_slashed_validators_leave_the_validator_set() {
+        let (key, offender) = key(6);
+        let mut state = staked_state(&[(offender.as_str(), 100)]);
+        state.unbonding.insert(offender.clone(), vec![Unbonding { amount: 300, release_height: 50, recipient: String::new() }]);
+        let claims = conflicting_claims(&key, 4, 2);
+
+        // Penalty 200: the whole stake, then 100 from the unbonding queue
+        assert!(state.apply_transaction(&report(BOB, &offender, &claims[0], &claims[1], 1)));
+        assert!(!state.stakes.contains_key(&offender));
+        assert_eq!(state.get_unbonding_total(&offender), 200);
+        assert_eq!(state.get_validator_stake(&offender), 0);
+        assert!(state.staking_payouts(1_000).iter().all(|(addr, _)| addr != &offender));
+    }
+
+    #[test]
+    fn commission_raises_are_rate_limited_once_delegated() {
+        let (_, validator) = key(11);
+        let mut state = staked_state(&[(validator.as_str(), 1_000)]);
+        let set = |bps: u64, nonce: u64| Transaction::new_set_commission(validator.clone(), bps, nonce);
 
-        if transaction.sender != "SYSTEM" {
-             let current_nonce = *self.state.nonces.get(&transaction.sender).unwrap_or(&0);
-             if transaction.nonce <= current_nonce {
-                 println!("Error: Invalid Nonce");
-                 return false;
-             }
-        }
+        // No delegators yet: any commission up to the cap
+        assert!(!state.apply_transaction(&set(MAX_COMMISSION_BPS + 1, 1)));
+        assert!(state.apply_transaction(&set(2_000, 1)));
+        assert!(state.apply_transaction(&set(500, 2)));
 
-        match transaction.tx_type {
-            TxType::IssueToken => {
-                 if transaction.token == "VLT" { return false; }
-                 if self.state.tokens.contains_key(&transaction.token) { return false; }
-                 if transaction.token.len() < 3 || transaction.token.len() > 8 { return false; }
-            },
-            TxType::Burn => {
-                 if transaction.token == "VLT" { return false; } // Can prevent burning VLT if desired, or allow it.
-                 // Just check balance
-                 if self.get_balance(&transaction.sender, &transaction.token) < transaction.amount { return false; }
-            },
-            TxType::PlaceOrder => {
-                 // 1. Lock Funds
-                 let side = if transaction.receiver == "DEX_BUY" { "BUY" } else { "SELL" };
-                 
-                 // If BUY: User wants to buy Token using VLT. Must lock (Price * Amount) VLT.
-                 // If SELL: User wants to sell Token for VLT. Must lock Amount Token.
-                 
-                 if side == "BUY" {
-                     let cost = transaction.price * transaction.amount;
-                     let bal = self.get_balance(&transaction.sender, "VLT");
-                     if bal < cost { return false; }
-                     // Deduct VLT
-                     self.set_balance(&transaction.sender, "VLT", bal - cost);
-                 } else {
-                     let bal = self.get_balance(&transaction.sender, &transaction.token);
-                     if bal < transaction.amount { return false; }
-                     // Deduct Token
-                     self.set_balance(&transaction.sender, &transaction.token, bal - transaction.amount);
-                 }
+        state.delegations.entry(validator.clone()).or_default().insert(ALICE.to_string(), 1_000);
+        state.height = 10;
+        assert!(!state.apply_transaction(&set(MAX_COMMISSION_BPS, 3)));
+        assert!(!state.apply_transaction(&set(500 + MAX_COMMISSION_RAISE_BPS + 1, 3)));
+        assert!(state.apply_transaction(&set(500 + MAX_COMMISSION_RAISE_BPS, 3)));
+        assert_eq!(state.commission_raised[&validator], 10);
 
-                 // 2. Create Order Object
-                 let mut order = Order {
-                     id: hex::encode(&transaction.signature[0..10]), // fast ID from sig
-                     creator: transaction.sender.clone(),
-                     token: transaction.token.clone(),
-                     side: side.to_string(),
-                     price: transaction.price,
-                     amount: transaction.amount,
-                     timestamp: transaction.timestamp,
-                 };
-                 
-                 // 3. MATCHING ENGINE (Optimized)
-                 let mut filled = false;
-                 
-                 // Collect matches (Price Priority)
-                 // If BUY, match against ASKS (Sell orders). Lowest price first.
-                 // If SELL, match against BIDS (Buy orders). Highest price first.
-                 
-                 let matches: Vec<(String, u64, u64, String)> = if side == "BUY" {
-                      // BTreeMap iter gives keys ascending (Low Price -> High Price)
-                      self.state.asks.range((order.token.clone(), 0, 0)..(order.token.clone(), order.price + 1, u64::MAX))
-                         .map(|(k, v)| (v.clone(), k.1, k.2, "ASK".to_string()))
-                         .collect()
-                 } else {
-                      // Borrow check workaround: Need range then reverse for Bids (High -> Low)
-                      // Filter for token
-                      self.state.bids.iter().rev()
-                         .filter(|(k, _)| k.0 == order.token)
-                         .take_while(|(k, _)| k.1 >= order.price)
-                         .map(|(k, v)| (v.clone(), k.1, k.2, "BID".to_string()))
-                         .collect()
-                 };
+        // One raise per Unbonding Period, cuts any time
+        state.height = 10 + UNBONDING_PERIOD - 1;
+        assert!(!state.apply_transaction(&set(700, 4)));
+        assert!(state.apply_transaction(&set(100, 4)));
+        state.height = 10 + UNBONDING_PERIOD;
+        assert!(state.apply_transaction(&set(200, 5)));
+        assert_eq!(state.commissions[&validator], 200);
+    }
 
-                 // Note: We collected IDs to avoid holding the BTreeMap borrow
-                 for (maker_id, maker_price, _maker_time, _maker_tab) in matches {
-                      if order.amount == 0 { break; }
-                      
-                      let match_data = if let Some(maker) = self.state.orders.get(&maker_id) {
-                           Some((maker.amount, maker.creator.clone()))
-                      } else { None };
-
-                      if let Some((maker_amount, maker_creator)) = match_data {
-                           let trade_amt = std::cmp::min(order.amount, maker_amount);
-                           
-                           // Update Candles
-                           self.state.update_candle(&order.token, maker_price, trade_amt, transaction.timestamp);
-
-                           let value = trade_amt * maker_price;
-                           let seller = if side == "SELL" { &order.creator } else { &maker_creator };
-                           let buyer = if side == "BUY" { &order.creator } else { &maker_creator };
-                           
-                           let seller_c = seller.clone();
-                           let buyer_c = buyer.clone();
-                           
-                           // Credit Seller VLT
-                           let s_bal = self.get_balance(&seller_c, "VLT");
-                           self.set_balance(&seller_c, "VLT", s_bal + value);
-                           
-                           // Credit Buyer Token
-                           let b_bal = self.get_balance(&buyer_c, &order.token);
-                           self.set_balance(&buyer_c, &order.token, b_bal + trade_amt);
-                           
-                           order.amount -= trade_amt;
-                           
-                           // Update Maker
-                           let mut remove_maker = false;
-                           if let Some(maker) = self.state.orders.get_mut(&maker_id) {
-                                maker.amount -= trade_amt;
-                                if maker.amount == 0 { remove_maker = true; }
-                           }
-                           
-                           if remove_maker {
-                               if let Some(m) = self.state.orders.remove(&maker_id) {
-                                   if side == "BUY" { // Matched Ask
-                                       self.state.asks.remove(&(m.token, m.price, m.timestamp));
-                                   } else { // Matched Bid
-                                       self.state.bids.remove(&(m.token, m.price, m.timestamp));
-                                   }
-                               }
-                           }
-                      }
-                 }
+    #[test]
+    fn staking_payouts_split_commission_between_validator_and_delegators() {
+        let carol = "02c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0";
+        let mut state = staked_state(&[("validator-a", 3_000), ("validator-b", 1_000)]);
+        state.delegations.entry("validator-a".to_string()).or_default().insert(ALICE.to_string(), 3_000);
+        state.delegations.entry("validator-a".to_string()).or_default().insert(BOB.to_string(), 1_000);
+        state.delegations.entry("validator-b".to_string()).or_default().insert(carol.to_string(), 2_000);
+        state.commissions.insert("validator-a".to_string(), 1_000); // 10%
 
-                 // 4. Save Remainder
-                 if order.amount > 0 {
-                     self.state.orders.insert(order.id.clone(), order.clone());
-                     let key = (order.token.clone(), order.price, order.timestamp);
-                     if side == "BUY" {
-                         self.state.bids.insert(key, order.id);
-                     } else {
-                         self.state.asks.insert(key, order.id);
-                     }
-                 }
-            },
-            TxType::CancelOrder => {
-                // Return funds
-                if let Some(order) = self.state.orders.remove(&transaction.token) { // token field holds OrderID
-                    if order.creator != transaction.sender {
-                        // Unauthorized! Put it back.
-                        self.state.orders.insert(transaction.token.clone(), order);
-                        return false;
-                    }
-                    
-                    // Cleanup Indices
-                    let key = (order.token.clone(), order.price, order.timestamp);
-                    if order.side == "BUY" {
-                        self.state.bids.remove(&key);
-                    } else {
-                        self.state.asks.remove(&key);
-                    }
+        // 10,000 bonded: A backs 7,000 (own 3,000), B backs 3,000 (own 1,000, no commission)
+        let payouts: HashMap<String, u64> = state.staking_payouts(100_000).into_iter().collect();
+        assert_eq!(payouts["validator-a"], 30_000 + 4_000); // Own share + 10% of 40,000
+        assert_eq!(payouts[ALICE], 27_000);
+        assert_eq!(payouts[BOB], 9_000);
+        assert_eq!(payouts["validator-b"], 10_000);
+        assert_eq!(payouts[carol], 20_000);
+        assert_eq!(payouts.values().sum::<u64>(), 100_000);
+    }
 
-                    // Refund
-                    if order.side == "BUY" {
-                        let cost = order.price * order.amount;
-                        let bal = self.get_balance(&order.creator, "VLT");
-                        self.set_balance(&order.creator, "VLT", bal + cost);
-                    } else {
-                        let bal = self.get_balance(&order.creator, &order.token);
-                        self.set_balance(&order.creator, &order.token, bal + order.amount);
-                    }
-                }
-            },
-            TxType::Transfer => {
-                let bal = self.get_balance(&transaction.sender, &transaction.token);
-                
-                // V2: Dynamic Fee enforcement
-                // Rule: 0.1% of Amount + 1 VLT per pending tx
-                // ATOMIC UNITS:
-                // 1 VLT = 100,000,000
-                // 0.1% = amount / 1000
-                // 1 VLT congestion = 100,000,000 per tx
-                
-                let congestion_count = self.pending_transactions.len() as u64;
-                let congestion_surcharge = congestion_count * 100_000_000; // 1 VLT per tx
-                
-                let amount_factor = transaction.amount / 1000; // 0.1%
-                let min_fee = amount_factor + congestion_surcharge;
-                
-                // Enforce minimum of 0.001 VLT (100,000 units) base
-                let base_min = 100_000; 
-                let effective_min_fee = if min_fee < base_min { base_min } else { min_fee };
-                
-                /*
-                if transaction.fee < effective_min_fee {
-                    println!("Rejected: Fee too low. Required: {}, Provided: {}", effective_min_fee, transaction.fee);
-                    return false;
-                }
-                */
+    #[test]
+    fn undelegated_vlt_unbonds_before_release() {
+        let (_, validator) = key(12);
+        let mut state = staked_state(&[(validator.as_str(), 500)]);
+        state.set_balance(ALICE, "VLT", 1_000);
 
-                // Fee excluded from requirement
-                let required = transaction.amount; // + transaction.fee;
-                
-                // Fix Double Spend: Check pending transactions
-                let pending_spent: u64 = self.pending_transactions.iter()
-                    .filter(|t| t.sender == transaction.sender && t.token == transaction.token && t.tx_type == TxType::Transfer)
-                    .map(|t| t.amount) // + t.fee)
-                    .sum();
+        assert!(!state.apply_transaction(&Transaction::new_delegate(ALICE.to_string(), validator.clone(), 1_001, 1)));
+        assert!(state.apply_transaction(&Transaction::new_delegate(ALICE.to_string(), validator.clone(), 800, 1)));
+        assert_eq!(state.get_balance(ALICE, "VLT"), 200);
+        assert_eq!(state.get_validator_stake(&validator), 1_300);
 
-                if bal < (required + pending_spent) { 
-                    println!("Rejected: Double Spend / Insufficient Funds (Pending: {}, New: {}, Bal: {})", pending_spent, required, bal);
-                    return false; 
-                }
-            },
-            TxType::Stake => {
-                 if transaction.token != "VLT" { return false; }
-                 let bal = self.get_balance(&transaction.sender, "VLT");
-                 if bal < transaction.amount { return false; }
-            },
-            TxType::Unstake => {
-                 // Check if staked amount >= amount
-                 let current_stake = *self.state.stakes.get(&transaction.sender).unwrap_or(&0);
-                 if current_stake < transaction.amount { return false; }
-            },
-            TxType::AddLiquidity => {
-                 let parts: Vec<&str> = transaction.token.split('/').collect();
-                 if parts.len() != 2 { return false; }
-                 let token_a = parts[0];
-                 let token_b = parts[1];
-                 
-                 let amount_a = transaction.amount;
-                 let amount_b = transaction.price; // Using price field for second amount
-                 let sender = &transaction.sender;
-                 
-                 if self.get_balance(sender, token_a) < amount_a { return false; }
-                 if self.get_balance(sender, token_b) < amount_b { return false; }
-                 
-                 let pool_id = transaction.token.clone();
-                 
-                 // 1. Calculate Shares
-                 let (reserve_a, reserve_b, total_shares) = if let Some(p) = self.state.pools.get(&pool_id) {
-                     (p.reserve_a, p.reserve_b, p.total_shares)
-                 } else {
-                     (0, 0, 0)
-                 };
-                 
-                 let shares = if total_shares == 0 {
-                     ((amount_a as f64 * amount_b as f64).sqrt()) as u64
-                 } else {
-                     let s_a = (amount_a * total_shares) / reserve_a;
-                     let s_b = (amount_b * total_shares) / reserve_b;
-                     std::cmp::min(s_a, s_b)
-                 };
-                 
-                 if shares == 0 { return false; }
-                 
-                 // 2. Debit User
-                 let old_a = self.get_balance(sender, token_a);
-                 let old_b = self.get_balance(sender, token_b);
-                 self.set_balance(sender, token_a, old_a - amount_a);
-                 self.set_balance(sender, token_b, old_b - amount_b);
-                 
-                 // 3. Update Pool
-                 {
-                     let pool = self.state.pools.entry(pool_id.clone()).or_insert(Pool {
-                         token_a: token_a.to_string(),
-                         token_b: token_b.to_string(),
-                         reserve_a: 0,
-                         reserve_b: 0,
-                         total_shares: 0,
-                     });
-                     pool.reserve_a += amount_a;
-                     pool.reserve_b += amount_b;
-                     pool.total_shares += shares;
-                 }
-                 
-                 // 4. Credit LP Tokens
-                 let lp_token = format!("LP-{}", pool_id);
-                 let old_lp = self.get_balance(sender, &lp_token);
-                 self.set_balance(sender, &lp_token, old_lp + shares);
-            },
-            TxType::RemoveLiquidity => {
-                 let pool_id = transaction.token.clone();
-                 if !self.state.pools.contains_key(&pool_id) { return false; }
-                 
-                 let shares = transaction.amount;
-                 let sender = &transaction.sender;
-                 let lp_token = format!("LP-{}", pool_id);
-                 
-                 if self.get_balance(sender, &lp_token) < shares { return false; }
-                 
-                 // 1. Calculate Amounts
-                 let (amount_a, amount_b, token_a, token_b) = {
-                     let pool = self.state.pools.get(&pool_id).unwrap();
-                     let a = (shares * pool.reserve_a) / pool.total_shares;
-                     let b = (shares * pool.reserve_b) / pool.total_shares;
-                     (a, b, pool.token_a.clone(), pool.token_b.clone())
-                 };
-                 
-                 if amount_a == 0 && amount_b == 0 { return false; }
-                 
-                 // 2. Debit LP
-                 let old_lp = self.get_balance(sender, &lp_token);
-                 self.set_balance(sender, &lp_token, old_lp - shares);
-                 
-                 // 3. Update Pool
-                 {
-                     let pool = self.state.pools.get_mut(&pool_id).unwrap();
-                     pool.total_shares -= shares;
-                     pool.reserve_a -= amount_a;
-                     pool.reserve_b -= amount_b;
-                 }
-                 
-                 // 4. Credit Assets
-                 let old_a = self.get_balance(sender, &token_a);
-                 let old_b = self.get_balance(sender, &token_b);
-                 self.set_balance(sender, &token_a, old_a + amount_a);
-                 self.set_balance(sender, &token_b, old_b + amount_b);
-            },
-            TxType::Swap => {
-                 let pool_id = transaction.token.clone();
-                 if !self.state.pools.contains_key(&pool_id) { return false; }
-                 
-                 let sender = &transaction.sender;
-                 let is_a_to_b = transaction.receiver == "SWAP_A_TO_B";
-                 let input_amount = transaction.amount;
-                 let min_output = transaction.price; 
-                 
-                 let (token_in, token_out, output_amount) = {
-                     let pool = self.state.pools.get(&pool_id).unwrap();
-                     let (rin, rout, t_in, t_out) = if is_a_to_b {
-                        (pool.reserve_a, pool.reserve_b, pool.token_a.clone(), pool.token_b.clone())
-                     } else {
-                        (pool.reserve_b, pool.reserve_a, pool.token_b.clone(), pool.token_a.clone())
-                     };
-                     
-                     let input_with_fee = input_amount * 997;
-                     let numerator = input_with_fee * rout;
-                     let denominator = (rin * 1000) + input_with_fee;
-                     let output = numerator / denominator;
-                     
-                     if output < min_output { return false; }
-                     (t_in, t_out, output)
-                 };
-                 
-                 // 1. Debit Input
-                 let bal_in = self.get_balance(sender, &token_in);
-                 if bal_in < input_amount { return false; }
-                 self.set_balance(sender, &token_in, bal_in - input_amount);
-                 
-                 // 2. Update Pool
-                 {
-                     let pool = self.state.pools.get_mut(&pool_id).unwrap();
-                     if is_a_to_b {
-                         pool.reserve_a += input_amount;
-                         pool.reserve_b -= output_amount;
-                     } else {
-                         pool.reserve_b += input_amount;
-                         pool.reserve_a -= output_amount;
-                     }
-                 }
-                 
-                 // 3. Credit Output
-                 let bal_out = self.get_balance(sender, &token_out);
-                 self.set_balance(sender, &token_out, bal_out + output_amount);
-
-                 // Update Candles (AMM) determines price.
-                 // Price = Input / Output * 10^8 ? Simplified: Price = VLT Value.
-                 // Let's assume pair is always X/VLT. If VLT is involved, we can map price.
-                 // For now, just store price as output/input ratio * 10^8
-                 let price = (input_amount * 100_000_000) / output_amount; // Rough price
-                 self.state.update_candle(&pool_id, price, output_amount, transaction.timestamp);
-            },
-            TxType::IssueNFT => {
-                 // Check if NFT exists
-                 if self.state.nfts.contains_key(&transaction.token) { return false; }
-                 
-                 // Sender matches creator (logic simplified: anyone can mint uniquely named NFT)
-                 // Token field = NFT ID
-                 // Receiver field = URI (Metadata)
-                 
-                 let nft = NFT {
-                     id: transaction.token.clone(),
-                     owner: transaction.sender.clone(),
-                     uri: transaction.receiver.clone(), // HACK: Reusing receiver field for URI
-                     created_at: transaction.timestamp,
-                 };
-                 self.state.nfts.insert(nft.id.clone(), nft);
-            },
-            TxType::TransferNFT => {
-                 if let Some(nft) = self.state.nfts.get_mut(&transaction.token) {
-                     if nft.owner != transaction.sender { return false; }
-                     nft.owner = transaction.receiver.clone();
-                 } else { return false; }
-            },
-            TxType::BurnNFT => {
-                 if let Some(nft) = self.state.nfts.get(&transaction.token) {
-                     if nft.owner != transaction.sender { return false; }
-                 } else { return false; }
-                 self.state.nfts.remove(&transaction.token);
-            }
-        }
+        enter_block(&mut state, 40);
+        assert!(!state.apply_transaction(&Transaction::new_undelegate(ALICE.to_string(), validator.clone(), 801, 2)));
+        assert!(state.apply_transaction(&Transaction::new_undelegate(ALICE.to_string(), validator.clone(), 300, 2)));
+        assert!(state.apply_transaction(&Transaction::new_undelegate(ALICE.to_string(), validator.clone(), 500, 3)));
+        assert!(!state.delegations.contains_key(&validator));
+        assert_eq!(state.get_validator_stake(&validator), 500);
+        assert_eq!(state.get_unbonding_total(ALICE), 800); // Still slashable, not yet spendable
+        assert_eq!(state.get_balance(ALICE, "VLT"), 200);
 
-        self.pending_transactions.push(transaction);
-        true
+        enter_block(&mut state, 40 + UNBONDING_PERIOD - 1);
+        assert_eq!(state.get_balance(ALICE, "VLT"), 200);
+        enter_block(&mut state, 40 + UNBONDING_PERIOD);
+        assert_eq!(state.get_balance(ALICE, "VLT"), 1_000);
+        assert_eq!(state.get_unbonding_total(ALICE), 0);
     }
 
-    pub fn mine_pending_transactions(&mut self, miner_address: String) {
-        let height = self.chain.len() as u64;
-        let mut reward = self.calculate_reward(height);
-        
-        // Phase 12: Fee Split Logic
-        let dev_wallet = "024dea39ce2e873d5be2d8e092044a7dbd9cfa2dadcba5d32e9b141b7361422d56";
-        let mut total_fees: u64 = 0;
-        
-        let mut txs = self.pending_transactions.clone();
-        
-        for tx in &txs {
-             total_fees += tx.fee;
-        }
-        
-        if total_fees > 0 {
-             let dev_share = total_fees * 20 / 100; // 20% Dev Tax (Updated)
-             let miner_share = total_fees - dev_share;
-             
-             // 1. Add Miner Share to Block Reward
-             reward += miner_share;
-             
-             // 2. Create Dev Tx
-             // Note: We create this from "SYSTEM" to avoid signature checks, but logically it comes from the fees.
-             // Since fees are deducted from sender in apply_transaction, we need to MINT this dev share?
-             // NO. apply_transaction deducts (amount + fee) from sender.
-             // It credits 'amount' to receiver.
-             // The 'fee' vanishes from state unless we credit it somewhere.
-             // So yes, we MINT the fee destination here.
-             
-             if dev_share > 0 {
-                  let dev_tx = Transaction::new(String::from("SYSTEM"), dev_wallet.to_string(), dev_share, "VLT".to_string(), 0);
-                  txs.push(dev_tx);
-             }
-        }
-        
-        let my_stake = *self.state.stakes.get(&miner_address).unwrap_or(&0);
-        let reward_tx = Transaction::new(String::from("SYSTEM"), miner_address.clone(), reward, "VLT".to_string(), 0);
-        txs.insert(0, reward_tx); 
+    fn propose(proposer: &str, kind: ProposalKind, nonce: u64) -> Transaction {
+        let content = ProposalContent { title: "Raise min fee".to_string(), description: String::new(), kind };
+        Transaction::new_proposal(proposer.to_string(), serde_json::to_string(&content).unwrap(), nonce)
+    }
 
-        // Staking Logic - Use local collection to avoid self-borrow issues if targeting self.pending (though here we target local txs, so it IS safe)
-        if !self.state.stakes.is_empty() {
-             let total_staked: u64 = self.state.stakes.values().sum();
-             if total_staked > 0 {
-                 for (staker, amount) in &self.state.stakes {
-                     let staking_inflation = 10;
-                     if let Some(total_reward) = amount.checked_mul(staking_inflation) {
-                         if let Some(share) = total_reward.checked_div(total_staked) {
-                             if share > 0 {
-                                  let stake_tx = Transaction::new(String::from("SYSTEM"), staker.clone(), share, "VLT".to_string(), 0);
-                                  txs.push(stake_tx);
-                             }
-                         }
-                     }
-                 }
-             }
-        }
+    #[test]
+    fn governance_tallies_against_the_submission_snapshot() {
+        let carol = "02c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0";
+        let mut state = staked_state(&[(ALICE, MIN_PROPOSER_STAKE), (BOB, MIN_PROPOSER_STAKE)]);
+        state.set_balance(carol, "VLT", 100 * MIN_PROPOSER_STAKE);
+        enter_block(&mut state, 5);
+        let param = ProposalKind::ParamChange { param: "min_fee".to_string(), value: 1_000 };
+        assert!(!state.apply_transaction(&propose(carol, param.clone(), 1))); // Proposer stake
+        assert!(state.apply_transaction(&propose(ALICE, param, 1)));
+        let proposal = state.proposals[&1].clone();
+        assert_eq!((proposal.end_height, proposal.activation_height), (5 + VOTING_PERIOD, 5 + VOTING_PERIOD + ACTIVATION_DELAY));
+        assert_eq!(proposal.total_voting_power, 2 * MIN_PROPOSER_STAKE);
 
-        let previous_block = self.chain.last().unwrap();
-        let difficulty = self.get_next_difficulty();
-        
-        println!("Mining block {} [Difficulty: {}, Reward: {:.8} VLT]...", previous_block.index + 1, difficulty, reward as f64 / 100_000_000.0);
+        // Stake bonded after submission neither votes nor dilutes the quorum
+        assert!(state.apply_transaction(&Transaction::new_stake(carol.to_string(), 98 * MIN_PROPOSER_STAKE, 2)));
+        assert!(!state.apply_transaction(&Transaction::new_vote(carol.to_string(), 1, "VOTE_NO", 3)));
+        assert!(state.apply_transaction(&Transaction::new_vote(BOB.to_string(), 1, "VOTE_NO", 1)));
+        assert!(state.apply_transaction(&Transaction::new_vote(BOB.to_string(), 1, "VOTE_YES", 2))); // Re-vote
+        let tally = state.tally_proposal(&state.proposals[&1]);
+        assert_eq!((tally.yes, tally.no, tally.total_staked), (MIN_PROPOSER_STAKE, 0, 2 * MIN_PROPOSER_STAKE));
 
-        let mut new_block = Block::new(
-            previous_block.index + 1,
-            previous_block.hash.clone(),
-            txs,
-            difficulty as usize,
-            my_stake
-        );
+        enter_block(&mut state, 5 + VOTING_PERIOD - 1);
+        assert!(state.apply_transaction(&Transaction::new_vote(ALICE.to_string(), 1, "VOTE_YES", 2)));
+        enter_block(&mut state, 5 + VOTING_PERIOD);
+        assert!(!state.apply_transaction(&Transaction::new_vote(ALICE.to_string(), 1, "VOTE_NO", 3)));
+        assert_eq!(state.proposals[&1].status, ProposalStatus::Passed);
+        assert_eq!(state.gov_params.min_fee, 0);
 
-        new_block.mine(difficulty as usize);
-        
-        // Pass 2: Apply to state
-        for tx in &new_block.transactions {
-            self.state.apply_transaction(tx);
-        }
+        // Applied after the activation delay
+        enter_block(&mut state, 5 + VOTING_PERIOD + ACTIVATION_DELAY - 1);
+        assert_eq!(state.gov_params.min_fee, 0);
+        enter_block(&mut state, 5 + VOTING_PERIOD + ACTIVATION_DELAY);
+        assert_eq!(state.gov_params.min_fee, 1_000);
+        assert_eq!(state.proposals[&1].status, ProposalStatus::Executed);
+    }
 
-        self.chain.push(new_block.clone());
-        if let Some(ref db) = self.db {
-            let _ = db.save_block(&new_block);
-        }
-        
-        self.pending_transactions.clear();
+    #[test]
+    fn proposals_without_quorum_are_rejected() {
+        let mut state = staked_state(&[(ALICE, MIN_PROPOSER_STAKE), (BOB, 3 * MIN_PROPOSER_STAKE)]);
+        enter_block(&mut state, 1);
+        assert!(state.apply_transaction(&propose(ALICE, ProposalKind::ParamChange { param: "staking_inflation".to_string(), value: 0 }, 1)));
+        assert!(state.apply_transaction(&Transaction::new_vote(ALICE.to_string(), 1, "VOTE_YES", 2))); // 25% turnout
+
+        // Bob unbonds before the tally: the denominator stays at the snapshot
+        assert!(state.apply_transaction(&Transaction::new_unstake(BOB.to_string(), 3 * MIN_PROPOSER_STAKE, 1)));
+        enter_block(&mut state, 1 + VOTING_PERIOD);
+        let proposal = &state.proposals[&1];
+        assert_eq!(proposal.status, ProposalStatus::Rejected);
+        assert_eq!(proposal.tally.as_ref().unwrap().total_staked, 4 * MIN_PROPOSER_STAKE);
+        enter_block(&mut state, 1 + VOTING_PERIOD + ACTIVATION_DELAY);
+        assert_eq!(state.gov_params.staking_inflation, GovParams::default().staking_inflation);
     }
-    
-    pub fn get_mining_candidate(&self, miner_address: String) -> Block {
-        let _height = self.chain.len() as u64;
-        let mut reward = self.calculate_reward(_height);
-        
-        let mut txs = self.pending_transactions.clone();
 
-        // Limit transactions to prevent oversized blocks (Reserve 200 slots for System/Stake txs)
-        let max_txs = 1800;
-        if txs.len() > max_txs {
-            txs.truncate(max_txs);
-        }
+    #[test]
+    fn unstaked_vlt_is_released_to_the_receiver_after_the_unbonding_period() {
+        let (_, staker) = key(13);
+        let mut state = staked_state(&[(staker.as_str(), 1_000)]);
+        enter_block(&mut state, 10);
+        let mut to_bob = Transaction::new_unstake(staker.clone(), 600, 1);
+        to_bob.receiver = BOB.to_string();
+        assert!(state.apply_transaction(&to_bob));
+        assert_eq!(state.stakes[&staker], 400);
+        assert_eq!(state.unbonding[&staker][0].release_height, 10 + UNBONDING_PERIOD);
+        assert_eq!(state.get_unbonding_total(BOB), 0); // Queued under the staker
 
-        // Fee Split Logic
-        let dev_wallet = "024dea39ce2e873d5be2d8e092044a7dbd9cfa2dadcba5d32e9b141b7361422d56";
-        let mut total_fees: u64 = 0;
-        for tx in &txs { total_fees += tx.fee; }
+        enter_block(&mut state, 20);
+        assert!(state.apply_transaction(&Transaction::new_unstake(staker.clone(), 400, 2)));
+        assert!(state.apply_transaction(&Transaction::new_unstake(staker.clone(), 1, 3))); // Nothing left: no-op
+        assert_eq!(state.get_unbonding_total(&staker), 1_000);
 
-        if total_fees > 0 {
-             let dev_share = total_fees * 20 / 100; 
-             let miner_share = total_fees - dev_share;
-             reward += miner_share;
-             
-             if dev_share > 0 {
-                 let dev_tx = Transaction::new(String::from("SYSTEM"), dev_wallet.to_string(), dev_share, "VLT".to_string(), 0);
-                 txs.push(dev_tx);
-             }
-        }
+        enter_block(&mut state, 10 + UNBONDING_PERIOD - 1);
+        assert_eq!(state.get_balance(BOB, "VLT"), 0);
+        enter_block(&mut state, 10 + UNBONDING_PERIOD);
+        assert_eq!(state.get_balance(BOB, "VLT"), 600);
+        assert_eq!(state.get_balance(&staker, "VLT"), 0);
+        assert_eq!(state.get_unbonding_total(&staker), 400);
 
-        let my_stake = *self.state.stakes.get(&miner_address).unwrap_or(&0);
-        let reward_tx = Transaction::new(String::from("SYSTEM"), miner_address.clone(), reward, "VLT".to_string(), 0);
-        txs.insert(0, reward_tx);
+        // Entries due in a skipped block are released by the next one
+        enter_block(&mut state, 20 + UNBONDING_PERIOD + 5);
+        assert_eq!(state.get_balance(&staker, "VLT"), 400);
+        assert!(!state.unbonding.contains_key(&staker));
+    }
 
-        if !self.state.stakes.is_empty() {
-             let total_staked: u64 = self.state.stakes.values().sum();
-             if total_staked > 0 {
-                 for (staker, amount) in &self.state.stakes {
-                     let staking_inflation = 10;
-                     if let Some(total_reward) = amount.checked_mul(staking_inflation) {
-                         if let Some(share) = total_reward.checked_div(total_staked) {
-                             if share > 0 {
-                                  let stake_tx = Transaction::new(String::from("SYSTEM"), staker.clone(), share, "VLT".to_string(), 0);
-                                  txs.push(stake_tx);
-                             }
-                         }
-                     }
-                 }
-             }
+    #[test]
+    fn minted_supply_follows_the_emission_schedule() {
+        let mut bc = test_chain();
+        bc.params.emission.halving_interval = 3;
+        for _ in 0..7 {
+            assert!(bc.submit_block(claim_block(&bc, ALICE, 0, 0, None, vec![])));
         }
+        assert_eq!(bc.calculate_reward(7), bc.params.emission.initial_reward / 4);
 
-        let previous_block = self.chain.last().unwrap();
-        let difficulty = self.get_next_difficulty();
+        let premine = bc.params.emission.premine;
+        for height in [0, 2, 3, 7] {
+            let report = bc.supply_at(height);
+            assert_eq!(report.subsidy, bc.params.emission.subsidy_until(height));
+            assert_eq!(report.minted, premine + report.subsidy, "height {}", height);
+        }
+        assert_eq!(bc.state.get_balance(ALICE, "VLT"), bc.supply_at(7).subsidy);
+        assert!(bc.audit_supply().ok);
+
+        // Coinbase above reward + fees + staking inflation
+        let mut greedy = claim_block(&bc, ALICE, 0, 0, None, vec![]);
+        greedy.transactions[0].amount += bc.state.gov_params.staking_inflation + 1;
+        greedy.merkle_root = Block::calculate_merkle_root(&greedy.transactions);
+        greedy.hash = greedy.calculate_hash();
+        assert!(!bc.submit_block(greedy));
+    }
+
+    #[test]
+    fn burns_leave_the_tracked_supply() {
+        let mut bc = test_chain();
+        let (alice_key, alice) = key(1);
+        assert!(bc.state.apply_transaction(&issue(&alice, "ASH", 1_000, "", 1)));
+        let signed = |mut tx: Transaction| { tx.sign(&alice_key); tx };
+
+        // Pending transfers and burns spend from the same balance
+        assert!(bc.create_transaction(signed(Transaction::new(alice.clone(), BOB.to_string(), 600, "ASH".to_string(), 2))));
+        assert!(!bc.create_transaction(signed(Transaction::new_burn(alice.clone(), "ASH".to_string(), 500, 3))));
+        assert!(bc.create_transaction(signed(Transaction::new_burn(alice.clone(), "ASH".to_string(), 400, 3))));
+        assert!(!bc.create_transaction(signed(Transaction::new_burn(alice.clone(), "VLT".to_string(), 1, 4))));
+        assert!(mine_template(&mut bc, BOB, vec![]));
+
+        let ash = supply(&bc.state, "ASH");
+        assert_eq!((ash.issued, ash.burned, ash.circulating()), (1_000, 400, 600));
+        assert_eq!(bc.state.holder_count("ASH"), 1);
+
+        // VLT burns reaching the state transition shrink the total supply
+        let before = bc.supply_at(u64::MAX);
+        bc.state.set_balance(&alice, "VLT", 50);
+        assert!(bc.state.apply_transaction(&Transaction::new_burn(alice.clone(), "VLT".to_string(), 30, 4)));
+        let after = bc.supply_at(u64::MAX);
+        assert_eq!(after.burned, before.burned + 30);
+        assert_eq!(after.total, before.total - 30);
+    }
 
-        // my_stake already captured above
-        Block::new(
-            previous_block.index + 1,
-            previous_block.hash.clone(),
-            txs,
-            difficulty as usize,
-            my_stake
-        )
+    fn control(issuer: &str, token: &str, action: ComplianceAction, nonce: u64) -> Transaction {
+        Transaction::new_token_control(issuer.to_string(), token.to_string(), serde_json::to_string(&action).unwrap(), nonce)
     }
 
-    pub fn submit_block(&mut self, block: Block) -> bool {
-         let calculated = block.calculate_hash();
+    fn send(from: &str, to: &str, token: &str, amount: u64) -> Transaction {
+        Transaction::new(from.to_string(), to.to_string(), amount, token.to_string(), 0)
+    }
 
-         // Hybrid Consensus Validation
-         
-         // 0. DoS Protection: Block Size Limit
-         if block.transactions.len() > 2000 {
-             println!("[Security] Block Rejected: Too many transactions ({})", block.transactions.len());
-             return false;
-         }
+    #[test]
+    fn issuer_controls_gate_token_movements() {
+        let issuer = "02e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0e0";
+        let mut state = ChainState::new();
+        let controlled = r#"{"freezable":true,"pausable":true,"allowlistable":true}"#;
+        assert!(state.apply_transaction(&issue(issuer, "GOLD", 1_000, controlled, 1)));
+        assert!(state.apply_transaction(&issue(issuer, "PLAIN", 1_000, "", 2)));
+        assert!(state.apply_transaction(&send(issuer, ALICE, "GOLD", 300)));
 
-         // 1. Verify Claimed Stake
-         let miner_addr = block.transactions[0].receiver.clone(); // Coinbase receiver is the miner
-         let actual_stake = *self.state.stakes.get(&miner_addr).unwrap_or(&0);
-         if block.validator_stake > actual_stake {
-             println!("[Hybrid] Invalid Stake Claim: Claimed {}, Actual {}", block.validator_stake, actual_stake);
-             return false;
-         }
+        // Controls are opt-in and issuer-only
+        assert!(!state.apply_transaction(&control(issuer, "PLAIN", ComplianceAction::Pause, 3)));
+        assert!(!state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Clawback { address: ALICE.to_string(), amount: 1 }, 3)));
+        assert!(!state.apply_transaction(&control(ALICE, "GOLD", ComplianceAction::Freeze { address: BOB.to_string() }, 1)));
 
-         // 2. Apply Difficulty Bonus (Reduction)
-         // Rule: Every 100 VLT (10,000,000,000 units) Stake reduces required zeros by 1.
-         // Cap: Max reduction of 5 zeros (Requires 500 VLT).
-         let bonus = (block.validator_stake / 10_000_000_000) as u32; // 100 VLT = 1 Level
-         let bonus_capped = bonus.min(5); 
-         
-         // Fix: Handle Bits vs Legacy Diff
-         let mut required_diff = 4; // Default
-         
-         if block.difficulty >= 0x1d00ffff {
-             // It's Bits.
-             // If >= 0x207fffff (Diff 0), we require 0 zeros.
-             if block.difficulty >= 0x207fffff {
-                 required_diff = 0;
-             } else if block.difficulty >= 0x1f00ffff {
-                 required_diff = 1;
-             } else {
-                 required_diff = 4; // Standard
-             }
-         } else {
-             // Legacy Small Int Difficulty
-             required_diff = block.difficulty.saturating_sub(bonus_capped);
-             if required_diff < 1 { required_diff = 1; }
-         }
+        // Freeze: neither side of a movement may be frozen
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Freeze { address: ALICE.to_string() }, 3)));
+        assert!(!state.apply_transaction(&send(ALICE, BOB, "GOLD", 10)));
+        assert!(!state.apply_transaction(&send(issuer, ALICE, "GOLD", 10)));
+        assert!(!state.apply_transaction(&Transaction::new_burn(ALICE.to_string(), "GOLD".to_string(), 10, 1)));
+        assert!(state.apply_transaction(&send(issuer, BOB, "GOLD", 10)));
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Unfreeze { address: ALICE.to_string() }, 4)));
+        assert!(state.apply_transaction(&send(ALICE, BOB, "GOLD", 10)));
 
-         let target_prefix = "0".repeat(required_diff as usize);
-         
-         
-         // 2.5 Verify Merkle Root Integrity (Anti-Corruption)
-         let calculated_merkle = Block::calculate_merkle_root(&block.transactions);
-         if block.merkle_root != calculated_merkle {
-             println!("[Security] Block Rejected: Merkle Root Mismatch. Header: {}, Body: {}", block.merkle_root, calculated_merkle);
-             return false;
-         }
+        // Pause: everything stops, other tokens unaffected
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Pause, 5)));
+        assert!(!state.apply_transaction(&send(issuer, BOB, "GOLD", 1)));
+        assert!(state.apply_transaction(&send(issuer, BOB, "PLAIN", 1)));
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Unpause, 6)));
 
-         if block.hash != calculated || !block.hash.starts_with(&target_prefix) {
-             println!("[Hybrid] PoW Failed. Required Prefix Length: {} (Target: {})", required_diff, target_prefix);
-             return false;
-         }
-         
-         // 3. Verify Transactions (Signatures & Fees)
-         let mut total_fees = 0;
-         let mut seen_txs = std::collections::HashSet::new();
-         let mut total_system_mint = 0;
+        // Allowlist mode: only listed holders (and the issuer)
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::SetAllowlistMode { enabled: true }, 7)));
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Allow { address: ALICE.to_string() }, 8)));
+        assert!(state.apply_transaction(&send(issuer, ALICE, "GOLD", 5)));
+        assert!(!state.apply_transaction(&send(ALICE, BOB, "GOLD", 5)));
+        assert!(!state.apply_transaction(&send(BOB, ALICE, "GOLD", 5)));
 
-         for (i, tx) in block.transactions.iter().enumerate() {
-             // Track System Minting (Coinbase + Staking + Dev)
-             if tx.sender == "SYSTEM" {
-                 total_system_mint += tx.amount;
-             }
+        // Renouncing a control lifts what it enforced, and for good
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Renounce { control: TokenControl::Allowlist }, 9)));
+        assert!(state.apply_transaction(&send(ALICE, BOB, "GOLD", 5)));
+        assert!(!state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::SetAllowlistMode { enabled: true }, 10)));
+        assert!(state.token_compliance.contains_key("GOLD"));
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Renounce { control: TokenControl::Freeze }, 10)));
+        assert!(state.apply_transaction(&control(issuer, "GOLD", ComplianceAction::Renounce { control: TokenControl::Pause }, 11)));
+        assert!(!state.token_compliance.contains_key("GOLD"));
+    }
 
-             if i == 0 { continue; } // Skip Coinbase for Sig Check
-             
-             // FIX: Prevent Duplicate Txs in same block
-             let tx_hash = tx.get_hash();
-             if seen_txs.contains(&tx_hash) {
-                 println!("[Security] Rejected Block: Duplicate Transaction Detected");
-                 return false;
-             }
-             seen_txs.insert(tx_hash);
+    #[test]
+    fn clawback_returns_tokens_to_the_issuer() {
+        let issuer = "02e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1";
+        let mut state = ChainState::new();
+        assert!(state.apply_transaction(&issue(issuer, "BOND", 500, r#"{"clawbackable":true}"#, 1)));
+        assert!(state.apply_transaction(&send(issuer, BOB, "BOND", 200)));
 
-             total_fees += tx.fee;
-             
-             // Critical: Verify Signature
-             if !tx.verify() {
-                 println!("[Security] Invalid Signature in Tx: {:?}", hex::encode(tx.get_hash()));
-                 return false;
-             }
-         }
+        let claw = |amount: u64, nonce: u64| control(issuer, "BOND", ComplianceAction::Clawback { address: BOB.to_string(), amount }, nonce);
+        assert!(!state.apply_transaction(&claw(201, 2)));
+        assert!(!state.apply_transaction(&claw(0, 2)));
+        assert!(state.apply_transaction(&claw(150, 2)));
+        assert_eq!((state.get_balance(BOB, "BOND"), state.get_balance(issuer, "BOND")), (50, 450));
+        assert_eq!(supply(&state, "BOND").circulating(), 500);
 
-         // 4. Verify Total Emission (Inflation Protection)
-         let expected_base_reward = self.calculate_reward(block.index);
-         let staking_inflation = 10; // Must match mining logic
-         let max_allowed = expected_base_reward + total_fees + staking_inflation;
-         
-         if total_system_mint > max_allowed {
-              println!("[Security] Inflation Detected! Total Minted: {}, Max Allowed: {}", total_system_mint, max_allowed);
-              return false;
-         }
+        // Transfers stay open: clawback alone never restricts movements
+        assert!(state.apply_transaction(&send(BOB, ALICE, "BOND", 50)));
+    }
 
-         let last = self.chain.last().unwrap();
-         if block.previous_hash != last.hash || block.index != last.index + 1 {
-              println!("[Security] Invalid Previous Hash or Index");
-              return false;
-         }
+    #[test]
+    fn listed_pairs_trade_between_issued_tokens() {
+        let mut state = ChainState::new();
+        assert!(state.apply_transaction(&issue(ALICE, "GOLD", 1_000, "", 1)));
+        assert!(state.apply_transaction(&issue(BOB, "USD", 100_000, "", 1)));
+        let in_usd = OrderParams { quote: "USD".to_string(), ..OrderParams::default() };
+        let sell = Transaction::new_pair_order(ALICE.to_string(), "GOLD".to_string(), &in_usd, "SELL", 100, 50, 2);
+        assert!(state.order_lock(&sell).is_err()); // Not listed yet
+        assert!(state.get_pair("GOLD", "VLT").is_some()); // Implicit TOKEN/VLT market
 
-         // 5. Verify Timestamp (Time Warp Protection)
-         let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
-         if block.timestamp < last.timestamp {
-             println!("[Security] Timestamp Invalid: Time Reversal. Last: {}, New: {}", last.timestamp, block.timestamp);
-             return false;
-         }
-         if block.timestamp > now + 7200 { // 2 Hours Drift
-             println!("[Security] Timestamp Invalid: Too far in future");
-             return false;
-         }
+        let gold_usd = TradingPair { tick_size: 5, lot_size: 10, ..TradingPair::new("GOLD", "USD") };
+        assert!(!state.apply_transaction(&Transaction::new_list_pair(BOB.to_string(), &TradingPair::new("GOLD", "EUR"), 2)));
+        assert!(state.apply_transaction(&Transaction::new_list_pair(BOB.to_string(), &gold_usd, 2)));
+        assert!(!state.apply_transaction(&Transaction::new_list_pair(ALICE.to_string(), &gold_usd, 3)));
+        assert_eq!(state.pairs["GOLD/USD"].lister, BOB);
 
-         // 6. Transaction Replay Protection
-         for tx in &block.transactions {
-             if tx.sender == "SYSTEM" { continue; }
-             let stored_nonce = *self.state.nonces.get(&tx.sender).unwrap_or(&0);
-             if tx.nonce <= stored_nonce {
-                 println!("[Security] Replay Attack Detected: Tx Nonce {} <= Stored {}", tx.nonce, stored_nonce);
-                 return false;
-             }
-         }
-         
-         for tx in &block.transactions {
-             if !self.state.apply_transaction(tx) {
-                 println!("[Consensus] Error: Transaction Application Failed during block submission");
-                 // State is already mutated partially. We should ideally revert.
-                 // For now, return false. P2P will disconnect us or we will re-sync.
-                 return false;
-             }
-         }
+        // Off the tick grid or lot size: rejected before anything is locked
+        let off_tick = Transaction::new_pair_order(ALICE.to_string(), "GOLD".to_string(), &in_usd, "SELL", 100, 52, 3);
+        let off_lot = Transaction::new_pair_order(ALICE.to_string(), "GOLD".to_string(), &in_usd, "SELL", 105, 50, 3);
+        assert!(state.order_lock(&off_tick).is_err());
+        assert!(state.order_lock(&off_lot).is_err());
 
-         self.chain.push(block.clone());
-         if let Some(ref db) = self.db {
-             let _ = db.save_block(&block);
-         }
-         
-         // Fix: Remove confirmed transactions from pending pool to prevent replay/stuck
-         let confirmed: HashSet<Vec<u8>> = block.transactions.iter().map(|tx| tx.get_hash()).collect();
-         self.pending_transactions.retain(|tx| !confirmed.contains(&tx.get_hash()));
+        assert!(state.apply_transaction(&sell));
+        let buy = Transaction::new_pair_order(BOB.to_string(), "GOLD".to_string(), &in_usd, "BUY", 60, 55, 2);
+        let lock = state.order_lock(&buy).unwrap();
+        assert_eq!((lock.asset.as_str(), lock.amount), ("USD", 60 * 55));
+        assert!(state.apply_transaction(&buy));
 
-         true
+        // Filled at the maker's price, the improvement refunded in USD
+        assert_eq!(state.get_balance(BOB, "GOLD"), 60);
+        assert_eq!(state.get_balance(BOB, "USD"), 100_000 - 60 * 50);
+        assert_eq!(state.get_balance(ALICE, "USD"), 60 * 50);
+        assert_eq!(state.get_balance(ALICE, "GOLD"), 900);
+        assert_eq!(state.order_book("GOLD/USD", 5).asks[0].amount, 40);
     }
 
+    // Asks of ALICE: 100 TKN at 10 and 100 TKN at 12. BOB holds 10,000 VLT.
+    fn ask_ladder() -> ChainState {
+        let mut state = ChainState::new();
+        assert!(state.apply_transaction(&issue(ALICE, "TKN", 1_000, "", 1)));
+        assert!(state.apply_transaction(&Transaction::new_order(ALICE.to_string(), "TKN".to_string(), "SELL", 100, 10, 2)));
+        assert!(state.apply_transaction(&Transaction::new_order(ALICE.to_string(), "TKN".to_string(), "SELL", 100, 12, 3)));
+        state.set_balance(BOB, "VLT", 10_000);
+        state
+    }
 
-    fn get_next_difficulty(&self) -> u32 {
-        let last_block = self.chain.last().unwrap();
-        
-        // Retarget every 10 blocks (Fast adj for testing)
-        let retarget_interval = 10;
-        let target_seconds_per_block = 60; // 1 Minute
-        let target_timespan = retarget_interval * target_seconds_per_block;
+    fn bob_buys(state: &mut ChainState, order_type: OrderType, amount: u64, price: u64) -> (u64, u64) {
+        let params = OrderParams { order_type, ..OrderParams::default() };
+        assert!(state.apply_transaction(&Transaction::new_pair_order(BOB.to_string(), "TKN".to_string(), &params, "BUY", amount, price, 1)));
+        (state.get_balance(BOB, "TKN"), 10_000 - state.get_balance(BOB, "VLT"))
+    }
 
-        if (last_block.index + 1) % retarget_interval != 0 {
-            return last_block.difficulty as u32;
-        }
+    fn bob_resting(state: &ChainState) -> usize {
+        state.orders.values().filter(|o| o.creator == BOB).count()
+    }
 
-        // Find the first block of this epoch
-        // Be careful with index underflow
-        let first_block_index = if last_block.index >= retarget_interval {
-            last_block.index - retarget_interval + 1
-        } else {
-            0
-        };
-        
-        let first_block = &self.chain[first_block_index as usize];
-        
-        // Calculate actual timespan
-        let actual_timespan = last_block.timestamp.saturating_sub(first_block.timestamp);
-        
-        // Dampening (Max 4x, Min 1/4x)
-        let actual_timespan = if actual_timespan < target_timespan / 4 {
-             target_timespan / 4
-        } else if actual_timespan > target_timespan * 4 {
-             target_timespan * 4
-        } else {
-             actual_timespan
-        };
+    #[test]
+    fn order_types_decide_what_rests_and_what_is_refunded() {
+        // Post-only: killed when it would take liquidity, rests otherwise
+        let mut state = ask_ladder();
+        assert_eq!(bob_buys(&mut state, OrderType::PostOnly, 50, 10), (0, 0));
+        assert_eq!(bob_resting(&state), 0);
+        assert_eq!(bob_buys(&mut state, OrderType::PostOnly, 50, 9), (0, 450));
+        assert_eq!(bob_resting(&state), 1);
 
-        // 3. Fallback for new chains
-        if self.chain.len() < retarget_interval as usize {
-            return 0x1f00ffff; // Genesis difficulty
-        }
-        
-        // Calculate new target
-        // Target = OldTarget * ActualTime / TargetTime
-        // We need to convert Bits -> Target (u256) -> Op -> Bits
-        // Since we don't have U256 lib easily exposed here, we do a simplified float math
-        // or just manipulation of the exponent.
-        
-        // Simplified Logic for "Bits":
-        // Bits = (Exponent << 24) | Mantissa
-        // Val = Mantissa * 256^(Exponent - 3)
-        // NewVal = OldVal * (Actual / Target)
-        
-        // Let's use f64 for approximation (Standard in many altcoins)
-        let last_diff_bits = last_block.difficulty as u32;
-        let exponent = (last_diff_bits >> 24) & 0xff;
-        let mantissa = last_diff_bits & 0x00ffffff;
-        
-        let mut value = mantissa as f64 * 256f64.powf((exponent as i32 - 3) as f64);
-        
-        value = value * (actual_timespan as f64 / target_timespan as f64);
-        
-        // Convert back to Bits
-        // Find rough exponent
-        if value <= 0.0 { return 0x207fffff; }
-        
-        let mut new_exponent = 0;
-        let mut tmp = value;
-        while tmp >= 256.0 * 256.0 * 256.0 { // Keep mantissa in 3 bytes (approx) 
-             // Ideally we find log256
-             tmp /= 256.0;
-             new_exponent += 1;
-        }
-        // Base is 3 bytes (0x00ffffff)
-        new_exponent += 3;
-        
-        // Re-normalize mantissa
-        let new_mantissa = (value / 256f64.powf((new_exponent - 3) as f64)) as u32;
-        
-        // Cap at Min Difficulty
-        // 0x20 is max exponent (32 bytes)
-        if new_exponent > 0x20 { return 0x207fffff; }
-        if new_exponent == 0x20 && new_mantissa > 0x7fffff { return 0x207fffff; }
-        
-        // Assemble
-        let new_bits = ((new_exponent as u32) << 24) | (new_mantissa & 0x00ffffff);
-        println!("[Retarget] Block {}: Timespan {}s (Target {}s) -> Diff Adjusted to {:x}", last_block.index + 1, actual_timespan, target_timespan, new_bits);
-        
-        new_bits
+        // Fill-or-kill: all or nothing
+        let mut state = ask_ladder();
+        assert_eq!(bob_buys(&mut state, OrderType::FillOrKill, 250, 12), (0, 0));
+        assert_eq!(bob_buys(&mut state, OrderType::FillOrKill, 150, 12), (150, 100 * 10 + 50 * 12));
+        assert_eq!(bob_resting(&state), 0);
+
+        // Immediate-or-cancel: fills up to its limit, the rest is refunded
+        let mut state = ask_ladder();
+        assert_eq!(bob_buys(&mut state, OrderType::ImmediateOrCancel, 150, 11), (100, 1_000));
+        assert_eq!(bob_resting(&state), 0);
+
+        // Limit: the unfilled rest stays on the book
+        let mut state = ask_ladder();
+        assert_eq!(bob_buys(&mut state, OrderType::Limit, 150, 11), (100, 1_000 + 50 * 11));
+        assert_eq!(state.order_book("TKN/VLT", 5).bids[0].amount, 50);
     }
 
-    pub fn calculate_reward(&self, height: u64) -> u64 {
-        let halving_interval = 105_000; // Accelerated Schedule (Results in ~21M Total Supply still? No, actually 10.5M if base is same... Wait, User said "Approx 4 Years" for 210k. 105k is 2 years. Supply curve depends on this. I will just change the number as requested.)
-        let initial_reward = 50 * 100_000_000; // 50 VLT in Atomic Units
-        let halvings = height / halving_interval;
-        if halvings >= 64 { return 0; }
-        initial_reward >> halvings
+    #[test]
+    fn market_orders_stop_at_the_slippage_limit() {
+        let market = |reference_price: u64, max_slippage_bps: u64| OrderParams {
+            order_type: OrderType::Market,
+            reference_price: Some(reference_price),
+            max_slippage_bps,
+            ..OrderParams::default()
+        };
+        let mut state = ask_ladder();
+        let wide = Transaction::new_pair_order(BOB.to_string(), "TKN".to_string(), &market(10, 2_000), "BUY", 150, 0, 1);
+        assert!(state.apply_transaction(&wide));
+        assert_eq!(state.get_balance(BOB, "VLT"), 10_000 - 100 * 10 - 50 * 12);
+
+        // 10% over the reference only reaches the first level, the rest never rests
+        let mut state = ask_ladder();
+        let narrow = Transaction::new_pair_order(BOB.to_string(), "TKN".to_string(), &market(10, 1_000), "BUY", 150, 0, 1);
+        assert!(state.apply_transaction(&narrow));
+        assert_eq!(state.get_balance(BOB, "TKN"), 100);
+        assert_eq!(state.get_balance(BOB, "VLT"), 10_000 - 1_000);
+        assert_eq!(bob_resting(&state), 0);
     }
-    
-    pub fn is_chain_valid(&self) -> bool {
-        for i in 1..self.chain.len() {
-            let current = &self.chain[i];
-            let previous = &self.chain[i - 1];
-            if current.hash != current.calculate_hash() { return false; }
-            if current.previous_hash != previous.hash { return false; }
-        }
-        true
+
+    #[test]
+    fn expired_orders_are_refunded_at_their_expiry_height() {
+        let mut state = ask_ladder();
+        let expiring = OrderParams { expiry_height: Some(30), ..OrderParams::default() };
+        assert!(state.apply_transaction(&Transaction::new_pair_order(BOB.to_string(), "TKN".to_string(), &expiring, "BUY", 10, 8, 1)));
+        assert_eq!(state.get_balance(BOB, "VLT"), 10_000 - 80);
+
+        enter_block(&mut state, 29);
+        assert_eq!(bob_resting(&state), 1);
+        enter_block(&mut state, 30);
+        assert_eq!(bob_resting(&state), 0);
+        assert_eq!(state.get_balance(BOB, "VLT"), 10_000);
+
+        // Already expired when included: killed without locking anything
+        assert!(state.apply_transaction(&Transaction::new_pair_order(BOB.to_string(), "TKN".to_string(), &expiring, "BUY", 10, 8, 2)));
+        assert_eq!((bob_resting(&state), state.get_balance(BOB, "VLT")), (0, 10_000));
     }
 
-    pub fn save(&self) {
-        if let Some(ref db) = self.db {
-            let _ = db.save_pending_txs(&self.pending_transactions);
-            // flushed by db call
-        }
+    #[test]
+    fn order_ids_are_tx_hashes_and_ties_fill_in_sequence() {
+        let carol = "02c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0";
+        let mut first = Transaction::new_order(BOB.to_string(), "TKN".to_string(), "SELL", 30, 10, 1);
+        let mut second = Transaction::new_order(carol.to_string(), "TKN".to_string(), "SELL", 30, 10, 1);
+        second.timestamp = 1_000; // Older timestamp, later in the block: sequence decides
+        first.timestamp = 2_000;
+        let taker = Transaction::new_order(ALICE.to_string(), "TKN".to_string(), "BUY", 40, 10, 2);
+        let blocks = vec![
+            Block::new(0, "0".to_string(), vec![
+                issue(ALICE, "TKN", 100, "", 1),
+                Transaction::new(ALICE.to_string(), BOB.to_string(), 30, "TKN".to_string(), 0),
+                Transaction::new(ALICE.to_string(), carol.to_string(), 30, "TKN".to_string(), 0),
+                Transaction::new("SYSTEM".to_string(), ALICE.to_string(), 1_000, "VLT".to_string(), 0),
+            ], 0x207fffff, 0),
+            Block::new(1, "genesis".to_string(), vec![first.clone(), second.clone(), taker], 0x207fffff, 0),
+        ];
+
+        let state = Blockchain::replay_state(&blocks, None);
+        let first_id = hex::encode(first.get_hash());
+        let second_id = hex::encode(second.get_hash());
+        assert!(!state.orders.contains_key(&first_id)); // Filled first
+        assert_eq!(state.orders[&second_id].amount, 20);
+        assert_eq!(state.orders[&second_id].sequence, 2);
+        assert_eq!(state.next_order_seq, 4); // The taker took one too
+        assert_eq!(state.get_balance(BOB, "VLT"), 300);
+        assert_eq!(state.get_balance(carol, "VLT"), 100);
+
+        // Same IDs and priorities on every replay
+        let again = Blockchain::replay_state(&blocks, None);
+        assert_eq!(again.orders[&second_id].sequence, state.orders[&second_id].sequence);
+        assert_eq!(again.block_trades.iter().map(|t| &t.maker_order).collect::<Vec<_>>(), vec![&first_id, &second_id]);
+
+        // Cancelled by ID, only by its creator
+        let mut state = state;
+        assert!(!state.apply_transaction(&Transaction::new_cancel(BOB.to_string(), second_id.clone(), 2)));
+        assert!(state.apply_transaction(&Transaction::new_cancel(carol.to_string(), second_id.clone(), 2)));
+        assert_eq!(state.get_balance(carol, "TKN"), 20);
     }
 
-    pub fn load() -> Self {
-        Blockchain::new()
+    #[test]
+    fn fills_are_recorded_per_block() {
+        let mut state = ask_ladder();
+        state.gov_params.dex_maker_fee_bps = 100;
+        state.gov_params.dex_taker_fee_bps = 200;
+        enter_block(&mut state, 12);
+        let sweep = Transaction::new_order(BOB.to_string(), "TKN".to_string(), "BUY", 150, 12, 1);
+        assert!(state.apply_transaction(&sweep));
+
+        let trades = &state.block_trades;
+        let hash = hex::encode(sweep.get_hash());
+        assert_eq!(trades.iter().map(|t| t.id.clone()).collect::<Vec<_>>(), vec![format!("{}:0", hash), format!("{}:1", hash)]);
+        let (first, second) = (&trades[0], &trades[1]);
+        assert_eq!((first.price, first.amount, first.quote_amount), (10, 100, 1_000));
+        assert_eq!((second.price, second.amount, second.quote_amount), (12, 50, 600));
+        assert_eq!((first.maker.as_str(), first.taker.as_str(), first.taker_side.as_str()), (ALICE, BOB, "BUY"));
+        assert_eq!(first.taker_order, hash);
+        assert_eq!((first.maker_fee, first.maker_fee_asset.as_str()), (10, "VLT"));
+        assert_eq!((first.taker_fee, first.taker_fee_asset.as_str()), (2, "TKN"));
+        assert_eq!((first.block_height, first.tx_hash.clone()), (12, hash));
+
+        // Records are per block
+        enter_block(&mut state, 13);
+        assert!(state.block_trades.is_empty());
     }
 
-    pub fn attempt_chain_replacement(&mut self, candidate: Vec<Block>) -> bool {
-         if candidate.len() <= self.chain.len() { return false; }
-         
-         println!("[Consensus] Validating remote chain candidate (Height: {})...", candidate.len());
-         
-         // 1. Genesis Check
-         if candidate.is_empty() || candidate[0].hash != self.chain[0].hash {
-             println!("[Consensus] Rejecting: Incompatible Genesis.");
-             return false;
-         }
+    #[test]
+    fn order_book_aggregates_price_levels() {
+        let mut state = ask_ladder();
+        let order = |state: &mut ChainState, who: &str, side: &str, amount: u64, price: u64, nonce: u64| {
+            assert!(state.apply_transaction(&Transaction::new_order(who.to_string(), "TKN".to_string(), side, amount, price, nonce)));
+        };
+        order(&mut state, ALICE, "SELL", 30, 12, 4);
+        order(&mut state, ALICE, "SELL", 40, 15, 5);
+        order(&mut state, BOB, "BUY", 20, 8, 1);
+        order(&mut state, BOB, "BUY", 25, 8, 2);
+        order(&mut state, BOB, "BUY", 10, 7, 3);
 
-         // 2. Structural & PoW Validation
-         for i in 1..candidate.len() {
-             let cur = &candidate[i];
-             let prev = &candidate[i-1];
-             
-             // Linkage
-             if cur.previous_hash != prev.hash || cur.index != prev.index + 1 { 
-                 println!("[Consensus] Rejecting: Broken Link at #{}", cur.index);
-                 return false; 
-             }
-             
-             // Hash Integrity
-             if cur.hash != cur.calculate_hash() { 
-                 println!("[Consensus] Rejecting: Invalid Hash at #{}", cur.index);
-                 return false; 
-             }
-             
-             // PoW Check (Simplified for MVP: Check formatting)
-             if !cur.hash.starts_with("0000") {
-                  // STRICT MODE:
-                  // return false;
-             }
-             
-             // Signature Check
-             for (t_i, tx) in cur.transactions.iter().enumerate() {
-                 if t_i > 0 && !tx.verify() {
-                     println!("[Consensus] Rejecting: Invalid Tx Signature at #{}", cur.index);
-                     return false;
-                 }
-             }
-         }
-         
-         println!("[Consensus] Remote chain accepted. Rebuilding state...");
-         self.chain = candidate;
-         self.rebuild_state();
-         // Save to DB
-         if let Some(ref db) = self.db {
-             let _ = db.save_chain(&self.chain);
-         }
-         true
+        let levels = |levels: &[PriceLevel]| levels.iter().map(|l| (l.price, l.amount, l.orders)).collect::<Vec<_>>();
+        let book = state.order_book("TKN/VLT", 10);
+        assert_eq!(levels(&book.asks), vec![(10, 100, 1), (12, 130, 2), (15, 40, 1)]); // Best first
+        assert_eq!(levels(&book.bids), vec![(8, 45, 2), (7, 10, 1)]);
+        assert_eq!((book.best_bid, book.best_ask, book.spread), (Some(8), Some(10), Some(2)));
+
+        // Depth counts levels, not orders
+        let top = state.order_book("TKN/VLT", 1);
+        assert_eq!((levels(&top.asks), levels(&top.bids)), (vec![(10, 100, 1)], vec![(8, 45, 2)]));
+
+        // Partial fills shrink the level; a one-sided book has no spread
+        order(&mut state, BOB, "BUY", 160, 12, 4);
+        let book = state.order_book("TKN/VLT", 10);
+        assert_eq!(levels(&book.asks), vec![(12, 70, 2), (15, 40, 1)]);
+        let empty = state.order_book("GOLD/VLT", 10);
+        assert!(empty.asks.is_empty() && empty.bids.is_empty() && empty.spread.is_none());
     }
 }
@@ -2,6 +2,7 @@
 use sled::Db;
 use crate::block::Block;
 use crate::transaction::Transaction;
+use crate::dex::{Candle, SwapRecord, Trade, CANDLE_INTERVALS};
 
 use serde_json;
 
@@ -225,6 +226,136 @@ impl Database {
         history
     }
 
+    // --- DEX Trade History ---
+    // candles: "pair:interval:time" -> Candle (zero padded time). Derived from trades, rebuilt with them.
+    // trades: TradeID -> Trade. Indices hold "pair:height:tradeid" / "addr:height:tradeid" -> TradeID
+    // (zero padded height, so a prefix scan returns oldest first)
+    fn trades(&self) -> sled::Result<sled::Tree> {
+        self.db.open_tree("trades")
+    }
+
+    fn trades_by_pair(&self) -> sled::Result<sled::Tree> {
+        self.db.open_tree("trades_by_pair")
+    }
+
+    fn trades_by_addr(&self) -> sled::Result<sled::Tree> {
+        self.db.open_tree("trades_by_addr")
+    }
+
+    fn candles(&self) -> sled::Result<sled::Tree> {
+        self.db.open_tree("candles")
+    }
+
+    fn candle_key(pair: &str, label: &str, time: u64) -> String {
+        format!("{}:{}:{:020}", pair, label, time)
+    }
+
+    // Folds one fill into every timeframe. Fills must arrive in chain order.
+    pub fn update_candles(&self, pair: &str, price: u64, volume: u64, timestamp: u64) -> sled::Result<()> {
+        let tree = self.candles()?;
+        for (label, secs) in CANDLE_INTERVALS {
+            let time = timestamp / secs * secs;
+            let key = Database::candle_key(pair, label, time);
+            let candle = match tree.get(key.as_bytes())?.and_then(|v| serde_json::from_slice::<Candle>(&v).ok()) {
+                Some(mut c) => { c.add(price, volume); c }
+                None => Candle::new(time, price, volume),
+            };
+            tree.insert(key.as_bytes(), serde_json::to_vec(&candle).unwrap())?;
+        }
+        Ok(())
+    }
+
+    // Oldest first within [start, end]; the most recent `limit` buckets when there are more
+    pub fn get_candles(&self, pair: &str, label: &str, start: u64, end: u64, limit: usize) -> Vec<Candle> {
+        let Ok(tree) = self.candles() else { return Vec::new(); };
+        // Include the bucket that contains `start`
+        let secs = crate::dex::candle_interval(label).unwrap_or(1);
+        let from = Database::candle_key(pair, label, start / secs * secs);
+        let to = Database::candle_key(pair, label, end);
+        let mut candles: Vec<Candle> = tree.range(from.as_bytes()..=to.as_bytes())
+            .rev()
+            .flatten()
+            .filter_map(|(_, v)| serde_json::from_slice::<Candle>(&v).ok())
+            .take(limit)
+            .collect();
+        candles.reverse();
+        candles
+    }
+
+    pub fn save_trades(&self, trades: &[Trade]) -> sled::Result<()> {
+        if trades.is_empty() { return Ok(()); }
+        let tree = self.trades()?;
+        let by_pair = self.trades_by_pair()?;
+        let by_addr = self.trades_by_addr()?;
+        for trade in trades {
+            tree.insert(trade.id.as_bytes(), serde_json::to_vec(trade).unwrap())?;
+            by_pair.insert(format!("{}:{:020}:{}", trade.pair, trade.block_height, trade.id).as_bytes(), trade.id.as_bytes())?;
+            by_addr.insert(format!("{}:{:020}:{}", trade.maker, trade.block_height, trade.id).as_bytes(), trade.id.as_bytes())?;
+            by_addr.insert(format!("{}:{:020}:{}", trade.taker, trade.block_height, trade.id).as_bytes(), trade.id.as_bytes())?;
+            self.update_candles(&trade.pair, trade.price, trade.amount, trade.timestamp)?;
+        }
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // AMM swaps only feed the pool's candles
+    pub fn save_swaps(&self, swaps: &[SwapRecord]) -> sled::Result<()> {
+        for swap in swaps {
+            self.update_candles(&swap.pool, swap.price, swap.volume, swap.timestamp)?;
+        }
+        Ok(())
+    }
+
+    pub fn clear_trades(&self) -> sled::Result<()> {
+        self.trades()?.clear()?;
+        self.trades_by_pair()?.clear()?;
+        self.trades_by_addr()?.clear()?;
+        self.candles()?.clear()?;
+        Ok(())
+    }
+
+    // Newest first
+    fn scan_trades(&self, index: &sled::Tree, prefix: &str, limit: usize) -> Vec<Trade> {
+        let mut result = Vec::new();
+        let Ok(tree) = self.trades() else { return result; };
+        for (_, trade_id) in index.scan_prefix(format!("{}:", prefix).as_bytes()).rev().flatten() {
+            if result.len() >= limit { break; }
+            if let Ok(Some(val)) = tree.get(&trade_id) {
+                if let Ok(trade) = serde_json::from_slice::<Trade>(&val) {
+                    result.push(trade);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn get_trades_by_pair(&self, pair: &str, limit: usize) -> Vec<Trade> {
+        match self.trades_by_pair() {
+            Ok(index) => self.scan_trades(&index, pair, limit),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // Newest first, stops at the first trade older than `since`
+    pub fn get_trades_by_pair_since(&self, pair: &str, since: u64) -> Vec<Trade> {
+        let mut result = Vec::new();
+        let (Ok(index), Ok(tree)) = (self.trades_by_pair(), self.trades()) else { return result; };
+        for (_, trade_id) in index.scan_prefix(format!("{}:", pair).as_bytes()).rev().flatten() {
+            let Ok(Some(val)) = tree.get(&trade_id) else { continue; };
+            let Ok(trade) = serde_json::from_slice::<Trade>(&val) else { continue; };
+            if trade.timestamp < since { break; }
+            result.push(trade);
+        }
+        result
+    }
+
+    pub fn get_trades_by_address(&self, address: &str, limit: usize) -> Vec<Trade> {
+        match self.trades_by_addr() {
+            Ok(index) => self.scan_trades(&index, address, limit),
+            Err(_) => Vec::new(),
+        }
+    }
+
     // --- PPS Ledger Methods ---
     pub fn credit_miner(&self, miner: &str, amount: u64) -> sled::Result<u64> {
         let ledger = self.miner_ledger()?;
@@ -272,3 +403,89 @@ impl Database {
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch() -> Database {
+        Database { db: sled::Config::new().temporary(true).open().unwrap() }
+    }
+
+    fn fill(id: &str, pair: &str, maker: &str, taker: &str, price: u64, block_height: u64, timestamp: u64) -> Trade {
+        Trade {
+            id: id.to_string(),
+            pair: pair.to_string(),
+            maker_order: format!("order-{}", maker),
+            taker_order: format!("order-{}", taker),
+            maker: maker.to_string(),
+            taker: taker.to_string(),
+            taker_side: "BUY".to_string(),
+            price,
+            amount: 10,
+            quote_amount: price * 10,
+            maker_fee: 0,
+            taker_fee: 0,
+            maker_fee_asset: String::new(),
+            taker_fee_asset: String::new(),
+            block_height,
+            timestamp,
+            tx_hash: id.split(':').next().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn trades_are_indexed_by_pair_and_by_both_parties() {
+        let db = scratch();
+        db.save_trades(&[
+            fill("a:0", "TKN/VLT", "maker1", "taker1", 10, 9, 600),
+            fill("a:1", "TKN/VLT", "maker2", "taker1", 11, 9, 600),
+            fill("b:0", "GOLD/USD", "maker1", "taker2", 50, 10, 660),
+            fill("c:0", "TKN/VLT", "maker1", "taker2", 12, 11, 720),
+        ]).unwrap();
+
+        let ids = |trades: Vec<Trade>| trades.into_iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(db.get_trades_by_pair("TKN/VLT", 10)), vec!["c:0", "a:1", "a:0"]); // Newest first
+        assert_eq!(ids(db.get_trades_by_pair("TKN/VLT", 2)), vec!["c:0", "a:1"]);
+        assert_eq!(ids(db.get_trades_by_address("maker1", 10)), vec!["c:0", "b:0", "a:0"]);
+        assert_eq!(ids(db.get_trades_by_address("taker1", 10)), vec!["a:1", "a:0"]);
+        assert_eq!(ids(db.get_trades_by_pair_since("TKN/VLT", 601)), vec!["c:0"]);
+        assert!(db.get_trades_by_pair("TKN", 10).is_empty()); // Prefix of another pair only
+
+        // A replaced chain re-indexes from scratch
+        db.clear_trades().unwrap();
+        assert!(db.get_trades_by_address("maker1", 10).is_empty());
+        assert!(db.get_candles("TKN/VLT", "1m", 0, u64::MAX, 10).is_empty());
+    }
+
+    #[test]
+    fn candles_bucket_fills_per_interval() {
+        let db = scratch();
+        db.save_trades(&[
+            fill("a:0", "TKN/VLT", "maker1", "taker1", 10, 1, 60),
+            fill("a:1", "TKN/VLT", "maker1", "taker1", 14, 1, 119),
+            fill("b:0", "TKN/VLT", "maker1", "taker1", 8, 2, 120),
+            fill("c:0", "TKN/VLT", "maker1", "taker1", 9, 3, 400),
+        ]).unwrap();
+
+        let ohlcv = |c: &Candle| (c.time, c.open, c.high, c.low, c.close, c.volume);
+        let minutes = db.get_candles("TKN/VLT", "1m", 0, u64::MAX, 10);
+        assert_eq!(minutes.iter().map(ohlcv).collect::<Vec<_>>(), vec![
+            (60, 10, 14, 10, 14, 20),
+            (120, 8, 8, 8, 8, 10),
+            (360, 9, 9, 9, 9, 10),
+        ]);
+        let fives = db.get_candles("TKN/VLT", "5m", 0, u64::MAX, 10);
+        assert_eq!(fives.iter().map(ohlcv).collect::<Vec<_>>(), vec![(0, 10, 14, 8, 8, 30), (300, 9, 9, 9, 9, 10)]);
+
+        // The limit keeps the most recent buckets, and `start` includes its own bucket
+        assert_eq!(db.get_candles("TKN/VLT", "1m", 0, u64::MAX, 2).iter().map(|c| c.time).collect::<Vec<_>>(), vec![120, 360]);
+        assert_eq!(db.get_candles("TKN/VLT", "1m", 90, 300, 10).iter().map(|c| c.time).collect::<Vec<_>>(), vec![60, 120]);
+
+        // Pool swaps chart under the pool id
+        db.save_swaps(&[SwapRecord { pool: "POOL".to_string(), price: 200, volume: 5, timestamp: 3_600 }]).unwrap();
+        let hours = db.get_candles("POOL", "1h", 0, u64::MAX, 10);
+        assert_eq!(hours.iter().map(ohlcv).collect::<Vec<_>>(), vec![(3_600, 200, 200, 200, 200, 5)]);
+        assert!(db.get_candles("TKN/VLT", "1h", 3_600, u64::MAX, 10).is_empty());
+    }
+}
@@ -0,0 +1,502 @@
+use serde::{Serialize, Deserialize};
+
+// Quote asset of legacy orders (no pair in tx.data) and of implicit TOKEN/VLT pairs
+pub const DEFAULT_QUOTE: &str = "VLT";
+// Unlisted TOKEN/VLT pairs trade with these sizes
+pub const DEFAULT_TICK_SIZE: u64 = 1;
+pub const DEFAULT_LOT_SIZE: u64 = 1;
+
+// Pair ID: "BASE/QUOTE". Always built from the parts, never split
+// (LP tokens contain '/' themselves).
+pub fn pair_id(base: &str, quote: &str) -> String {
+    format!("{}/{}", base, quote)
+}
+
+/// An order book market. Prices are quote units per base unit, amounts are base units.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TradingPair {
+    pub base: String,
+    pub quote: String,
+    pub tick_size: u64, // Price must be a multiple of this
+    pub lot_size: u64,  // Amount must be a multiple of this
+    #[serde(default)]
+    pub lister: String,
+}
+
+impl TradingPair {
+    pub fn new(base: &str, quote: &str) -> Self {
+        TradingPair {
+            base: base.to_string(),
+            quote: quote.to_string(),
+            tick_size: DEFAULT_TICK_SIZE,
+            lot_size: DEFAULT_LOT_SIZE,
+            lister: String::new(),
+        }
+    }
+
+    pub fn id(&self) -> String {
+        pair_id(&self.base, &self.quote)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.base == self.quote {
+            return Err("Base and quote must differ".to_string());
+        }
+        if self.base.is_empty() || self.quote.is_empty() {
+            return Err("Missing base or quote".to_string());
+        }
+        if self.tick_size == 0 || self.lot_size == 0 {
+            return Err("Tick and lot size must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn validate_order(&self, price: u64, amount: u64, order_type: OrderType) -> Result<(), String> {
+        // A market order's price is only a reference for the slippage cap
+        if price == 0 || (order_type != OrderType::Market && !price.is_multiple_of(self.tick_size)) {
+            return Err(format!("Price must be a positive multiple of the tick size {}", self.tick_size));
+        }
+        if amount == 0 || !amount.is_multiple_of(self.lot_size) {
+            return Err(format!("Amount must be a positive multiple of the lot size {}", self.lot_size));
+        }
+        Ok(())
+    }
+
+    // Quote units exchanged for `amount` base at `price`
+    pub fn quote_value(price: u64, amount: u64) -> Option<u64> {
+        price.checked_mul(amount)
+    }
+}
+
+// Market order slippage cap (100%)
+pub const MAX_SLIPPAGE_BPS: u64 = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum OrderType {
+    #[default]
+    Limit,             // Good-till-cancelled (or until expiry)
+    Market,            // Fills within max_slippage_bps of the signed reference_price, never rests
+    ImmediateOrCancel, // Fills what it can at the limit, remainder refunded
+    FillOrKill,        // Fills completely at the limit or not at all
+    PostOnly,          // Killed if it would match on placement
+}
+
+impl OrderType {
+    pub fn rests_on_book(&self) -> bool {
+        matches!(self, OrderType::Limit | OrderType::PostOnly)
+    }
+}
+
+/// Extra PlaceOrder parameters (JSON in tx.data, empty = defaults). tx.token is the base asset.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderParams {
+    #[serde(default = "default_quote")]
+    pub quote: String,
+    #[serde(default)]
+    pub order_type: OrderType,
+    #[serde(default)]
+    pub max_slippage_bps: u64, // Market orders only
+    #[serde(default)]
+    pub reference_price: Option<u64>, // Market orders only (required, tx.price is not signed)
+    #[serde(default)]
+    pub expiry_height: Option<u64>, // Remainder cancelled and refunded once height >= expiry_height
+    #[serde(default)]
+    pub expiry_time: Option<u64>, // ... or once the block timestamp >= expiry_time (Unix Seconds)
+}
+
+fn default_quote() -> String {
+    DEFAULT_QUOTE.to_string()
+}
+
+impl Default for OrderParams {
+    fn default() -> Self {
+        OrderParams {
+            quote: default_quote(),
+            order_type: OrderType::Limit,
+            max_slippage_bps: 0,
+            reference_price: None,
+            expiry_height: None,
+            expiry_time: None,
+        }
+    }
+}
+
+impl OrderParams {
+    pub fn from_data(data: &str) -> Result<Self, String> {
+        if data.is_empty() {
+            return Ok(OrderParams::default());
+        }
+        serde_json::from_str(data).map_err(|_| "Malformed Order Parameters".to_string())
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.quote == DEFAULT_QUOTE && self.order_type == OrderType::Limit && self.reference_price.is_none()
+            && self.expiry_height.is_none() && self.expiry_time.is_none()
+    }
+
+    pub fn is_expired(&self, height: u64, time: u64) -> bool {
+        self.expiry_height.is_some_and(|h| height >= h) || self.expiry_time.is_some_and(|t| time >= t)
+    }
+
+    // Price the order is validated against: tx.price for limit orders, reference_price for market orders
+    pub fn order_price(&self, tx_price: u64) -> Result<u64, String> {
+        if self.order_type != OrderType::Market {
+            return Ok(tx_price);
+        }
+        self.reference_price.ok_or("Market order without reference_price".to_string())
+    }
+
+    // Worst price the order may trade at: the limit for limit orders,
+    // reference price -/+ slippage for market orders (`price` from order_price)
+    pub fn limit_price(&self, side: &str, price: u64) -> Result<u64, String> {
+        if self.order_type != OrderType::Market {
+            return Ok(price);
+        }
+        if self.max_slippage_bps > MAX_SLIPPAGE_BPS {
+            return Err(format!("Slippage cap above {} bps", MAX_SLIPPAGE_BPS));
+        }
+        let slippage = (price as u128 * self.max_slippage_bps as u128 / 10_000) as u64;
+        if side == "BUY" {
+            price.checked_add(slippage).ok_or("Price overflow".to_string())
+        } else {
+            Ok(price - slippage)
+        }
+    }
+}
+
+/// Funds a PlaceOrder moves into escrow until it is filled or cancelled
+#[derive(Debug, Clone)]
+pub struct OrderLock {
+    pub pair: TradingPair,
+    pub side: &'static str, // "BUY" (locks quote) or "SELL" (locks base)
+    pub params: OrderParams,
+    pub limit: u64, // Worst acceptable price
+    pub asset: String,
+    pub amount: u64,
+}
+
+// Fee in basis points of `amount` (rounded down)
+pub fn fee_for(amount: u64, bps: u64) -> u64 {
+    (amount as u128 * bps as u128 / 10_000) as u64
+}
+
+/// One fill between a resting (maker) and an incoming (taker) order. Persisted per block.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trade {
+    pub id: String, // "<tx_hash>:<fill index>"
+    pub pair: String,
+    pub maker_order: String,
+    pub taker_order: String,
+    pub maker: String,
+    pub taker: String,
+    pub taker_side: String, // "BUY" or "SELL"
+    pub price: u64,         // Maker price
+    pub amount: u64,        // Base units
+    pub quote_amount: u64,
+    pub maker_fee: u64, // In the asset the maker received
+    pub taker_fee: u64, // In the asset the taker received
+    #[serde(default)]
+    pub maker_fee_asset: String,
+    #[serde(default)]
+    pub taker_fee_asset: String,
+    pub block_height: u64,
+    pub timestamp: u64,
+    pub tx_hash: String, // PlaceOrder of the taker
+}
+
+// Candle timeframes (label, seconds), aggregated from trade records
+pub const CANDLE_INTERVALS: [(&str, u64); 6] = [
+    ("1m", 60),
+    ("5m", 300),
+    ("15m", 900),
+    ("1h", 3_600),
+    ("4h", 14_400),
+    ("1d", 86_400),
+];
+
+pub fn candle_interval(label: &str) -> Option<u64> {
+    CANDLE_INTERVALS.iter().find(|(l, _)| *l == label).map(|(_, secs)| *secs)
+}
+
+/// OHLCV bucket starting at `time` (Unix Seconds, aligned to the interval)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Candle {
+    pub time: u64,
+    pub open: u64,
+    pub high: u64,
+    pub low: u64,
+    pub close: u64,
+    pub volume: u64, // Base units
+}
+
+impl Candle {
+    pub fn new(time: u64, price: u64, volume: u64) -> Self {
+        Candle { time, open: price, high: price, low: price, close: price, volume }
+    }
+
+    pub fn add(&mut self, price: u64, volume: u64) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume = self.volume.saturating_add(volume);
+    }
+}
+
+/// Aggregated resting size at one price
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PriceLevel {
+    pub price: u64,
+    pub amount: u64, // Sum of remaining base units
+    pub orders: usize,
+}
+
+/// Level-2 view of one pair (bids best first = highest, asks best first = lowest)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderBookDepth {
+    pub pair: String,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+    pub spread: Option<u64>,
+}
+
+/// Rolling 24h statistics of one pair
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Ticker {
+    pub pair: String,
+    pub last_price: Option<u64>,
+    pub open_24h: Option<u64>,
+    pub high_24h: Option<u64>,
+    pub low_24h: Option<u64>,
+    pub volume_24h: u64,       // Base units
+    pub quote_volume_24h: u64, // Quote units
+    pub trades_24h: usize,
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+}
+
+impl Ticker {
+    // `trades` newest first, all within the window
+    pub fn from_trades(pair: &str, last_price: Option<u64>, trades: &[Trade]) -> Self {
+        Ticker {
+            pair: pair.to_string(),
+            last_price,
+            open_24h: trades.last().map(|t| t.price),
+            high_24h: trades.iter().map(|t| t.price).max(),
+            low_24h: trades.iter().map(|t| t.price).min(),
+            volume_24h: trades.iter().fold(0u64, |v, t| v.saturating_add(t.amount)),
+            quote_volume_24h: trades.iter().fold(0u64, |v, t| v.saturating_add(t.quote_amount)),
+            trades_24h: trades.len(),
+            ..Default::default()
+        }
+    }
+}
+
+// Route search depth for swap quotes (pools per route)
+pub const MAX_SWAP_HOPS: usize = 3;
+
+/// Swap / RouteSwap options (JSON in tx.data, empty = exact input without deadline)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SwapParams {
+    #[serde(default)]
+    pub path: Vec<String>, // RouteSwap only: tokens visited, first = tx.token (input), last = output
+    #[serde(default)]
+    pub exact_output: bool, // amount = exact output, bounded by max_input
+    #[serde(default)]
+    pub max_input: Option<u64>, // Required for exact output
+    #[serde(default)]
+    pub min_output: Option<u64>, // Exact input bound (legacy swaps without it fall back to tx.price)
+    #[serde(default)]
+    pub deadline_height: Option<u64>, // Not executed in blocks above this height
+    #[serde(default)]
+    pub deadline_time: Option<u64>, // ... or with a block timestamp after this (Unix Seconds)
+}
+
+impl SwapParams {
+    pub fn from_data(data: &str) -> Result<Self, String> {
+        if data.is_empty() {
+            return Ok(SwapParams::default());
+        }
+        serde_json::from_str(data).map_err(|_| "Malformed Swap Parameters".to_string())
+    }
+
+    pub fn is_expired(&self, height: u64, time: u64) -> bool {
+        self.deadline_height.is_some_and(|h| height > h) || self.deadline_time.is_some_and(|t| time > t)
+    }
+}
+
+/// AMM price tick of one swap hop (candles are persisted per pool)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SwapRecord {
+    pub pool: String,
+    pub price: u64, // Input per output * 10^8
+    pub volume: u64, // Output units
+    pub timestamp: u64,
+}
+
+/// Read-only swap quote
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RouteQuote {
+    pub path: Vec<String>,
+    pub pools: Vec<String>,
+    pub amount_in: u64,
+    pub amounts_out: Vec<u64>, // After each hop
+    pub amount_out: u64,
+    pub price_impact_bps: u64, // Loss against the spot price, pool fees excluded
+}
+
+// Fixed-point scale of AMM prices (quote per base * 10^8)
+pub const PRICE_SCALE: u128 = 100_000_000;
+// Oracle history kept per pool (one observation per block with a new timestamp)
+pub const MAX_PRICE_OBSERVATIONS: usize = 10_000;
+
+// Price accumulators outgrow JSON numbers: serialized as decimal strings
+pub mod u128_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Pool cumulative prices at one point in time (Uniswap v2 style accumulators)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PriceObservation {
+    pub time: u64,
+    #[serde(with = "u128_string")]
+    pub price_a_cumulative: u128,
+    #[serde(with = "u128_string")]
+    pub price_b_cumulative: u128,
+}
+
+/// Time-weighted average prices of one pool over [start, end]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Twap {
+    pub pool: String,
+    pub start: u64,
+    pub end: u64,
+    pub price_a: u64, // token_b per token_a * 10^8
+    pub price_b: u64, // token_a per token_b * 10^8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sized(base: &str, quote: &str, tick_size: u64, lot_size: u64) -> TradingPair {
+        TradingPair { tick_size, lot_size, ..TradingPair::new(base, quote) }
+    }
+
+    #[test]
+    fn pairs_need_distinct_assets_and_positive_sizes() {
+        assert!(sized("GOLD", "USD", 5, 10).validate().is_ok());
+        assert!(sized("GOLD", "GOLD", 1, 1).validate().is_err());
+        assert!(sized("", "USD", 1, 1).validate().is_err());
+        assert!(sized("GOLD", "USD", 0, 1).validate().is_err());
+        assert!(sized("GOLD", "USD", 1, 0).validate().is_err());
+        // LP shares contain '/' themselves
+        assert_eq!(TradingPair::new("LP-GOLD/VLT", "USD").id(), "LP-GOLD/VLT/USD");
+    }
+
+    #[test]
+    fn orders_snap_to_ticks_and_lots() {
+        let pair = sized("GOLD", "USD", 5, 10);
+        assert!(pair.validate_order(25, 30, OrderType::Limit).is_ok());
+        assert!(pair.validate_order(26, 30, OrderType::Limit).is_err());
+        assert!(pair.validate_order(25, 35, OrderType::PostOnly).is_err());
+        assert!(pair.validate_order(0, 10, OrderType::Limit).is_err());
+        assert!(pair.validate_order(25, 0, OrderType::FillOrKill).is_err());
+        // Market reference prices are off the tick grid, amounts are not
+        assert!(pair.validate_order(26, 30, OrderType::Market).is_ok());
+        assert!(pair.validate_order(26, 31, OrderType::Market).is_err());
+
+        assert_eq!(TradingPair::quote_value(25, 30), Some(750));
+        assert_eq!(TradingPair::quote_value(u64::MAX / 2 + 1, 2), None);
+    }
+
+    fn market(reference_price: Option<u64>, max_slippage_bps: u64) -> OrderParams {
+        OrderParams { order_type: OrderType::Market, reference_price, max_slippage_bps, ..OrderParams::default() }
+    }
+
+    #[test]
+    fn market_orders_trade_within_signed_slippage() {
+        let params = market(Some(1_000), 250);
+        assert_eq!(params.order_price(7), Ok(1_000)); // tx.price is not signed
+        assert_eq!(params.limit_price("BUY", 1_000), Ok(1_025));
+        assert_eq!(params.limit_price("SELL", 1_000), Ok(975));
+        assert_eq!(market(Some(1_000), MAX_SLIPPAGE_BPS).limit_price("SELL", 1_000), Ok(0));
+        assert!(market(Some(1_000), MAX_SLIPPAGE_BPS + 1).limit_price("BUY", 1_000).is_err());
+        assert!(market(Some(u64::MAX), 1).limit_price("BUY", u64::MAX).is_err());
+        assert!(market(None, 100).order_price(1_000).is_err());
+
+        // Limit orders trade at their own price
+        let limit = OrderParams { max_slippage_bps: 500, ..OrderParams::default() };
+        assert_eq!(limit.order_price(7), Ok(7));
+        assert_eq!(limit.limit_price("BUY", 7), Ok(7));
+    }
+
+    #[test]
+    fn order_params_default_to_resting_limit_orders() {
+        let params = OrderParams::from_data("").unwrap();
+        assert!(params.is_default() && params.order_type.rests_on_book());
+        assert!(OrderParams::from_data("{not json").is_err());
+        assert!(OrderType::PostOnly.rests_on_book());
+        assert!(!OrderType::ImmediateOrCancel.rests_on_book() && !OrderType::FillOrKill.rests_on_book());
+
+        let expiring: OrderParams = serde_json::from_str(r#"{"order_type":"Limit","expiry_height":10,"expiry_time":5000}"#).unwrap();
+        assert!(!expiring.is_default());
+        assert!(!expiring.is_expired(9, 4_999));
+        assert!(expiring.is_expired(10, 0));
+        assert!(expiring.is_expired(0, 5_000));
+    }
+
+    #[test]
+    fn candles_track_ohlcv() {
+        let mut candle = Candle::new(120, 50, 3);
+        candle.add(70, 2);
+        candle.add(40, 1);
+        candle.add(55, 4);
+        assert_eq!((candle.open, candle.high, candle.low, candle.close, candle.volume), (50, 70, 40, 55, 10));
+        assert_eq!(candle.time, 120);
+
+        assert_eq!(candle_interval("15m"), Some(900));
+        assert_eq!(candle_interval("1d"), Some(86_400));
+        assert_eq!(candle_interval("2h"), None);
+    }
+
+    #[test]
+    fn ticker_summarises_the_window() {
+        let trade = |price: u64, amount: u64| Trade {
+            id: String::new(),
+            pair: "TKN/VLT".to_string(),
+            maker_order: String::new(),
+            taker_order: String::new(),
+            maker: String::new(),
+            taker: String::new(),
+            taker_side: "BUY".to_string(),
+            price,
+            amount,
+            quote_amount: price * amount,
+            maker_fee: 0,
+            taker_fee: 0,
+            maker_fee_asset: String::new(),
+            taker_fee_asset: String::new(),
+            block_height: 0,
+            timestamp: 0,
+            tx_hash: String::new(),
+        };
+        // Newest first
+        let ticker = Ticker::from_trades("TKN/VLT", Some(11), &[trade(11, 5), trade(15, 2), trade(9, 10), trade(10, 1)]);
+        assert_eq!((ticker.open_24h, ticker.high_24h, ticker.low_24h, ticker.last_price), (Some(10), Some(15), Some(9), Some(11)));
+        assert_eq!((ticker.volume_24h, ticker.quote_volume_24h, ticker.trades_24h), (18, 185, 4));
+
+        // A quiet window keeps the last price but has no range
+        let quiet = Ticker::from_trades("TKN/VLT", Some(11), &[]);
+        assert_eq!((quiet.last_price, quiet.open_24h, quiet.high_24h, quiet.volume_24h), (Some(11), None, None, 0));
+    }
+}
@@ -0,0 +1,229 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+// Voting window after submission (~1 Day at 60s blocks)
+pub const VOTING_PERIOD: u64 = 1_440;
+// Passed parameter changes take effect this many blocks after the tally
+pub const ACTIVATION_DELAY: u64 = 10;
+// Minimum share of total stake that must vote (Yes + No + Abstain)
+pub const QUORUM_PERCENT: u64 = 33;
+// Share of Yes among Yes + No required to pass
+pub const PASS_THRESHOLD_PERCENT: u64 = 50;
+// Anti-Spam: proposer must hold this much stake (100 VLT)
+pub const MIN_PROPOSER_STAKE: u64 = 10_000_000_000;
+// Order book fees collect here until governance names a recipient
+pub const DEX_FEE_ACCOUNT: &str = "DEX_FEES";
+
+/// Economics that can be changed by governance (formerly hardcoded in chain.rs)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GovParams {
+    pub dev_fee_percent: u64,   // Share of block fees minted to the Dev Wallet
+    pub min_fee: u64,           // Mempool minimum fee per transaction (Atomic Units)
+    pub staking_inflation: u64, // Minted per block for Stakers & Delegators
+    // Order book fees start at 0 (fills already in the chain stay fee-free on replay), set by governance
+    #[serde(default)]
+    pub dex_maker_fee_bps: u64, // Order book fee on the maker's proceeds
+    #[serde(default)]
+    pub dex_taker_fee_bps: u64, // Order book fee on the taker's proceeds
+    #[serde(default)]
+    pub dex_fee_lp_percent: u64, // Share of each fee added to the same pair's AMM pool (LPs)
+    #[serde(default = "default_fee_recipient")]
+    pub dex_fee_recipient: String, // Receives the rest of the fees
+}
+
+fn default_fee_recipient() -> String { DEX_FEE_ACCOUNT.to_string() }
+
+impl Default for GovParams {
+    fn default() -> Self {
+        GovParams {
+            dev_fee_percent: 20,
+            min_fee: 0,
+            staking_inflation: 10,
+            dex_maker_fee_bps: 0,
+            dex_taker_fee_bps: 0,
+            dex_fee_lp_percent: 0,
+            dex_fee_recipient: default_fee_recipient(),
+        }
+    }
+}
+
+impl GovParams {
+    pub fn validate(param: &str, value: u64) -> Result<(), String> {
+        match param {
+            "dev_fee_percent" if value > 100 => Err("dev_fee_percent must be <= 100".to_string()),
+            "min_fee" if value > 100_000_000 => Err("min_fee must be <= 1 VLT".to_string()),
+            "staking_inflation" if value > 100_000_000 => Err("staking_inflation must be <= 1 VLT per block".to_string()),
+            "dex_maker_fee_bps" | "dex_taker_fee_bps" if value > 1_000 => Err(format!("{} must be <= 1000 (10%)", param)),
+            "dex_fee_lp_percent" if value > 100 => Err("dex_fee_lp_percent must be <= 100".to_string()),
+            "dev_fee_percent" | "min_fee" | "staking_inflation" | "dex_maker_fee_bps" | "dex_taker_fee_bps" | "dex_fee_lp_percent" => Ok(()),
+            _ => Err(format!("Unknown parameter '{}'", param)),
+        }
+    }
+
+    pub fn set(&mut self, param: &str, value: u64) {
+        match param {
+            "dev_fee_percent" => self.dev_fee_percent = value,
+            "min_fee" => self.min_fee = value,
+            "staking_inflation" => self.staking_inflation = value,
+            "dex_maker_fee_bps" => self.dex_maker_fee_bps = value,
+            "dex_taker_fee_bps" => self.dex_taker_fee_bps = value,
+            "dex_fee_lp_percent" => self.dex_fee_lp_percent = value,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum ProposalKind {
+    Text,
+    ParamChange { param: String, value: u64 },
+    FeeRecipient { address: String }, // New dex_fee_recipient
+}
+
+/// Payload of a Propose transaction (JSON in tx.data)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProposalContent {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: ProposalKind,
+}
+
+impl ProposalContent {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.is_empty() || self.title.len() > 128 {
+            return Err("Title must be 1-128 characters".to_string());
+        }
+        if self.description.len() > 2_000 {
+            return Err("Description too long (Max 2000)".to_string());
+        }
+        match &self.kind {
+            ProposalKind::ParamChange { param, value } => GovParams::validate(param, *value)?,
+            ProposalKind::FeeRecipient { address } => {
+                let valid = hex::decode(address).ok()
+                    .is_some_and(|bytes| k256::ecdsa::VerifyingKey::from_sec1_bytes(&bytes).is_ok());
+                if !valid {
+                    return Err("Fee recipient must be a valid address".to_string());
+                }
+            }
+            ProposalKind::Text => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum VoteOption {
+    Yes,
+    No,
+    Abstain,
+}
+
+impl VoteOption {
+    // Vote transactions carry the option in the receiver field
+    pub fn from_receiver(receiver: &str) -> Option<Self> {
+        match receiver {
+            "VOTE_YES" => Some(VoteOption::Yes),
+            "VOTE_NO" => Some(VoteOption::No),
+            "VOTE_ABSTAIN" => Some(VoteOption::Abstain),
+            _ => None,
+        }
+    }
+
+    pub fn receiver(&self) -> &'static str {
+        match self {
+            VoteOption::Yes => "VOTE_YES",
+            VoteOption::No => "VOTE_NO",
+            VoteOption::Abstain => "VOTE_ABSTAIN",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ProposalStatus {
+    Voting,
+    Passed,   // Waiting for activation_height
+    Rejected,
+    Executed,
+}
+
+/// Stake-weighted result (weights and total from the snapshot taken when the proposal was submitted)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Tally {
+    pub yes: u64,
+    pub no: u64,
+    pub abstain: u64,
+    pub total_staked: u64, // All bonded VLT (stakes + delegations) at submission
+}
+
+impl Tally {
+    pub fn quorum_reached(&self) -> bool {
+        let voted = self.yes as u128 + self.no as u128 + self.abstain as u128;
+        self.total_staked > 0 && voted * 100 >= self.total_staked as u128 * QUORUM_PERCENT as u128
+    }
+
+    pub fn passed(&self) -> bool {
+        let decisive = self.yes as u128 + self.no as u128;
+        self.quorum_reached() && decisive > 0 && self.yes as u128 * 100 > decisive * PASS_THRESHOLD_PERCENT as u128
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: String,
+    pub title: String,
+    pub description: String,
+    pub kind: ProposalKind,
+    pub submit_height: u64,
+    pub end_height: u64,        // Votes accepted while height < end_height
+    pub activation_height: u64, // Parameter change applied here if passed
+    pub status: ProposalStatus,
+    pub votes: BTreeMap<String, VoteOption>, // Voter -> Latest Vote
+    #[serde(default)]
+    pub weights: BTreeMap<String, u64>, // Voter -> Weight of the latest vote (from voting_power)
+    #[serde(default)]
+    pub voting_power: BTreeMap<String, u64>, // Snapshot at submit_height: Staker -> Own + delegated stake
+    #[serde(default)]
+    pub total_voting_power: u64,             // Sum of the snapshot (Quorum denominator)
+    pub tally: Option<Tally>,                // Final result, set at end_height
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(yes: u64, no: u64, abstain: u64, total_staked: u64) -> Tally {
+        Tally { yes, no, abstain, total_staked }
+    }
+
+    #[test]
+    fn quorum_counts_abstentions_and_threshold_does_not() {
+        assert!(!tally(32, 0, 0, 100).quorum_reached());
+        assert!(tally(33, 0, 0, 100).quorum_reached());
+        assert!(tally(1, 0, 32, 100).passed()); // Abstain counts for quorum only
+        assert!(!tally(0, 0, 100, 100).passed()); // No decisive votes
+        assert!(!tally(0, 0, 0, 0).quorum_reached());
+
+        // Strict majority of Yes + No
+        assert!(!tally(20, 20, 0, 100).passed());
+        assert!(tally(21, 20, 0, 100).passed());
+        assert!(!tally(60, 10, 0, 1_000).passed()); // Without quorum
+    }
+
+    #[test]
+    fn proposals_validate_their_changes() {
+        let content = |kind: ProposalKind| ProposalContent { title: "Fees".to_string(), description: String::new(), kind };
+        assert!(content(ProposalKind::ParamChange { param: "dex_taker_fee_bps".to_string(), value: 1_000 }).validate().is_ok());
+        assert!(content(ProposalKind::ParamChange { param: "dex_taker_fee_bps".to_string(), value: 1_001 }).validate().is_err());
+        assert!(content(ProposalKind::ParamChange { param: "block_reward".to_string(), value: 1 }).validate().is_err());
+        assert!(content(ProposalKind::FeeRecipient { address: "treasury".to_string() }).validate().is_err());
+        assert!(ProposalContent { title: String::new(), description: String::new(), kind: ProposalKind::Text }.validate().is_err());
+
+        let mut params = GovParams::default();
+        params.set("min_fee", 500);
+        params.set("unknown", 7);
+        assert_eq!(params, GovParams { min_fee: 500, ..GovParams::default() });
+    }
+}
@@ -7,6 +7,10 @@ mod api;
 mod stratum;
 mod db;
 mod script;
+mod params;
+mod governance;
+mod dex;
+mod sigcache;
 // mod gui; // Phase 37: GUI Module (Disabled)
 
 use chain::Blockchain;
@@ -49,6 +53,14 @@ fn main() {
 
     // 1. Blockchain
     let blockchain = Arc::new(Mutex::new(Blockchain::load()));
+
+    // Config: Assume-Valid Block (--assumevalid=<hash>, "0" disables)
+    if let Some(arg) = args.iter().find(|a| a.starts_with("--assumevalid=")) {
+        let hash = arg.trim_start_matches("--assumevalid=").to_string();
+        let mut chain = blockchain.lock().unwrap();
+        chain.params.assume_valid = if hash == "0" { None } else { Some(hash) };
+        log(&format!("Assume-Valid: {:?}", chain.params.assume_valid), &logs);
+    }
     
     // 2. Node
     let node = Arc::new(Node::new(blockchain.clone(), port));
@@ -96,7 +108,31 @@ fn main() {
     );
     api_server.start();
 
-    // ...
+    // 6. Finality Attester: sign open checkpoints with the node wallet (when it stakes)
+    let chain_att = blockchain.clone();
+    let wallet_att = miner_wallet.clone();
+    let node_att = node.clone();
+    let logs_att = logs.clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_secs(10));
+            let wallet = wallet_att.lock().unwrap();
+            let address = wallet.get_address();
+            let mut chain = chain_att.lock().unwrap();
+            if let Some((height, hash)) = chain.open_checkpoint_for(&address) {
+                // After the wallet's pending txs, not on top of them
+                let nonce = chain.pending_nonce(&address) + 1;
+                let mut tx = transaction::Transaction::new_attestation(address, height, hash, nonce);
+                tx.sign(&wallet.private_key);
+                if chain.create_transaction(tx.clone()) {
+                    chain.save();
+                    drop(chain);
+                    log(&format!("[Finality] Attested checkpoint #{}", height), &logs_att);
+                    node_att.broadcast_attestation(tx);
+                }
+            }
+        }
+    });
 
     // 7. Stratum Servers
     log(&format!("[Stratum] Starting Multi-Port Mining Servers (Base: {})...", stratum_base), &logs);
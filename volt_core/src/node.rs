@@ -16,6 +16,7 @@ pub enum Message {
     Chain(Vec<Block>),
     GetPeers,
     Peers(Vec<String>),
+    Attestation(Transaction), // Finality checkpoint vote (relayed to all peers once accepted)
 }
 
 pub struct Node {
@@ -56,13 +57,21 @@ impl Node {
                                 match msg {
                                     Message::NewBlock(block) => {
                                         println!("[P2P] Received Block #{}", block.index);
-                                        let mut chain = chain_inner.lock().unwrap();
-                                        // FIX: Validate block before accepting! (Prevent Chain Corruption)
-                                        if chain.submit_block(block.clone()) {
-                                            println!("[P2P] Block #{} Accepted & Verified.", block.index);
-                                            // chain.save() is called inside submit_block
+                                        // Verify signatures in parallel BEFORE taking the chain lock.
+                                        // Valid results land in the shared cache, so submit_block does not redo them.
+                                        let sig_cache = chain_inner.lock().unwrap().sig_cache.clone();
+                                        let txs: Vec<&Transaction> = block.transactions.iter().skip(1).collect();
+                                        if let Err(e) = sig_cache.verify_batch(&txs) {
+                                            println!("[Security] Rejected Block #{} from Peer: {}", block.index, e);
                                         } else {
-                                            println!("[Security] Rejected Invalid Block #{} from Peer.", block.index);
+                                            let mut chain = chain_inner.lock().unwrap();
+                                            // FIX: Validate block before accepting! (Prevent Chain Corruption)
+                                            if chain.submit_block(block.clone()) {
+                                                println!("[P2P] Block #{} Accepted & Verified.", block.index);
+                                                // chain.save() is called inside submit_block
+                                            } else {
+                                                println!("[Security] Rejected Invalid Block #{} from Peer.", block.index);
+                                            }
                                         }
                                     },
                                     Message::NewTransaction(tx) => {
@@ -70,6 +79,13 @@ impl Node {
                                         let mut chain = chain_inner.lock().unwrap();
                                         chain.create_transaction(tx);
                                     },
+                                    Message::Attestation(tx) => {
+                                        let accepted = chain_inner.lock().unwrap().create_transaction(tx.clone());
+                                        if accepted {
+                                            println!("[Finality] Attestation for #{} accepted, relaying", tx.amount);
+                                            Node::send_to_peers(&peers_inner, &Message::Attestation(tx));
+                                        }
+                                    },
                                     Message::GetChain => {
                                         println!("[P2P] Received Chain Request");
                                         let chain = chain_inner.lock().unwrap();
@@ -84,8 +100,7 @@ impl Node {
                                     },
                                     Message::Chain(remote_chain) => {
                                         println!("[P2P] Received Chain Data (Height: {})", remote_chain.len());
-                                        let mut chain = chain_inner.lock().unwrap();
-                                        if chain.attempt_chain_replacement(remote_chain) {
+                                        if Node::replace_chain(&chain_inner, remote_chain) {
                                             println!("[P2P] Chain synchronized successfully.");
                                         }
                                     },
@@ -127,6 +142,22 @@ impl Node {
         });
     }
 
+    // Verifies the candidate's signatures in parallel BEFORE taking the chain lock (valid ones land in the
+    // shared cache), then validates and applies it under the lock
+    fn replace_chain(blockchain: &Arc<Mutex<Blockchain>>, remote_chain: Vec<Block>) -> bool {
+        let (sig_cache, assume_valid, height) = {
+            let chain = blockchain.lock().unwrap();
+            (chain.sig_cache.clone(), chain.params.assume_valid.clone(), chain.chain.len())
+        };
+        if remote_chain.len() <= height { return false; }
+        let txs = Blockchain::candidate_signatures(&remote_chain, assume_valid.as_ref());
+        if let Err(e) = sig_cache.verify_batch(&txs) {
+            println!("[Consensus] Rejecting remote chain: Invalid Tx Signature: {}", e);
+            return false;
+        }
+        blockchain.lock().unwrap().attempt_chain_replacement(remote_chain)
+    }
+
     pub fn connect_to_peer(&self, peer_addr: String) {
         let mut peers = self.peers.lock().unwrap();
         peers.push(peer_addr.clone());
@@ -140,8 +171,7 @@ impl Node {
              let mut de = serde_json::Deserializer::from_reader(&stream);
              if let Ok(Message::Chain(remote_chain)) = Message::deserialize(&mut de) {
                  println!("[Sync] Received chain from peer (Height: {})", remote_chain.len());
-                 let mut chain = self.blockchain.lock().unwrap();
-                 if chain.attempt_chain_replacement(remote_chain) {
+                 if Node::replace_chain(&self.blockchain, remote_chain) {
                      println!("[Sync] Sync complete.");
                  }
              }
@@ -180,6 +210,21 @@ impl Node {
         }
     }
 
+    pub fn broadcast_attestation(&self, tx: Transaction) {
+        Node::send_to_peers(&self.peers, &Message::Attestation(tx));
+    }
+
+    fn send_to_peers(peers: &Arc<Mutex<Vec<String>>>, msg: &Message) {
+        let msg_json = serde_json::to_string(msg).unwrap_or_default();
+        let peers = peers.lock().unwrap().clone();
+
+        for peer in peers.iter() {
+             if let Ok(mut stream) = TcpStream::connect(peer) {
+                 let _ = stream.write(msg_json.as_bytes());
+             }
+        }
+    }
+
     pub fn start_discovery(&self) {
         let peers_ref = self.peers.clone();
         
@@ -0,0 +1,172 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+// BIP9: Version bits header (top 3 bits = 001), remaining 29 bits are signalling flags
+pub const VERSIONBITS_TOP_BITS: u32 = 0x20000000;
+pub const VERSIONBITS_TOP_MASK: u32 = 0xE0000000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DeploymentState {
+    Defined,
+    Started,
+    LockedIn,
+    Active,
+    Failed,
+}
+
+/// A soft fork signalled through header version bits (BIP9-style, height based)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Deployment {
+    pub name: String,
+    pub bit: u8,             // 0..=28
+    pub start_height: u64,   // Signalling starts at the first window boundary >= start_height
+    pub timeout_height: u64, // Fails if not locked in by the first window boundary >= timeout_height
+}
+
+impl Deployment {
+    pub fn mask(&self) -> u32 {
+        1u32 << self.bit
+    }
+
+    pub fn is_signalled_by(&self, version: u32) -> bool {
+        (version & VERSIONBITS_TOP_MASK) == VERSIONBITS_TOP_BITS && (version & self.mask()) != 0
+    }
+}
+
+/// Block subsidy schedule and genesis premine (Atomic Units: 1 VLT = 100,000,000)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmissionSchedule {
+    pub initial_reward: u64,
+    pub halving_interval: u64,
+    pub premine: u64,
+    pub premine_address: String,
+}
+
+impl EmissionSchedule {
+    pub fn block_reward(&self, height: u64) -> u64 {
+        let halvings = height / self.halving_interval;
+        if halvings >= 64 { return 0; }
+        self.initial_reward >> halvings
+    }
+
+    // Sum of scheduled subsidies for blocks 1..=height (Genesis only carries the premine)
+    pub fn subsidy_until(&self, height: u64) -> u64 {
+        let mut total: u128 = 0;
+        let mut start = 1;
+        while start <= height {
+            let reward = self.block_reward(start);
+            if reward == 0 { break; }
+            // Last height of this halving era
+            let era_end = (start / self.halving_interval + 1).saturating_mul(self.halving_interval) - 1;
+            let end = era_end.min(height);
+            total += (end - start + 1) as u128 * reward as u128;
+            start = end.saturating_add(1);
+            if end == u64::MAX { break; }
+        }
+        total.min(u64::MAX as u128) as u64
+    }
+
+    // Premine + every scheduled subsidy. Fee and staking mints come on top of this.
+    pub fn max_supply(&self) -> u64 {
+        self.premine.saturating_add(self.subsidy_until(u64::MAX))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChainParams {
+    // Difficulty Retarget (also the version bits signalling window)
+    pub retarget_interval: u64,
+    pub target_block_time: u64,
+
+    // Version Bits: blocks per window that must signal for lock-in
+    pub activation_threshold: u64,
+    pub deployments: Vec<Deployment>,
+
+    // Checkpoints (Height -> Hash): forks below a checkpoint are rejected
+    pub checkpoints: BTreeMap<u64, String>,
+    // Assume-Valid: signatures in ancestors of this block are not re-verified during sync
+    pub assume_valid: Option<String>,
+
+    pub emission: EmissionSchedule,
+}
+
+impl ChainParams {
+    pub fn mainnet() -> Self {
+        ChainParams {
+            retarget_interval: 10, // Fast adj for testing
+            target_block_time: 60, // 1 Minute
+            activation_threshold: 9, // 90% of the window
+            deployments: vec![
+                // Signalling test deployment (no consensus rules attached)
+                Deployment { name: "testdummy".to_string(), bit: 28, start_height: 0, timeout_height: u64::MAX },
+                // Stake claims must be committed in the Coinbase and signed by the staking key
+                Deployment { name: "stakesig".to_string(), bit: 1, start_height: 0, timeout_height: u64::MAX },
+            ],
+            checkpoints: BTreeMap::from([
+                (0, "6f22e8ff0d766afb8b685c50677bf7fc2d98f8769236e769414a060f916c9bae".to_string()), // Genesis
+            ]),
+            assume_valid: None,
+            emission: EmissionSchedule {
+                initial_reward: 50 * 100_000_000, // 50 VLT
+                halving_interval: 105_000,        // ~2 Years at 60s blocks
+                premine: 105_000_000_000_000,     // 1,050,000 VLT (5% of 21M)
+                premine_address: "024dea39ce2e873d5be2d8e092044a7dbd9cfa2dadcba5d32e9b141b7361422d56".to_string(),
+            },
+        }
+    }
+
+    // True if there is no checkpoint at `height` or the hash matches it
+    pub fn checkpoint_matches(&self, height: u64, hash: &str) -> bool {
+        match self.checkpoints.get(&height) {
+            Some(expected) => expected == hash,
+            None => true,
+        }
+    }
+
+    pub fn last_checkpoint(&self) -> Option<(u64, &String)> {
+        self.checkpoints.iter().next_back().map(|(h, hash)| (*h, hash))
+    }
+
+    pub fn deployment(&self, name: &str) -> Option<&Deployment> {
+        self.deployments.iter().find(|d| d.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(initial_reward: u64, halving_interval: u64) -> EmissionSchedule {
+        EmissionSchedule { initial_reward, halving_interval, premine: 1_000, premine_address: String::new() }
+    }
+
+    #[test]
+    fn rewards_halve_every_interval_until_zero() {
+        let emission = schedule(100, 10);
+        assert_eq!(emission.block_reward(1), 100);
+        assert_eq!(emission.block_reward(9), 100);
+        assert_eq!(emission.block_reward(10), 50);
+        assert_eq!(emission.block_reward(25), 25);
+        assert_eq!(emission.block_reward(70), 0); // 100 >> 7
+        assert_eq!(emission.block_reward(64 * 10), 0);
+        assert_eq!(emission.block_reward(u64::MAX), 0);
+    }
+
+    #[test]
+    fn subsidy_sums_the_schedule_block_by_block() {
+        let emission = schedule(100, 10);
+        for height in [0, 1, 9, 10, 11, 35, 69, 70, 500] {
+            let expected: u64 = (1..=height).map(|h| emission.block_reward(h)).sum();
+            assert_eq!(emission.subsidy_until(height), expected, "height {}", height);
+        }
+        // Genesis era is one block short: 9 * 100 + 10 * (50 + 25 + 12 + 6 + 3 + 1)
+        assert_eq!(emission.max_supply(), 1_000 + 900 + 970);
+
+        // Mainnet stays below 21M VLT
+        let mainnet = ChainParams::mainnet().emission;
+        let vlt = 100_000_000;
+        assert_eq!(mainnet.subsidy_until(105_000), 104_999 * 50 * vlt + 25 * vlt);
+        assert!(mainnet.max_supply() < 21_000_000 * vlt);
+        assert!(mainnet.max_supply() > mainnet.premine + 10_499_000 * vlt);
+    }
+}
@@ -19,6 +19,11 @@ pub enum OpCode {
     OpEqualVerify,
     /// Lock until timestamp (CLTV)
     OpCheckLockTimeVerify,
+    /// Pop window (u64 seconds) and pool ID, push the pool's TWAP (u64, token_b per token_a * 10^8)
+    /// over the window ending at the last block before the transaction's block
+    OpTwap,
+    /// Pop b and a (u64 each), fail unless a <= b
+    OpLessOrEqualVerify,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -35,18 +40,49 @@ impl Script {
         self.ops.push(op);
         self
     }
+
+    /// Standard output lock `OpDup OpHash256 <hash> OpEqualVerify OpCheckSig` (Transaction::new, receiver side)
+    pub fn is_p2pkh(&self) -> bool {
+        matches!(self.ops.as_slice(), [OpCode::OpDup, OpCode::OpHash256, OpCode::OpPush(_), OpCode::OpEqualVerify, OpCode::OpCheckSig])
+    }
+
+    /// Number of signature checks this script can trigger (counted towards block sigop limits)
+    pub fn sig_op_count(&self) -> u64 {
+        self.ops.iter().filter(|op| **op == OpCode::OpCheckSig).count() as u64
+    }
+}
+
+/// Read-only chain data available to scripts
+pub trait PriceOracle {
+    /// TWAP of `pool` over the last `window` seconds (token_b per token_a * 10^8)
+    fn twap(&self, pool: &str, window: u64) -> Option<u64>;
 }
 
-pub struct VirtualMachine {
+pub struct VirtualMachine<'a> {
     stack: Vec<Vec<u8>>,
+    oracle: &'a dyn PriceOracle,
 }
 
-impl VirtualMachine {
-    pub fn new() -> Self {
-        VirtualMachine { stack: Vec::new() }
+// Stack items are numbers as 8 bytes big endian
+fn to_u64(item: &[u8]) -> Option<u64> {
+    item.try_into().ok().map(u64::from_be_bytes)
+}
+
+impl<'a> VirtualMachine<'a> {
+    pub fn with_oracle(oracle: &'a dyn PriceOracle) -> Self {
+        VirtualMachine { stack: Vec::new(), oracle }
     }
 
+    /// Runs the script and requires a TRUE (1) stack top
     pub fn execute(&mut self, script: &Script, context: &Transaction) -> bool {
+        if !self.run(script, context) {
+            return false;
+        }
+        self.stack.last() == Some(&vec![1])
+    }
+
+    /// Runs the script, leaving its items on the stack (unlocking scripts feed the locking script)
+    pub fn run(&mut self, script: &Script, context: &Transaction) -> bool {
         for op in &script.ops {
             match op {
                 OpCode::OpPush(data) => {
@@ -115,14 +151,27 @@ impl VirtualMachine {
                          let lock_time = u64::from_be_bytes(item[0..8].try_into().unwrap_or([0;8]));
                          if context.timestamp < lock_time { return false; } // Lock is still active
                     } else { return false; }
+                },
+                OpCode::OpTwap => {
+                    if self.stack.len() < 2 { return false; }
+                    let window = match to_u64(&self.stack.pop().unwrap()) { Some(w) => w, None => return false };
+                    let pool = match String::from_utf8(self.stack.pop().unwrap()) { Ok(p) => p, Err(_) => return false };
+                    match self.oracle.twap(&pool, window) {
+                        Some(price) => self.stack.push(price.to_be_bytes().to_vec()),
+                        None => return false, // No price history for the window
+                    }
+                },
+                OpCode::OpLessOrEqualVerify => {
+                    if self.stack.len() < 2 { return false; }
+                    let b = self.stack.pop().unwrap();
+                    let a = self.stack.pop().unwrap();
+                    match (to_u64(&a), to_u64(&b)) {
+                        (Some(a), Some(b)) if a <= b => {},
+                        _ => return false,
+                    }
                 }
             }
         }
-        
-        // Validation successful if stack top is TRUE (1)
-        if let Some(top) = self.stack.last() {
-             return top == &vec![1];
-        }
-        false
+        true
     }
 }
@@ -0,0 +1,168 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::thread;
+use sha2::{Sha256, Digest};
+use crate::transaction::Transaction;
+
+// Bounded so a flood of valid transactions cannot exhaust memory (oldest entries evicted first)
+const MAX_CACHE_ENTRIES: usize = 200_000;
+
+// Below this many signatures, spawning threads costs more than it saves
+const MIN_PARALLEL_BATCH: usize = 16;
+
+/// Signatures already proven valid, shared between mempool admission and block validation.
+/// Key = SHA256(tx body hash + signature), so any change to the body or signature misses the cache.
+pub struct SignatureCache {
+    inner: Mutex<CacheInner>,
+}
+
+struct CacheInner {
+    entries: HashSet<Vec<u8>>,
+    order: VecDeque<Vec<u8>>,
+}
+
+impl SignatureCache {
+    pub fn new() -> Self {
+        SignatureCache {
+            inner: Mutex::new(CacheInner { entries: HashSet::new(), order: VecDeque::new() }),
+        }
+    }
+
+    fn key(tx: &Transaction) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(tx.get_hash());
+        hasher.update(tx.signature.as_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.inner.lock().unwrap().entries.contains(key)
+    }
+
+    fn insert(&self, key: Vec<u8>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.entries.insert(key.clone()) {
+            inner.order.push_back(key);
+            while inner.order.len() > MAX_CACHE_ENTRIES {
+                if let Some(old) = inner.order.pop_front() {
+                    inner.entries.remove(&old);
+                }
+            }
+        }
+    }
+
+    /// Verify a single transaction, consulting and filling the cache
+    pub fn verify(&self, tx: &Transaction) -> Result<(), String> {
+        if tx.sender == "SYSTEM" { return Ok(()); }
+        let key = SignatureCache::key(tx);
+        if self.contains(&key) { return Ok(()); }
+        tx.verify_signature().map_err(|e| format!("{} (Tx {})", e, hex::encode(tx.get_hash())))?;
+        self.insert(key);
+        Ok(())
+    }
+
+    /// Verify many transactions across all CPU cores. Returns the first failure.
+    pub fn verify_batch(&self, txs: &[&Transaction]) -> Result<(), String> {
+        let pending: Vec<&Transaction> = txs.iter()
+            .copied()
+            .filter(|tx| tx.sender != "SYSTEM" && !self.contains(&SignatureCache::key(tx)))
+            .collect();
+
+        if pending.len() < MIN_PARALLEL_BATCH {
+            for tx in pending {
+                self.verify(tx)?;
+            }
+            return Ok(());
+        }
+
+        let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let chunk_size = pending.len().div_ceil(workers);
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = pending.chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || {
+                    for tx in chunk {
+                        self.verify(tx)?;
+                    }
+                    Ok(())
+                }))
+                .collect();
+
+            let mut result = Ok(());
+            for handle in handles {
+                let outcome = handle.join().unwrap_or_else(|_| Err("Verification thread panicked".to_string()));
+                if result.is_ok() {
+                    result = outcome;
+                }
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    fn signed_transfer(seed: u8, nonce: u64) -> Transaction {
+        let key = SigningKey::from_slice(&[seed; 32]).unwrap();
+        let sender = hex::encode(key.verifying_key().to_sec1_bytes());
+        let mut tx = Transaction::new(sender, "02ff".to_string(), 1, "VLT".to_string(), nonce);
+        tx.sign(&key);
+        tx
+    }
+
+    #[test]
+    fn valid_signatures_are_cached_and_changes_miss() {
+        let cache = SignatureCache::new();
+        let tx = signed_transfer(1, 1);
+        assert!(!cache.contains(&SignatureCache::key(&tx)));
+        assert!(cache.verify(&tx).is_ok());
+        assert!(cache.contains(&SignatureCache::key(&tx)));
+        assert!(cache.verify(&tx).is_ok());
+
+        // Any change to the body or the signature is a miss and fails verification
+        let mut changed = tx.clone();
+        changed.amount += 1;
+        assert!(!cache.contains(&SignatureCache::key(&changed)));
+        assert!(cache.verify(&changed).is_err());
+        assert!(!cache.contains(&SignatureCache::key(&changed)));
+    }
+
+    #[test]
+    fn malformed_keys_and_signatures_are_errors() {
+        let cache = SignatureCache::new();
+        let tx = signed_transfer(1, 1);
+        let cases = [
+            ("zz".to_string(), tx.signature.clone()),               // Sender not hex
+            ("02".repeat(33), tx.signature.clone()),                // Not a curve point
+            (String::new(), tx.signature.clone()),                  // Empty sender
+            (tx.sender.clone(), "xyz".to_string()),                 // Signature not hex
+            (tx.sender.clone(), "3006020101020101".to_string()),    // DER, invalid scalars
+            (tx.sender.clone(), String::new()),                     // Unsigned
+        ];
+        for (sender, signature) in cases {
+            let mut bad = tx.clone();
+            bad.sender = sender;
+            bad.signature = signature;
+            assert!(cache.verify(&bad).is_err());
+            assert!(cache.verify_batch(&[&bad]).is_err());
+        }
+    }
+
+    #[test]
+    fn batches_verify_in_parallel_and_report_a_bad_signature() {
+        let cache = SignatureCache::new();
+        let txs: Vec<Transaction> = (1..=40).map(|n| signed_transfer(n as u8, n)).collect();
+        let refs: Vec<&Transaction> = txs.iter().collect();
+        assert!(cache.verify_batch(&refs).is_ok());
+        assert!(refs.iter().all(|tx| cache.contains(&SignatureCache::key(tx))));
+
+        let mut forged = txs[7].clone();
+        forged.signature = txs[8].signature.clone();
+        let mut with_forged = refs.clone();
+        with_forged.push(&forged);
+        assert!(cache.verify_batch(&with_forged).is_err());
+    }
+}
@@ -218,11 +218,20 @@ fn handle_client(
             if should_update {
                 // Generate New Job
                 let miner = miner_notify.lock().unwrap().clone();
-                let new_block = { // Removed mut, not needed for get_mining_candidate
+                let candidate = {
                     if let Ok(c) = chain_notify.lock() {
-                        c.get_mining_candidate(miner)
+                        c.get_mining_candidate(miner.clone())
                     } else { continue; }
                 };
+                let new_block = match candidate {
+                    Ok(block) => block,
+                    Err(e) => {
+                        // No job until the next block (the address may unstake meanwhile)
+                        println!("[Stratum] No job for {}: {}", miner, e);
+                        *height_notify.lock().unwrap() = current_height;
+                        continue;
+                    }
+                };
                 
                 // Update Template
                 *block_notify.lock().unwrap() = Some(new_block.clone());
@@ -248,6 +257,7 @@ fn handle_client(
                 
                 let coinb2 = format!("ffffffff01{}1976a91439209d6f37e633202573205730305f523030303088ac00000000", amt_hex);
                 let bits_hex = format!("{:08x}", new_block.difficulty);
+                let version_hex = format!("{:08x}", new_block.version); // Version Bits Signalling
 
                 // Merkle Branch
                 let mut branch: Vec<String> = Vec::new();
@@ -275,7 +285,7 @@ fn handle_client(
 
                 let notify = serde_json::json!({
                     "id": null, "method": "mining.notify",
-                    "params": [ job_id, prev_hash_hex, coinb1, coinb2, branch, "00000001", bits_hex, ntime, true ]
+                    "params": [ job_id, prev_hash_hex, coinb1, coinb2, branch, version_hex, bits_hex, ntime, true ]
                 });
                 
                 if let Ok(n_str) = serde_json::to_string(&notify) {
@@ -299,6 +309,7 @@ fn handle_client(
             Ok(_) => {
                 if let Ok(req) = serde_json::from_str::<RpcRequest>(&line) {
                     let mut result = None;
+                    let mut error = None;
                     
                     match req.method.as_str() {
                         "mining.subscribe" => {
@@ -308,21 +319,27 @@ fn handle_client(
                             ]));
                         },
                         "mining.authorize" => {
-                            result = Some(serde_json::json!(true));
-                            *is_authorized.lock().unwrap() = true; // Use original Arc, not the moved clone
-                            
-                            // Send Initial Diff (Standard)
-                            let diff_notify = serde_json::json!({ "id": null, "method": "mining.set_difficulty", "params": [0.1] });
-                            let _ = stream_writer_resp.write_all((serde_json::to_string(&diff_notify).unwrap() + "\n").as_bytes());
-
-                            if let Some(user_full) = req.params.get(0).and_then(|v| v.as_str()) {
-                                // Strip Worker Name (e.g. "Addr.Rig1" -> "Addr")
-                                let addr_part = user_full.split('.').next().unwrap_or(user_full);
-                                *session_miner_addr.lock().unwrap() = addr_part.to_string();
-                                println!("[Stratum] Authorized Miner: {} (Worker: {})", addr_part, user_full);
+                            // Strip Worker Name (e.g. "Addr.Rig1" -> "Addr")
+                            let user_full = req.params.first().and_then(|v| v.as_str()).unwrap_or("");
+                            let addr_part = user_full.split('.').next().unwrap_or(user_full);
+                            if let Err(e) = chain.lock().unwrap().check_stratum_miner(addr_part) {
+                                println!("[Stratum] Refused Miner {}: {}", addr_part, e);
+                                error = Some(serde_json::json!([24, e, null])); // 24: Unauthorized worker
+                            } else {
+                                result = Some(serde_json::json!(true));
+                                *is_authorized.lock().unwrap() = true; // Use original Arc, not the moved clone
+
+                                // Send Initial Diff (Standard)
+                                let diff_notify = serde_json::json!({ "id": null, "method": "mining.set_difficulty", "params": [0.1] });
+                                let _ = stream_writer_resp.write_all((serde_json::to_string(&diff_notify).unwrap() + "\n").as_bytes());
+
+                                if !user_full.is_empty() {
+                                    *session_miner_addr.lock().unwrap() = addr_part.to_string();
+                                    println!("[Stratum] Authorized Miner: {} (Worker: {})", addr_part, user_full);
+                                }
+                                // Trigger immediate update by resetting height
+                                *last_notified_height.lock().unwrap() = 0;
                             }
-                            // Trigger immediate update by resetting height
-                            *last_notified_height.lock().unwrap() = 0; 
                         },
                         "mining.submit" => {
                             // Params: [worker_name, job_id, extranonce2, ntime, nonce]
@@ -578,8 +595,8 @@ fn handle_client(
                         _ => {}
                     }
                     
-                    if let Some(res) = result {
-                        let resp = RpcResponse { id: req.id, result: Some(res), error: None };
+                    if result.is_some() || error.is_some() {
+                        let resp = RpcResponse { id: req.id, result, error };
                         if let Ok(resp_str) = serde_json::to_string(&resp) {
                             let _ = stream_writer_resp.write_all((resp_str + "\n").as_bytes());
                         }
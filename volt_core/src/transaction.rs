@@ -18,7 +18,21 @@ pub enum TxType {
     Swap,
     IssueNFT,
     TransferNFT,
-    BurnNFT
+    BurnNFT,
+    Slash,
+    Delegate,
+    Undelegate,
+    SetCommission,
+    Propose,
+    Vote,
+    Attest,
+    Mint,
+    RenounceMint,
+    TokenControl,
+    Approve,
+    TransferFrom,
+    ListPair,
+    RouteSwap
 }
 
 fn default_tx_type() -> TxType { TxType::Transfer }
@@ -58,6 +72,10 @@ pub struct Transaction {
     // Phase 34: DEX
     #[serde(default)]
     pub price: u64, // For Limit Orders (VLT per Token Unit)
+
+    // Structured Payload (JSON), e.g. Slashing Evidence
+    #[serde(default)]
+    pub data: String,
 }
 
 impl Transaction {
@@ -92,6 +110,7 @@ impl Transaction {
             script_pub_key,
             script_sig: Script::new(),
             price: 0,
+            data: String::new(),
         }
     }
 
@@ -114,7 +133,58 @@ impl Transaction {
             script_pub_key: Script::new(),
             script_sig: Script::new(),
             price: 0,
+            data: String::new(),
+        }
+    }
+
+    // Issuer mints `amount` more of `token` to `receiver` (requires mint authority)
+    pub fn new_mint(sender: String, token: String, receiver: String, amount: u64, nonce: u64) -> Self {
+        let mut tx = Transaction::new_token_issue(sender, token, amount, nonce);
+        tx.receiver = receiver;
+        tx.tx_type = TxType::Mint;
+        tx.fee = 100_000;
+        tx
+    }
+
+    // Issuer permanently gives up mint authority (fixed supply from here on)
+    pub fn new_renounce_mint(sender: String, token: String, nonce: u64) -> Self {
+        let mut tx = Transaction::new_token_issue(sender, token, 0, nonce);
+        tx.tx_type = TxType::RenounceMint;
+        tx.fee = 100_000;
+        tx
+    }
+
+    // Issuer compliance action (Freeze, Pause, Allowlist, Clawback, Renounce one control) as JSON in data
+    pub fn new_token_control(sender: String, token: String, action: String, nonce: u64) -> Self {
+        let mut tx = Transaction::new_token_issue(sender, token, 0, nonce);
+        tx.receiver = "TOKEN_CONTROL".to_string();
+        tx.tx_type = TxType::TokenControl;
+        tx.fee = 100_000;
+        tx.data = action;
+        tx
+    }
+
+    // Allowance: `spender` may move up to `amount` of the sender's `token` (0 revokes).
+    // Expiry height travels in the signed data field (None = no expiry).
+    pub fn new_approve(sender: String, spender: String, token: String, amount: u64, expiry_height: Option<u64>, nonce: u64) -> Self {
+        let mut tx = Transaction::new_token_issue(sender, token, amount, nonce);
+        tx.receiver = spender;
+        tx.tx_type = TxType::Approve;
+        tx.fee = 100_000;
+        if expiry_height.is_some() {
+            tx.data = serde_json::to_string(&crate::chain::ApproveParams { expiry_height }).unwrap_or_default();
         }
+        tx
+    }
+
+    // Spender moves `amount` of `owner`'s tokens to `receiver` (owner address in data)
+    pub fn new_transfer_from(spender: String, owner: String, receiver: String, token: String, amount: u64, nonce: u64) -> Self {
+        let mut tx = Transaction::new_token_issue(spender, token, amount, nonce);
+        tx.receiver = receiver;
+        tx.tx_type = TxType::TransferFrom;
+        tx.fee = 100_000;
+        tx.data = owner;
+        tx
     }
 
     pub fn calculate_hash(&self) -> String {
@@ -158,6 +228,7 @@ impl Transaction {
             script_pub_key: Script::new(),
             script_sig: Script::new(),
             price: 0,
+            data: String::new(),
         }
     }
 
@@ -183,7 +254,45 @@ impl Transaction {
             script_pub_key: Script::new(),
             script_sig: Script::new(),
             price,
+            data: String::new(),
+        }
+    }
+
+    // Order on a BASE/QUOTE pair with type and expiry (the plain `new_order` is a VLT quoted limit order)
+    pub fn new_pair_order(sender: String, base: String, params: &crate::dex::OrderParams, side: &str, amount: u64, price: u64, nonce: u64) -> Self {
+        let mut tx = Transaction::new_order(sender, base, side, amount, price, nonce);
+        if !params.is_default() {
+            tx.data = serde_json::to_string(params).unwrap_or_default();
         }
+        tx
+    }
+
+    // Lists a BASE/QUOTE order book with its tick and lot sizes (TradingPair JSON in data)
+    pub fn new_list_pair(sender: String, pair: &crate::dex::TradingPair, nonce: u64) -> Self {
+        let mut tx = Transaction::new_token_issue(sender, pair.base.clone(), 0, nonce);
+        tx.receiver = "DEX_LIST".to_string();
+        tx.tx_type = TxType::ListPair;
+        tx.data = serde_json::to_string(pair).unwrap_or_default();
+        tx
+    }
+
+    // Multi-hop AMM swap along params.path (first = input token) with one bound for the whole route:
+    // exact input (amount in, minimum output) or exact output (amount out, maximum input).
+    // The bound is signed as part of the data; price only mirrors it.
+    pub fn new_route_swap(sender: String, params: &crate::dex::SwapParams, amount: u64, limit: u64, nonce: u64) -> Self {
+        let mut params = params.clone();
+        if params.exact_output {
+            params.max_input = Some(limit);
+        } else {
+            params.min_output = Some(limit);
+        }
+        let mut tx = Transaction::new_token_issue(sender, params.path.first().cloned().unwrap_or_default(), amount, nonce);
+        tx.receiver = "SWAP_ROUTE".to_string();
+        tx.tx_type = TxType::RouteSwap;
+        tx.price = limit;
+        tx.fee = 100_000;
+        tx.data = serde_json::to_string(&params).unwrap_or_default();
+        tx
     }
 
     pub fn new_cancel(sender: String, order_id: String, nonce: u64) -> Self {
@@ -206,6 +315,7 @@ impl Transaction {
             script_pub_key: Script::new(),
             script_sig: Script::new(),
             price: 0,
+            data: String::new(),
         }
     }
 
@@ -228,6 +338,7 @@ impl Transaction {
             script_pub_key: Script::new(),
             script_sig: Script::new(),
             price: 0,
+            data: String::new(),
         }
     }
 
@@ -250,6 +361,80 @@ impl Transaction {
             script_pub_key: Script::new(),
             script_sig: Script::new(),
             price: 0,
+            data: String::new(),
+        }
+    }
+
+    // Delegation: VLT stays owned by the delegator but counts toward the validator's stake
+    pub fn new_delegate(sender: String, validator: String, amount: u64, nonce: u64) -> Self {
+        let mut tx = Transaction::new_stake(sender, amount, nonce);
+        tx.receiver = validator;
+        tx.tx_type = TxType::Delegate;
+        tx
+    }
+
+    pub fn new_undelegate(sender: String, validator: String, amount: u64, nonce: u64) -> Self {
+        let mut tx = Transaction::new_stake(sender, amount, nonce);
+        tx.receiver = validator;
+        tx.tx_type = TxType::Undelegate;
+        tx
+    }
+
+    // Commission in basis points (100 = 1%) kept by the validator from its delegators' rewards
+    pub fn new_set_commission(sender: String, commission_bps: u64, nonce: u64) -> Self {
+        let mut tx = Transaction::new_stake(sender.clone(), commission_bps, nonce);
+        tx.receiver = sender;
+        tx.tx_type = TxType::SetCommission;
+        tx
+    }
+
+    // Finality: the staker's signature over (checkpoint height, block hash)
+    pub fn new_attestation(sender: String, height: u64, block_hash: String, nonce: u64) -> Self {
+        let mut tx = Transaction::new_stake(sender, height, nonce);
+        tx.receiver = "FINALITY".to_string();
+        tx.tx_type = TxType::Attest;
+        tx.data = block_hash;
+        tx
+    }
+
+    // Governance: data = ProposalContent (JSON)
+    pub fn new_proposal(sender: String, content: String, nonce: u64) -> Self {
+        let mut tx = Transaction::new_stake(sender, 0, nonce);
+        tx.receiver = "GOVERNANCE".to_string();
+        tx.tx_type = TxType::Propose;
+        tx.data = content;
+        tx
+    }
+
+    // Governance: amount = Proposal ID, receiver = VOTE_YES / VOTE_NO / VOTE_ABSTAIN
+    pub fn new_vote(sender: String, proposal_id: u64, option: &str, nonce: u64) -> Self {
+        let mut tx = Transaction::new_stake(sender, proposal_id, nonce);
+        tx.receiver = option.to_string();
+        tx.tx_type = TxType::Vote;
+        tx
+    }
+
+    // Slash the offender (receiver) using Equivocation Evidence; the reporter earns part of the penalty
+    pub fn new_slash(sender: String, offender: String, evidence: String, nonce: u64) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        Transaction {
+            sender,
+            receiver: offender,
+            amount: 0,
+            signature: String::new(),
+            timestamp,
+            token: "VLT".to_string(),
+            tx_type: TxType::Slash,
+            nonce,
+            fee: 100_000,
+            script_pub_key: Script::new(),
+            script_sig: Script::new(),
+            price: 0,
+            data: evidence,
         }
     }
 
@@ -279,6 +464,10 @@ impl Transaction {
              bytes.extend(self.receiver.as_bytes());
              bytes.extend(&self.amount.to_le_bytes());
              bytes.extend(&self.timestamp.to_le_bytes());
+             // Coinbase Stake Commitment (price = claimed validator stake), only when set
+             if self.price > 0 {
+                 bytes.extend(&self.price.to_le_bytes());
+             }
         } else {
              // Regular Transaction (Binary Packing)
              bytes.extend(self.sender.as_bytes());
@@ -301,10 +490,28 @@ impl Transaction {
                  TxType::IssueNFT => 10,
                  TxType::TransferNFT => 11,
                  TxType::BurnNFT => 12,
+                 TxType::Slash => 13,
+                 TxType::Delegate => 14,
+                 TxType::Undelegate => 15,
+                 TxType::SetCommission => 16,
+                 TxType::Propose => 17,
+                 TxType::Vote => 18,
+                 TxType::Attest => 19,
+                 TxType::Mint => 20,
+                 TxType::RenounceMint => 21,
+                 TxType::TokenControl => 22,
+                 TxType::Approve => 23,
+                 TxType::TransferFrom => 24,
+                 TxType::ListPair => 25,
+                 TxType::RouteSwap => 26,
              };
              bytes.push(type_byte);
              bytes.extend(&self.nonce.to_le_bytes());
              bytes.extend(&self.fee.to_le_bytes());
+             // Payload (only when set, keeps hashes of older tx types unchanged)
+             if !self.data.is_empty() {
+                 bytes.extend(self.data.as_bytes());
+             }
              
              // Script Pub Key (Ops)
              for _op in &self.script_pub_key.ops {
@@ -324,6 +531,17 @@ impl Transaction {
         hasher2.finalize().to_vec()
     }
 
+    /// Size of the transaction as relayed and stored (JSON bytes)
+    pub fn serialized_size(&self) -> usize {
+        serde_json::to_vec(self).map(|b| b.len()).unwrap_or(usize::MAX)
+    }
+
+    /// Signature operations: the sender signature plus any OpCheckSig in the scripts
+    pub fn sig_op_count(&self) -> u64 {
+        let base = if self.sender == "SYSTEM" { 0 } else { 1 };
+        base + self.script_sig.sig_op_count() + self.script_pub_key.sig_op_count()
+    }
+
     pub fn sign(&mut self, private_key: &SigningKey) {
         let hash = self.get_hash();
         let signature: Signature = private_key.sign(&hash);
@@ -342,17 +560,22 @@ impl Transaction {
     }
 
     pub fn verify(&self) -> bool {
+        self.verify_signature().is_ok()
+    }
+
+    // Malformed keys/signatures are rejection errors, never panics (untrusted network input)
+    pub fn verify_signature(&self) -> Result<(), String> {
          if self.sender == "SYSTEM" {
-             return true; // Mining rewards have no sender
+             return Ok(()); // Mining rewards have no sender
          }
 
-        let public_key_bytes = hex::decode(&self.sender).expect("Invalid sender hex");
-        let public_key = VerifyingKey::from_sec1_bytes(&public_key_bytes).expect("Invalid public key");
+        let public_key_bytes = hex::decode(&self.sender).map_err(|_| "Invalid sender hex".to_string())?;
+        let public_key = VerifyingKey::from_sec1_bytes(&public_key_bytes).map_err(|_| "Invalid public key".to_string())?;
         
-        let signature_bytes = hex::decode(&self.signature).expect("Invalid signature hex");
-        let signature = Signature::from_der(&signature_bytes).expect("Invalid signature");
+        let signature_bytes = hex::decode(&self.signature).map_err(|_| "Invalid signature hex".to_string())?;
+        let signature = Signature::from_der(&signature_bytes).map_err(|_| "Invalid signature encoding".to_string())?;
 
         let hash = self.get_hash();
-        public_key.verify(&hash, &signature).is_ok()
+        public_key.verify(&hash, &signature).map_err(|_| "Signature mismatch".to_string())
     }
 }
@@ -147,6 +147,7 @@ struct WalletApp {
     contact_name_input: String,
     contact_addr_input: String,
     assets: HashMap<String, i64>,
+    asset_meta: HashMap<String, Value>, // Token Metadata (name, decimals, max_supply, uri, mintable)
     qr_texture: Option<egui::TextureHandle>,
     mnemonic_display: String,
     import_input: String,
@@ -186,6 +187,7 @@ enum GuiMessage {
         assets: HashMap<String, i64>,
         peers: usize,
     },
+    AssetMetadata(HashMap<String, Value>),
     ShowMnemonic(String),
 }
 
@@ -278,6 +280,7 @@ impl WalletApp {
                 let mut blocks_vec = Vec::new();
 
                 let mut assets_map = HashMap::new();
+                let mut meta_map = HashMap::new();
                 let mut peers_count = 0;
 
                 if let Ok(res) = client.call("get_status", None) {
@@ -322,6 +325,9 @@ impl WalletApp {
                                 if let Some(a) = data.get("assets") {
                                     if let Ok(map) = serde_json::from_value(a.clone()) { assets_map = map; }
                                 }
+                                if let Some(m) = data.get("metadata") {
+                                    if let Ok(map) = serde_json::from_value(m.clone()) { meta_map = map; }
+                                }
                             }
                         }
                      } else {
@@ -351,6 +357,7 @@ impl WalletApp {
                     balance, address, history: history_vec, blocks: blocks_vec,
                     status, block_height, pending_txs, is_locked, assets: assets_map, peers: peers_count
                 }).ok();
+                tx_gui.send(GuiMessage::AssetMetadata(meta_map)).ok();
                 thread::sleep(Duration::from_secs(1));
             }
         });
@@ -389,6 +396,7 @@ impl WalletApp {
             contact_name_input: String::new(),
             contact_addr_input: String::new(),
             assets: HashMap::new(),
+            asset_meta: HashMap::new(),
             qr_texture: None,
             mnemonic_display: String::new(),
             import_input: String::new(),
@@ -461,6 +469,7 @@ impl eframe::App for WalletApp {
                         self.show_sync_window = false;
                     }
                 },
+                GuiMessage::AssetMetadata(meta) => { self.asset_meta = meta; }
                 GuiMessage::ShowMnemonic(m) => { self.mnemonic_display = m; }
              }
         }
@@ -676,7 +685,7 @@ impl eframe::App for WalletApp {
                                  ui.label("None");
                              } else {
                                  for (t, b) in &self.assets {
-                                     if t != "VLT" { ui.label(format!("{} {}", b, t)); }
+                                     if t != "VLT" { ui.label(format!("{} {}", format_token_amount(*b, self.asset_meta.get(t)), t)); }
                                  }
                              }
                           });
@@ -919,17 +928,32 @@ impl eframe::App for WalletApp {
                      } else {
                          for (sym, bal) in &self.assets {
                              if sym != "VLT" {
+                                  let meta = self.asset_meta.get(sym);
                                   ui.group(|ui| {
                                       ui.horizontal(|ui| {
                                           ui.label(egui::RichText::new(sym).strong().size(16.0));
+                                          if let Some(name) = meta.and_then(|m| m["name"].as_str()).filter(|n| n != sym) {
+                                              ui.label(egui::RichText::new(name).color(COL_SUBTEXT));
+                                          }
                                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                               // Assuming other assets are also atomic? If not, this might be formatted wrong.
-                                               // For safety, let's treat custom tokens as raw units for now or consistent?
-                                               // User likely wants consistency. Let's assume all tokens are 8-decimals.
-                                               let val = *bal as f64;
-                                               ui.label(egui::RichText::new(format!("{:.8}", val / 100_000_000.0)).strong());
+                                               // Formatted with the token's own decimals (8 if unknown)
+                                               ui.label(egui::RichText::new(format_token_amount(*bal, meta)).strong());
                                            });
                                       });
+                                      if let Some(m) = meta {
+                                          ui.horizontal(|ui| {
+                                              let supply = match m["max_supply"].as_u64() {
+                                                  Some(max) => format!("Max Supply: {}", format_token_amount(max as i64, meta)),
+                                                  None => "Max Supply: Unlimited".to_string(),
+                                              };
+                                              ui.label(egui::RichText::new(supply).size(10.0).color(COL_SUBTEXT));
+                                              let mint = if m["mintable"].as_bool().unwrap_or(false) { "Mintable" } else { "Fixed Supply" };
+                                              ui.label(egui::RichText::new(mint).size(10.0).color(COL_SUBTEXT));
+                                              if let Some(uri) = m["uri"].as_str().filter(|u| !u.is_empty()) {
+                                                  ui.hyperlink_to("Logo / Info", uri);
+                                              }
+                                          });
+                                      }
                                   });
                              }
                          }
@@ -1122,6 +1146,13 @@ impl eframe::App for WalletApp {
 }
 
 // --- Daemon Management ---
+// Atomic units -> display amount using the token's decimals (8 when no metadata)
+fn format_token_amount(amount: i64, meta: Option<&Value>) -> String {
+    let decimals = meta.and_then(|m| m["decimals"].as_u64()).unwrap_or(8) as usize;
+    let val = amount as f64 / 10f64.powi(decimals as i32);
+    format!("{:.*}", decimals, val)
+}
+
 fn spawn_daemon() -> Option<std::process::Child> {
     use std::process::{Command, Stdio};
     